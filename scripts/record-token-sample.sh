@@ -0,0 +1,52 @@
+#!/bin/bash
+
+# 录制本地 token 估算的对照样本
+# 用途：把样本中的 Claude Code /v1/messages 请求体发送到上游 count_tokens 接口，
+#       将上游返回的 input_tokens 写回样本的 upstream_input_tokens 字段
+#
+# 用法：
+#   ANTHROPIC_API_KEY=sk-ant-... ./scripts/record-token-sample.sh <样本.json>...
+#   ANTHROPIC_API_KEY=sk-ant-... ./scripts/record-token-sample.sh src-tauri/src/proxy/testdata/claude_code/*.json
+#
+# 样本格式：{"upstream_input_tokens": null, "request": {...}}
+# 新增样本时，从真实的 Claude Code 流量中抓取请求体（例如用 mitmproxy 记录发往 /v1/messages 的请求），
+# 去除其中的敏感内容后放入 request 字段，再运行本脚本录制上游计数。
+
+set -e  # 遇到错误立即退出
+
+if [ $# -eq 0 ]; then
+    echo "用法: $0 <样本.json>..."
+    exit 1
+fi
+
+API_BASE="${ANTHROPIC_BASE_URL:-https://api.anthropic.com}"
+
+if [ -z "$ANTHROPIC_API_KEY" ]; then
+    echo "请设置 ANTHROPIC_API_KEY 环境变量"
+    exit 1
+fi
+
+if ! command -v jq &> /dev/null; then
+    echo "需要安装 jq"
+    exit 1
+fi
+
+for SAMPLE in "$@"; do
+    # count_tokens 只接受影响输入 token 的字段
+    COUNT_BODY=$(jq '.request | {model, messages, system, tools, tool_choice, thinking} | with_entries(select(.value != null))' "$SAMPLE")
+
+    RESPONSE=$(curl -sS "$API_BASE/v1/messages/count_tokens" \
+        -H "x-api-key: $ANTHROPIC_API_KEY" \
+        -H "anthropic-version: 2023-06-01" \
+        -H "content-type: application/json" \
+        -d "$COUNT_BODY")
+
+    INPUT_TOKENS=$(echo "$RESPONSE" | jq -e '.input_tokens') || {
+        echo "$SAMPLE: count_tokens 请求失败: $RESPONSE"
+        exit 1
+    }
+
+    jq --argjson tokens "$INPUT_TOKENS" '.upstream_input_tokens = $tokens' "$SAMPLE" > "$SAMPLE.tmp"
+    mv "$SAMPLE.tmp" "$SAMPLE"
+    echo "$SAMPLE: input_tokens = $INPUT_TOKENS"
+done
@@ -1,7 +1,12 @@
 // Tauri 命令：配置管理 API
 
-use crate::config::{ConfigManager, MappingRule, ModelMappingMode, Profile};
+use crate::config::{
+    BudgetAction, BudgetMetric, BudgetPeriod, ClientApiKey, ConfigManager, CountTokensMode, HeaderRule, MappingRule, ModelMappingMode, PoolMember,
+    PoolStrategy, Profile, ProfileBudget, ProfilePool, ProfileTimeouts, RetryPolicy, RouteMatchType, RoutingRule, UpstreamAuthScheme, UpstreamNetwork, UpstreamProtocol,
+};
+use crate::db::ModelPrice;
 use crate::logger::RequestLog;
+use crate::proxy::BudgetStatus;
 use std::sync::{Arc, RwLock};
 use tauri::{Manager, State};
 
@@ -18,6 +23,33 @@ pub struct ProfileDto {
     pub model_mapping_mode: ModelMappingMode,
     pub override_model: Option<String>,
     pub model_mappings: Vec<MappingRule>,
+    /// 上游协议（未提供时保留原有设置）
+    #[serde(default)]
+    pub upstream_protocol: Option<UpstreamProtocol>,
+    /// 故障转移链（未提供时保留原有设置）
+    #[serde(default)]
+    pub fallback_profile_ids: Option<Vec<String>>,
+    /// count_tokens 处理方式（未提供时保留原有设置）
+    #[serde(default)]
+    pub count_tokens_mode: Option<CountTokensMode>,
+    /// 向上游传递 API Key 的方式（未提供时保留原有设置）
+    #[serde(default)]
+    pub auth_scheme: Option<UpstreamAuthScheme>,
+    /// 自定义鉴权请求头或查询参数的名称（未提供时保留原有设置）
+    #[serde(default)]
+    pub auth_param: Option<Option<String>>,
+    /// 转发到上游时应用的请求头规则（未提供时保留原有设置）
+    #[serde(default)]
+    pub header_rules: Option<Vec<HeaderRule>>,
+    /// 上游请求的超时设置（未提供时保留原有设置）
+    #[serde(default)]
+    pub timeouts: Option<ProfileTimeouts>,
+    /// 网络设置（出站代理和 TLS），为空时使用全局默认值（未提供时保留原有设置）
+    #[serde(default)]
+    pub network: Option<Option<UpstreamNetwork>>,
+    /// 暂时性失败的重试策略（未提供时保留原有设置）
+    #[serde(default)]
+    pub retry: Option<RetryPolicy>,
 }
 
 impl From<&Profile> for ProfileDto {
@@ -31,6 +63,15 @@ impl From<&Profile> for ProfileDto {
             model_mapping_mode: profile.model_mapping_mode.clone(),
             override_model: profile.override_model.clone(),
             model_mappings: profile.model_mappings.clone(),
+            upstream_protocol: Some(profile.upstream_protocol.clone()),
+            fallback_profile_ids: Some(profile.fallback_profile_ids.clone()),
+            count_tokens_mode: Some(profile.count_tokens_mode.clone()),
+            auth_scheme: Some(profile.auth_scheme.clone()),
+            auth_param: Some(profile.auth_param.clone()),
+            header_rules: Some(profile.header_rules.clone()),
+            timeouts: Some(profile.timeouts.clone()),
+            network: Some(profile.network.clone()),
+            retry: Some(profile.retry.clone()),
         }
     }
 }
@@ -62,6 +103,24 @@ pub struct CreateProfileDto {
     pub model_mapping_mode: ModelMappingMode,
     pub override_model: Option<String>,
     pub model_mappings: Vec<MappingRule>,
+    #[serde(default)]
+    pub upstream_protocol: UpstreamProtocol,
+    #[serde(default)]
+    pub fallback_profile_ids: Vec<String>,
+    #[serde(default)]
+    pub count_tokens_mode: CountTokensMode,
+    #[serde(default)]
+    pub auth_scheme: UpstreamAuthScheme,
+    #[serde(default)]
+    pub auth_param: Option<String>,
+    #[serde(default)]
+    pub header_rules: Vec<HeaderRule>,
+    #[serde(default)]
+    pub timeouts: ProfileTimeouts,
+    #[serde(default)]
+    pub network: Option<UpstreamNetwork>,
+    #[serde(default)]
+    pub retry: RetryPolicy,
 }
 
 #[tauri::command]
@@ -81,6 +140,20 @@ pub fn create_profile(
     new_profile.model_mapping_mode = profile.model_mapping_mode;
     new_profile.override_model = profile.override_model;
     new_profile.model_mappings = profile.model_mappings;
+    new_profile.upstream_protocol = profile.upstream_protocol;
+    new_profile.fallback_profile_ids = profile.fallback_profile_ids;
+    new_profile.count_tokens_mode = profile.count_tokens_mode;
+    new_profile.auth_scheme = profile.auth_scheme;
+    new_profile.auth_param = profile.auth_param;
+    new_profile.header_rules = profile.header_rules;
+    new_profile.timeouts = profile.timeouts;
+    new_profile.network = profile.network;
+
+    if let Some(network) = &new_profile.network {
+        network.validate()?;
+    }
+    new_profile.retry.validate()?;
+    new_profile.retry = profile.retry;
 
     let profile_id = manager.create_profile(new_profile.clone()).map_err(|e| e.to_string())?;
 
@@ -116,9 +189,34 @@ pub fn update_profile(
         model_mapping_mode: profile.model_mapping_mode,
         override_model: profile.override_model,
         model_mappings: profile.model_mappings,
+        upstream_protocol: profile.upstream_protocol
+            .unwrap_or_else(|| existing_profile.upstream_protocol.clone()),
+        fallback_profile_ids: profile.fallback_profile_ids
+            .unwrap_or_else(|| existing_profile.fallback_profile_ids.clone()),
+        count_tokens_mode: profile.count_tokens_mode
+            .unwrap_or_else(|| existing_profile.count_tokens_mode.clone()),
+        auth_scheme: profile.auth_scheme
+            .unwrap_or_else(|| existing_profile.auth_scheme.clone()),
+        auth_param: profile.auth_param
+            .unwrap_or_else(|| existing_profile.auth_param.clone()),
+        header_rules: profile.header_rules
+            .unwrap_or_else(|| existing_profile.header_rules.clone()),
+        timeouts: profile.timeouts
+            .unwrap_or_else(|| existing_profile.timeouts.clone()),
+        network: profile.network
+            .unwrap_or_else(|| existing_profile.network.clone()),
+        retry: profile.retry
+            .unwrap_or_else(|| existing_profile.retry.clone()),
     };
 
+    if let Some(network) = &updated_profile.network {
+        network.validate()?;
+    }
+    updated_profile.retry.validate()?;
+
     manager.update_profile(&id, updated_profile.clone()).map_err(|e| e.to_string())?;
+    // 连接设置可能已变化，下次请求时重新构建该 Profile 的 HTTP 客户端
+    crate::proxy::invalidate_client(&id);
 
     // 异步保存到数据库
     tauri::async_runtime::spawn(async move {
@@ -135,6 +233,7 @@ pub fn delete_profile(config: State<SharedConfigManager>, id: String) -> Result<
     let mut manager = config.write().map_err(|e| e.to_string())?;
 
     manager.delete_profile(&id).map_err(|e| e.to_string())?;
+    crate::proxy::invalidate_client(&id);
 
     // 异步从数据库删除
     let id_clone = id.clone();
@@ -153,17 +252,400 @@ pub fn activate_profile(config: State<SharedConfigManager>, id: String) -> Resul
 
     manager.activate_profile(&id).map_err(|e| e.to_string())?;
 
-    // 异步保存所有 profiles 到数据库（因为需要更新所有的 is_active 状态）
+    // 异步保存所有 profiles 和负载均衡池到数据库（因为需要更新所有的 is_active 状态）
+    save_activation_state(&manager);
+
+    log::info!("Profile activated: {}", id);
+
+    Ok(())
+}
+
+/// 异步保存所有 profiles 和负载均衡池的激活状态
+fn save_activation_state(manager: &ConfigManager) {
     let profiles: Vec<_> = manager.list_profiles().iter().map(|p| (*p).clone()).collect();
+    let pools: Vec<_> = manager.list_pools().iter().map(|p| (*p).clone()).collect();
     tauri::async_runtime::spawn(async move {
         for profile in profiles {
             if let Err(e) = crate::db::save_profile_to_db(&profile).await {
                 log::error!("Failed to save profile to database: {}", e);
             }
         }
+        for pool in pools {
+            if let Err(e) = crate::db::save_pool_to_db(&pool).await {
+                log::error!("Failed to save pool to database: {}", e);
+            }
+        }
     });
+}
 
-    log::info!("Profile activated: {}", id);
+// 负载均衡池相关命令
+
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PoolDto {
+    pub id: String,
+    pub name: String,
+    pub strategy: PoolStrategy,
+    pub members: Vec<PoolMember>,
+    pub is_active: bool,
+}
+
+impl From<&ProfilePool> for PoolDto {
+    fn from(pool: &ProfilePool) -> Self {
+        Self {
+            id: pool.id.clone(),
+            name: pool.name.clone(),
+            strategy: pool.strategy.clone(),
+            members: pool.members.clone(),
+            is_active: pool.is_active,
+        }
+    }
+}
+
+// 创建和更新负载均衡池时使用的 DTO（不需要 id 和 isActive）
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SavePoolDto {
+    pub name: String,
+    #[serde(default)]
+    pub strategy: PoolStrategy,
+    pub members: Vec<PoolMember>,
+}
+
+/// 校验池成员引用的配置都存在
+fn validate_pool_members(manager: &ConfigManager, members: &[PoolMember]) -> Result<(), String> {
+    for member in members {
+        if manager.get_profile(&member.profile_id).is_none() {
+            return Err(format!("Profile not found: {}", member.profile_id));
+        }
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_all_pools(config: State<SharedConfigManager>) -> Result<Vec<PoolDto>, String> {
+    let manager = config.read().map_err(|e| e.to_string())?;
+    Ok(manager.list_pools().into_iter().map(PoolDto::from).collect())
+}
+
+#[tauri::command]
+pub fn create_pool(config: State<SharedConfigManager>, pool: SavePoolDto) -> Result<String, String> {
+    let mut manager = config.write().map_err(|e| e.to_string())?;
+    validate_pool_members(&manager, &pool.members)?;
+
+    let new_pool = ProfilePool::new(pool.name, pool.strategy, pool.members);
+    let pool_id = manager.create_pool(new_pool.clone())?;
+
+    // 异步保存到数据库
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = crate::db::save_pool_to_db(&new_pool).await {
+            log::error!("Failed to save pool to database: {}", e);
+        }
+    });
+
+    Ok(pool_id)
+}
+
+#[tauri::command]
+pub fn update_pool(
+    config: State<SharedConfigManager>,
+    id: String,
+    pool: SavePoolDto,
+) -> Result<(), String> {
+    let mut manager = config.write().map_err(|e| e.to_string())?;
+    validate_pool_members(&manager, &pool.members)?;
+
+    let existing_pool = manager.get_pool(&id)
+        .ok_or_else(|| "Pool not found".to_string())?;
+
+    let updated_pool = ProfilePool {
+        id: id.clone(),
+        name: pool.name,
+        strategy: pool.strategy,
+        members: pool.members,
+        is_active: existing_pool.is_active,
+    };
+
+    manager.update_pool(&id, updated_pool.clone())?;
+
+    // 异步保存到数据库
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = crate::db::save_pool_to_db(&updated_pool).await {
+            log::error!("Failed to save pool to database: {}", e);
+        }
+    });
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn delete_pool(config: State<SharedConfigManager>, id: String) -> Result<(), String> {
+    let mut manager = config.write().map_err(|e| e.to_string())?;
+
+    manager.delete_pool(&id)?;
+
+    // 异步从数据库删除
+    let id_clone = id.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = crate::db::delete_pool_from_db(&id_clone).await {
+            log::error!("Failed to delete pool from database: {}", e);
+        }
+    });
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn activate_pool(config: State<SharedConfigManager>, id: String) -> Result<(), String> {
+    let mut manager = config.write().map_err(|e| e.to_string())?;
+
+    manager.activate_pool(&id)?;
+
+    // 激活池会取消所有配置的激活状态，一并保存
+    save_activation_state(&manager);
+
+    log::info!("Pool activated: {}", id);
+
+    Ok(())
+}
+
+// 模型路由相关命令
+
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RoutingRuleDto {
+    /// 新建的规则不需要 id
+    #[serde(default)]
+    pub id: Option<String>,
+    pub pattern: String,
+    #[serde(default)]
+    pub match_type: RouteMatchType,
+    pub profile_id: String,
+    #[serde(default)]
+    pub strip_prefix: bool,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[tauri::command]
+pub fn get_routing_rules(config: State<SharedConfigManager>) -> Result<Vec<RoutingRuleDto>, String> {
+    let manager = config.read().map_err(|e| e.to_string())?;
+
+    let rules = manager.get_routing_rules()
+        .iter()
+        .map(|rule| RoutingRuleDto {
+            id: Some(rule.id.clone()),
+            pattern: rule.pattern.clone(),
+            match_type: rule.match_type.clone(),
+            profile_id: rule.profile_id.clone(),
+            strip_prefix: rule.strip_prefix,
+            enabled: rule.enabled,
+        })
+        .collect();
+
+    Ok(rules)
+}
+
+/// 整体替换路由表（列表顺序即匹配顺序）
+#[tauri::command]
+pub fn set_routing_rules(
+    config: State<SharedConfigManager>,
+    rules: Vec<RoutingRuleDto>,
+) -> Result<(), String> {
+    let mut manager = config.write().map_err(|e| e.to_string())?;
+
+    let rules: Vec<RoutingRule> = rules
+        .into_iter()
+        .map(|rule| RoutingRule {
+            id: rule.id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
+            pattern: rule.pattern,
+            match_type: rule.match_type,
+            profile_id: rule.profile_id,
+            strip_prefix: rule.strip_prefix,
+            enabled: rule.enabled,
+        })
+        .collect();
+
+    manager.set_routing_rules(rules.clone())?;
+
+    // 异步保存到数据库
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = crate::db::save_routing_rules_to_db(&rules).await {
+            log::error!("Failed to save routing rules to database: {}", e);
+        }
+    });
+
+    Ok(())
+}
+
+// 预算相关命令
+
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileBudgetDto {
+    /// 新建的预算不需要 id
+    #[serde(default)]
+    pub id: Option<String>,
+    pub profile_id: String,
+    #[serde(default)]
+    pub period: BudgetPeriod,
+    #[serde(default)]
+    pub metric: BudgetMetric,
+    pub limit: f64,
+    #[serde(default)]
+    pub action: BudgetAction,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+#[tauri::command]
+pub fn get_budgets(config: State<SharedConfigManager>) -> Result<Vec<ProfileBudgetDto>, String> {
+    let manager = config.read().map_err(|e| e.to_string())?;
+
+    let budgets = manager.get_budgets()
+        .iter()
+        .map(|budget| ProfileBudgetDto {
+            id: Some(budget.id.clone()),
+            profile_id: budget.profile_id.clone(),
+            period: budget.period,
+            metric: budget.metric,
+            limit: budget.limit,
+            action: budget.action,
+            enabled: budget.enabled,
+        })
+        .collect();
+
+    Ok(budgets)
+}
+
+/// 整体替换所有预算
+#[tauri::command]
+pub fn set_budgets(
+    config: State<SharedConfigManager>,
+    budgets: Vec<ProfileBudgetDto>,
+) -> Result<(), String> {
+    let mut manager = config.write().map_err(|e| e.to_string())?;
+
+    let budgets: Vec<ProfileBudget> = budgets
+        .into_iter()
+        .map(|budget| ProfileBudget {
+            id: budget.id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
+            profile_id: budget.profile_id,
+            period: budget.period,
+            metric: budget.metric,
+            limit: budget.limit,
+            action: budget.action,
+            enabled: budget.enabled,
+        })
+        .collect();
+
+    manager.set_budgets(budgets.clone())?;
+
+    // 异步保存到数据库
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = crate::db::save_budgets_to_db(&budgets).await {
+            log::error!("Failed to save budgets to database: {}", e);
+        }
+    });
+
+    Ok(())
+}
+
+/// 获取所有预算在当前周期的用量
+#[tauri::command]
+pub async fn get_budget_status(config: State<'_, SharedConfigManager>) -> Result<Vec<BudgetStatus>, String> {
+    let budgets = {
+        let manager = config.read().map_err(|e| e.to_string())?;
+        manager.get_budgets().to_vec()
+    };
+
+    crate::proxy::get_budget_status(&budgets).await
+}
+
+// 模型价格相关命令
+
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelPriceDto {
+    /// 新建的价格不需要 id
+    #[serde(default)]
+    pub id: Option<String>,
+    #[serde(default)]
+    pub provider: Option<String>,
+    #[serde(default)]
+    pub profile_id: Option<String>,
+    pub model_pattern: String,
+    #[serde(default)]
+    pub match_type: RouteMatchType,
+    /// 以下价格单位均为 美元 / 百万 token
+    #[serde(default)]
+    pub input_price: f64,
+    #[serde(default)]
+    pub output_price: f64,
+    #[serde(default)]
+    pub cache_creation_price: f64,
+    #[serde(default)]
+    pub cache_read_price: f64,
+}
+
+#[tauri::command]
+pub async fn get_model_prices() -> Result<Vec<ModelPriceDto>, String> {
+    let prices = crate::db::load_model_prices_from_db().await?;
+
+    Ok(prices
+        .into_iter()
+        .map(|price| ModelPriceDto {
+            id: Some(price.id),
+            provider: price.provider,
+            profile_id: price.profile_id,
+            model_pattern: price.model_pattern,
+            match_type: price.match_type,
+            input_price: price.input_price,
+            output_price: price.output_price,
+            cache_creation_price: price.cache_creation_price,
+            cache_read_price: price.cache_read_price,
+        })
+        .collect())
+}
+
+/// 整体替换价格表（列表顺序即同优先级下的匹配顺序），只影响之后保存的日志
+#[tauri::command]
+pub async fn set_model_prices(prices: Vec<ModelPriceDto>) -> Result<(), String> {
+    let mut validated = Vec::with_capacity(prices.len());
+
+    for price in prices {
+        if price.model_pattern.trim().is_empty() {
+            return Err("Model pattern cannot be empty".to_string());
+        }
+        if price.match_type == RouteMatchType::Regex {
+            regex::Regex::new(&price.model_pattern)
+                .map_err(|e| format!("Invalid regex pattern '{}': {}", price.model_pattern, e))?;
+        }
+        let rates = [price.input_price, price.output_price, price.cache_creation_price, price.cache_read_price];
+        if rates.iter().any(|rate| !rate.is_finite() || *rate < 0.0) {
+            return Err(format!("Invalid price for '{}': prices must be non-negative", price.model_pattern));
+        }
+
+        validated.push(ModelPrice {
+            id: price.id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
+            // 空字符串视为不限制
+            provider: price.provider.filter(|p| !p.trim().is_empty()),
+            profile_id: price.profile_id.filter(|p| !p.trim().is_empty()),
+            model_pattern: price.model_pattern,
+            match_type: price.match_type,
+            input_price: price.input_price,
+            output_price: price.output_price,
+            cache_creation_price: price.cache_creation_price,
+            cache_read_price: price.cache_read_price,
+        });
+    }
+
+    crate::db::save_model_prices_to_db(&validated).await?;
+    log::info!("Model prices updated: {} entries", validated.len());
 
     Ok(())
 }
@@ -191,6 +673,19 @@ pub struct RequestLogDto {
     pub is_stream: bool,
     pub request_size_bytes: Option<i64>,
     pub response_size_bytes: Option<i64>,
+    pub parent_request_id: Option<String>,
+    pub pool_id: Option<String>,
+    pub endpoint: Option<String>,
+    pub tokenizer: Option<String>,
+    pub token_source: String,
+    pub cost: Option<f64>,
+    pub client_key_id: Option<String>,
+    pub attempts: i32,
+    pub completion_reason: Option<String>,
+    pub error_type: Option<String>,
+    pub outcome: String,
+    pub cache_status: Option<String>,
+    pub cache_source_request_id: Option<String>,
 }
 
 impl From<RequestLog> for RequestLogDto {
@@ -214,6 +709,19 @@ impl From<RequestLog> for RequestLogDto {
             is_stream: log.is_stream,
             request_size_bytes: log.request_size_bytes,
             response_size_bytes: log.response_size_bytes,
+            parent_request_id: log.parent_request_id,
+            pool_id: log.pool_id,
+            endpoint: log.endpoint,
+            tokenizer: log.tokenizer,
+            token_source: log.token_source,
+            cost: log.cost,
+            client_key_id: log.client_key_id,
+            attempts: log.attempts,
+            completion_reason: log.completion_reason,
+            error_type: log.error_type,
+            outcome: log.outcome,
+            cache_status: log.cache_status,
+            cache_source_request_id: log.cache_source_request_id,
         }
     }
 }
@@ -245,6 +753,10 @@ pub struct DashboardStatsDto {
     pub today_tokens: i32,
     pub total_requests: i32,
     pub total_tokens: i32,
+    pub today_estimated_tokens: i32,
+    pub total_estimated_tokens: i32,
+    pub today_cost: f64,
+    pub total_cost: f64,
 }
 
 #[tauri::command]
@@ -256,6 +768,10 @@ pub async fn get_dashboard_stats() -> Result<DashboardStatsDto, String> {
         today_tokens: stats.today_tokens,
         total_requests: stats.total_requests,
         total_tokens: stats.total_tokens,
+        today_estimated_tokens: stats.today_estimated_tokens,
+        total_estimated_tokens: stats.total_estimated_tokens,
+        today_cost: stats.today_cost,
+        total_cost: stats.total_cost,
     })
 }
 
@@ -266,17 +782,22 @@ pub async fn get_dashboard_stats() -> Result<DashboardStatsDto, String> {
 pub struct TokenDataPointDto {
     pub label: String,
     pub tokens: i32,
+    pub cost: f64,
 }
 
 #[tauri::command]
-pub async fn get_token_stats(time_range: String) -> Result<Vec<TokenDataPointDto>, String> {
-    let data_points = crate::db::get_token_stats(&time_range).await?;
+pub async fn get_token_stats(
+    time_range: String,
+    token_source: Option<String>,
+) -> Result<Vec<TokenDataPointDto>, String> {
+    let data_points = crate::db::get_token_stats(&time_range, token_source.as_deref()).await?;
 
     Ok(data_points
         .into_iter()
         .map(|dp| TokenDataPointDto {
             label: dp.label,
             tokens: dp.tokens,
+            cost: dp.cost,
         })
         .collect())
 }
@@ -289,6 +810,9 @@ pub struct ProfileConsumptionDto {
     pub profile_id: String,
     pub profile_name: String,
     pub total_tokens: i32,
+    pub total_cost: f64,
+    pub total_requests: i32,
+    pub total_retries: i32,
     pub percentage: f32,
     pub rank: i32,
 }
@@ -297,9 +821,10 @@ pub struct ProfileConsumptionDto {
 pub async fn get_profile_consumption_ranking(
     time_range: Option<String>,
     limit: Option<i32>,
+    token_source: Option<String>,
 ) -> Result<Vec<ProfileConsumptionDto>, String> {
     let time_range_ref = time_range.as_deref();
-    let rankings = crate::db::get_profile_consumption_ranking(time_range_ref, limit).await?;
+    let rankings = crate::db::get_profile_consumption_ranking(time_range_ref, limit, token_source.as_deref()).await?;
 
     Ok(rankings
         .into_iter()
@@ -307,12 +832,50 @@ pub async fn get_profile_consumption_ranking(
             profile_id: r.profile_id,
             profile_name: r.profile_name,
             total_tokens: r.total_tokens,
+            total_cost: r.total_cost,
+            total_requests: r.total_requests,
+            total_retries: r.total_retries,
             percentage: r.percentage,
             rank: r.rank,
         })
         .collect())
 }
 
+// 客户端 API Key 用量统计相关命令
+
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ClientKeyConsumptionDto {
+    pub client_key_id: Option<String>,
+    pub client_key_name: String,
+    pub total_requests: i32,
+    pub total_tokens: i32,
+    pub total_cost: f64,
+    pub percentage: f32,
+    pub rank: i32,
+}
+
+#[tauri::command]
+pub async fn get_client_key_consumption(
+    time_range: Option<String>,
+    limit: Option<i32>,
+) -> Result<Vec<ClientKeyConsumptionDto>, String> {
+    let consumption = crate::db::get_client_key_consumption(time_range.as_deref(), limit).await?;
+
+    Ok(consumption
+        .into_iter()
+        .map(|c| ClientKeyConsumptionDto {
+            client_key_id: c.client_key_id,
+            client_key_name: c.client_key_name,
+            total_requests: c.total_requests,
+            total_tokens: c.total_tokens,
+            total_cost: c.total_cost,
+            percentage: c.percentage,
+            rank: c.rank,
+        })
+        .collect())
+}
+
 // API Key 管理相关命令
 
 #[tauri::command]
@@ -329,7 +892,7 @@ pub fn refresh_proxy_api_key(config: State<SharedConfigManager>) -> Result<Strin
     // 异步保存到数据库
     let key_clone = new_key.clone();
     tauri::async_runtime::spawn(async move {
-        if let Err(e) = crate::db::save_app_config("proxy_api_key", &key_clone).await {
+        if let Err(e) = crate::db::save_secret_app_config("proxy_api_key", &key_clone).await {
             log::error!("Failed to save proxy API key to database: {}", e);
         }
     });
@@ -338,6 +901,128 @@ pub fn refresh_proxy_api_key(config: State<SharedConfigManager>) -> Result<Strin
     Ok(new_key)
 }
 
+// 客户端 API Key 相关命令
+
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ClientApiKeyDto {
+    pub id: String,
+    pub name: String,
+    pub key: String,
+    pub created_at: i64,
+    pub last_used_at: Option<i64>,
+    pub expires_at: Option<i64>,
+    pub revoked: bool,
+}
+
+impl From<&ClientApiKey> for ClientApiKeyDto {
+    fn from(client_key: &ClientApiKey) -> Self {
+        Self {
+            id: client_key.id.clone(),
+            name: client_key.name.clone(),
+            key: client_key.key.clone(),
+            created_at: client_key.created_at,
+            last_used_at: client_key.last_used_at,
+            expires_at: client_key.expires_at,
+            revoked: client_key.revoked,
+        }
+    }
+}
+
+/// 保存客户端 API Key 到数据库（后台执行）
+fn spawn_save_client_key(client_key: ClientApiKey) {
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = crate::db::save_client_key_to_db(&client_key).await {
+            log::error!("Failed to save client key to database: {}", e);
+        }
+    });
+}
+
+#[tauri::command]
+pub fn get_client_keys(config: State<SharedConfigManager>) -> Result<Vec<ClientApiKeyDto>, String> {
+    let manager = config.read().map_err(|e| e.to_string())?;
+
+    let mut client_keys: Vec<ClientApiKeyDto> = manager.list_client_keys()
+        .into_iter()
+        .map(ClientApiKeyDto::from)
+        .collect();
+    client_keys.sort_by_key(|k| k.created_at);
+
+    Ok(client_keys)
+}
+
+#[tauri::command]
+pub fn create_client_key(
+    config: State<SharedConfigManager>,
+    name: String,
+    expires_at: Option<i64>,
+) -> Result<ClientApiKeyDto, String> {
+    let mut manager = config.write().map_err(|e| e.to_string())?;
+
+    let client_key = ClientApiKey::new(name.trim().to_string(), expires_at);
+    manager.create_client_key(client_key.clone())?;
+
+    log::info!("Client API key created: {}", client_key.name);
+    let dto = ClientApiKeyDto::from(&client_key);
+    spawn_save_client_key(client_key);
+
+    Ok(dto)
+}
+
+/// 修改客户端 API Key 的名称和过期时间
+#[tauri::command]
+pub fn update_client_key(
+    config: State<SharedConfigManager>,
+    id: String,
+    name: String,
+    expires_at: Option<i64>,
+) -> Result<(), String> {
+    let mut manager = config.write().map_err(|e| e.to_string())?;
+
+    let mut client_key = manager.get_client_key(&id)
+        .cloned()
+        .ok_or("Client key not found")?;
+    if name.trim().is_empty() {
+        return Err("Key name cannot be empty".to_string());
+    }
+    client_key.name = name.trim().to_string();
+    client_key.expires_at = expires_at;
+    manager.update_client_key(client_key.clone())?;
+
+    spawn_save_client_key(client_key);
+    Ok(())
+}
+
+/// 吊销客户端 API Key（保留记录以便统计，之后使用该密钥的请求会被拒绝）
+#[tauri::command]
+pub fn revoke_client_key(config: State<SharedConfigManager>, id: String) -> Result<(), String> {
+    let mut manager = config.write().map_err(|e| e.to_string())?;
+
+    let mut client_key = manager.get_client_key(&id)
+        .cloned()
+        .ok_or("Client key not found")?;
+    client_key.revoked = true;
+    manager.update_client_key(client_key.clone())?;
+
+    log::info!("Client API key revoked: {}", client_key.name);
+    spawn_save_client_key(client_key);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn delete_client_key(config: State<SharedConfigManager>, id: String) -> Result<(), String> {
+    let mut manager = config.write().map_err(|e| e.to_string())?;
+    manager.delete_client_key(&id)?;
+
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = crate::db::delete_client_key_from_db(&id).await {
+            log::error!("Failed to delete client key from database: {}", e);
+        }
+    });
+
+    Ok(())
+}
+
 #[tauri::command]
 pub fn get_auth_enabled(config: State<SharedConfigManager>) -> Result<bool, String> {
     let manager = config.read().map_err(|e| e.to_string())?;
@@ -401,6 +1086,7 @@ pub async fn set_proxy_config(
 ) -> Result<(), String> {
     // 验证配置
     config.validate()?;
+    config.upstream_network.validate()?;
 
     // 保存到数据库
     crate::db::save_proxy_config(&config).await?;
@@ -426,6 +1112,7 @@ pub async fn restart_proxy_server(
 ) -> Result<String, String> {
     // 验证配置
     proxy_config.validate()?;
+    proxy_config.upstream_network.validate()?;
 
     // 保存配置
     crate::db::save_proxy_config(&proxy_config).await?;
@@ -56,6 +56,258 @@ impl ModelMappingMode {
     }
 }
 
+/// 上游 API 协议
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum UpstreamProtocol {
+    /// Anthropic Messages API：请求原样转发到 /v1/messages
+    #[default]
+    Anthropic,
+    /// OpenAI Chat Completions API：请求和响应在两种格式之间转换
+    OpenAI,
+}
+
+impl UpstreamProtocol {
+    pub fn as_str(&self) -> &str {
+        match self {
+            UpstreamProtocol::Anthropic => "anthropic",
+            UpstreamProtocol::OpenAI => "openai",
+        }
+    }
+}
+
+impl From<&str> for UpstreamProtocol {
+    fn from(s: &str) -> Self {
+        match s {
+            "openai" => UpstreamProtocol::OpenAI,
+            _ => UpstreamProtocol::Anthropic,
+        }
+    }
+}
+
+/// /v1/messages/count_tokens 的处理方式
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum CountTokensMode {
+    /// 转发到上游，上游返回 404/501 时在本地计算
+    #[default]
+    Auto,
+    /// 始终转发到上游
+    Upstream,
+    /// 始终在本地计算（上游不支持 count_tokens）
+    Local,
+}
+
+impl CountTokensMode {
+    pub fn as_str(&self) -> &str {
+        match self {
+            CountTokensMode::Auto => "auto",
+            CountTokensMode::Upstream => "upstream",
+            CountTokensMode::Local => "local",
+        }
+    }
+}
+
+impl From<&str> for CountTokensMode {
+    fn from(s: &str) -> Self {
+        match s {
+            "upstream" => CountTokensMode::Upstream,
+            "local" => CountTokensMode::Local,
+            _ => CountTokensMode::Auto,
+        }
+    }
+}
+
+/// 向上游传递 API Key 的方式
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum UpstreamAuthScheme {
+    /// Authorization: Bearer {api_key}
+    #[default]
+    Bearer,
+    /// x-api-key: {api_key}（Anthropic 官方 API，同时补充 anthropic-version）
+    XApiKey,
+    /// 自定义请求头 {auth_param}: {api_key}（默认 api-key，如 Azure）
+    Header,
+    /// 查询参数 ?{auth_param}={api_key}（默认 key）
+    Query,
+    /// 不发送 API Key（由自定义请求头规则处理鉴权）
+    None,
+}
+
+impl UpstreamAuthScheme {
+    pub fn as_str(&self) -> &str {
+        match self {
+            UpstreamAuthScheme::Bearer => "bearer",
+            UpstreamAuthScheme::XApiKey => "x_api_key",
+            UpstreamAuthScheme::Header => "header",
+            UpstreamAuthScheme::Query => "query",
+            UpstreamAuthScheme::None => "none",
+        }
+    }
+}
+
+impl From<&str> for UpstreamAuthScheme {
+    fn from(s: &str) -> Self {
+        match s {
+            "x_api_key" => UpstreamAuthScheme::XApiKey,
+            "header" => UpstreamAuthScheme::Header,
+            "query" => UpstreamAuthScheme::Query,
+            "none" => UpstreamAuthScheme::None,
+            _ => UpstreamAuthScheme::Bearer,
+        }
+    }
+}
+
+/// 请求头规则的操作
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum HeaderAction {
+    /// 请求中没有该头时添加
+    Add,
+    /// 总是设置为指定值（覆盖客户端发送的值）
+    #[default]
+    Override,
+    /// 移除该头
+    Remove,
+}
+
+impl HeaderAction {
+    pub fn as_str(&self) -> &str {
+        match self {
+            HeaderAction::Add => "add",
+            HeaderAction::Override => "override",
+            HeaderAction::Remove => "remove",
+        }
+    }
+}
+
+impl From<&str> for HeaderAction {
+    fn from(s: &str) -> Self {
+        match s {
+            "add" => HeaderAction::Add,
+            "remove" => HeaderAction::Remove,
+            _ => HeaderAction::Override,
+        }
+    }
+}
+
+/// 转发到上游时的请求头规则（如 OpenRouter 的 HTTP-Referer、X-Title）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HeaderRule {
+    #[serde(default)]
+    pub action: HeaderAction,
+    /// 请求头名称
+    pub name: String,
+    /// 请求头的值（移除时忽略）
+    #[serde(default)]
+    pub value: String,
+}
+
+/// 上游请求的超时设置（秒）
+///
+/// 未设置时使用默认值（流式与非流式请求不同），设置为 0 表示不限制。
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileTimeouts {
+    /// 建立连接的超时（默认 10 秒）
+    #[serde(default)]
+    pub connect_secs: Option<u64>,
+    /// 发出请求到收到响应头的超时（默认流式 120 秒，非流式 600 秒）
+    #[serde(default)]
+    pub first_byte_secs: Option<u64>,
+    /// 流式响应两个数据块之间的最长间隔（默认 120 秒）
+    #[serde(default)]
+    pub idle_secs: Option<u64>,
+    /// 整个请求的超时（默认流式不限制，非流式 600 秒）
+    #[serde(default)]
+    pub total_secs: Option<u64>,
+}
+
+/// 上游暂时性失败（连接失败、429、5xx、529）时的重试策略
+///
+/// 只在向客户端返回任何数据之前重试；用尽后再按故障转移链切换 Profile。
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RetryPolicy {
+    /// 最大尝试次数（含首次请求，1 表示不重试）
+    pub max_attempts: u32,
+    /// 首次重试前的等待时间（毫秒），之后每次翻倍
+    pub base_delay_ms: u64,
+    /// 单次等待的上限（毫秒）
+    pub max_delay_ms: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay_ms: 500,
+            max_delay_ms: 8_000,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// 验证重试策略
+    pub fn validate(&self) -> Result<(), String> {
+        if !(1..=10).contains(&self.max_attempts) {
+            return Err("Retry max attempts must be between 1 and 10".to_string());
+        }
+        if self.max_delay_ms < self.base_delay_ms {
+            return Err("Retry max delay must not be less than base delay".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// 访问上游时的网络设置（出站代理和 TLS）
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct UpstreamNetwork {
+    /// 出站代理地址（http://、https://、socks5:// 或 socks5h://），为空表示直连
+    #[serde(default)]
+    pub proxy_url: Option<String>,
+    #[serde(default)]
+    pub proxy_username: Option<String>,
+    #[serde(default)]
+    pub proxy_password: Option<String>,
+    /// 不经过代理的主机（逗号分隔，格式同 NO_PROXY 环境变量）
+    #[serde(default)]
+    pub no_proxy: Option<String>,
+    /// 额外信任的 CA 证书文件（PEM 格式，用于内部网关）
+    #[serde(default)]
+    pub ca_cert_path: Option<String>,
+    /// 跳过上游 TLS 证书校验（仅用于内部网关）
+    #[serde(default)]
+    pub tls_skip_verify: bool,
+}
+
+impl UpstreamNetwork {
+    /// 有效的代理地址（去掉空白，空字符串视为未设置）
+    pub fn proxy_url(&self) -> Option<&str> {
+        self.proxy_url.as_deref().map(str::trim).filter(|url| !url.is_empty())
+    }
+
+    /// 验证代理地址和证书文件
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(url) = self.proxy_url() {
+            let parsed = reqwest::Url::parse(url)
+                .map_err(|e| format!("Invalid proxy URL {}: {}", url, e))?;
+            if !matches!(parsed.scheme(), "http" | "https" | "socks5" | "socks5h") {
+                return Err(format!("Unsupported proxy scheme: {}", parsed.scheme()));
+            }
+        }
+        if let Some(path) = self.ca_cert_path.as_deref().filter(|p| !p.is_empty()) {
+            if !std::path::Path::new(path).is_file() {
+                return Err(format!("CA certificate file not found: {}", path));
+            }
+        }
+        Ok(())
+    }
+}
+
 /// API 配置档案
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Profile {
@@ -79,9 +331,46 @@ pub struct Profile {
     /// 映射模式使用的映射规则列表
     #[serde(default)]
     pub model_mappings: Vec<MappingRule>,
+
+    /// 上游 API 协议
+    #[serde(default)]
+    pub upstream_protocol: UpstreamProtocol,
+    /// 故障转移链：当前配置不可用时按顺序尝试的备用配置 ID
+    #[serde(default)]
+    pub fallback_profile_ids: Vec<String>,
+    /// count_tokens 请求的处理方式
+    #[serde(default)]
+    pub count_tokens_mode: CountTokensMode,
+
+    /// 向上游传递 API Key 的方式
+    #[serde(default)]
+    pub auth_scheme: UpstreamAuthScheme,
+    /// 自定义鉴权请求头或查询参数的名称（为空时使用默认名称）
+    #[serde(default)]
+    pub auth_param: Option<String>,
+    /// 转发到上游时按顺序应用的请求头规则
+    #[serde(default)]
+    pub header_rules: Vec<HeaderRule>,
+
+    /// 上游请求的超时设置
+    #[serde(default)]
+    pub timeouts: ProfileTimeouts,
+
+    /// 网络设置（出站代理和 TLS），为空时使用代理服务器配置中的全局默认值
+    #[serde(default)]
+    pub network: Option<UpstreamNetwork>,
+
+    /// 暂时性失败的重试策略
+    #[serde(default)]
+    pub retry: RetryPolicy,
 }
 
 impl Profile {
+    /// API Key 无法解密（密钥文件丢失或被替换），内存中保留的是原始密文
+    pub fn is_api_key_locked(&self) -> bool {
+        crate::db::is_encrypted(&self.api_key)
+    }
+
     pub fn new(name: String, api_base_url: String, api_key: String) -> Self {
         Self {
             id: uuid::Uuid::new_v4().to_string(),
@@ -92,6 +381,15 @@ impl Profile {
             model_mapping_mode: ModelMappingMode::Passthrough,
             override_model: None,
             model_mappings: Vec::new(),
+            upstream_protocol: UpstreamProtocol::Anthropic,
+            fallback_profile_ids: Vec::new(),
+            count_tokens_mode: CountTokensMode::Auto,
+            auth_scheme: UpstreamAuthScheme::Bearer,
+            auth_param: None,
+            header_rules: Vec::new(),
+            timeouts: ProfileTimeouts::default(),
+            network: None,
+            retry: RetryPolicy::default(),
         }
     }
 
@@ -134,11 +432,355 @@ impl Profile {
     }
 }
 
+/// 负载均衡策略
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PoolStrategy {
+    /// 加权轮询：按权重比例分配请求
+    #[default]
+    WeightedRoundRobin,
+    /// 最少并发：选择当前进行中请求最少的成员
+    LeastInFlight,
+    /// 最低延迟：选择最近响应延迟最低的成员
+    LowestLatency,
+}
+
+impl PoolStrategy {
+    pub fn as_str(&self) -> &str {
+        match self {
+            PoolStrategy::WeightedRoundRobin => "weighted_round_robin",
+            PoolStrategy::LeastInFlight => "least_in_flight",
+            PoolStrategy::LowestLatency => "lowest_latency",
+        }
+    }
+}
+
+impl From<&str> for PoolStrategy {
+    fn from(s: &str) -> Self {
+        match s {
+            "least_in_flight" => PoolStrategy::LeastInFlight,
+            "lowest_latency" => PoolStrategy::LowestLatency,
+            _ => PoolStrategy::WeightedRoundRobin,
+        }
+    }
+}
+
+/// 负载均衡池成员
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PoolMember {
+    /// 成员配置 ID
+    pub profile_id: String,
+    /// 权重（仅加权轮询使用）
+    #[serde(default = "default_pool_weight")]
+    pub weight: u32,
+}
+
+fn default_pool_weight() -> u32 {
+    1
+}
+
+/// 负载均衡池：一组按策略分配流量的配置档案
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfilePool {
+    /// 池 ID
+    pub id: String,
+    /// 池名称
+    pub name: String,
+    /// 负载均衡策略
+    #[serde(default)]
+    pub strategy: PoolStrategy,
+    /// 成员列表
+    #[serde(default)]
+    pub members: Vec<PoolMember>,
+    /// 是否激活（与配置档案互斥）
+    #[serde(default)]
+    pub is_active: bool,
+}
+
+impl ProfilePool {
+    pub fn new(name: String, strategy: PoolStrategy, members: Vec<PoolMember>) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            name,
+            strategy,
+            members,
+            is_active: false,
+        }
+    }
+}
+
+/// 路由规则的匹配方式
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum RouteMatchType {
+    /// 精确匹配模型名称
+    #[default]
+    Exact,
+    /// 匹配模型名称前缀（如 "task:frontend/"）
+    Prefix,
+    /// 正则表达式匹配
+    Regex,
+}
+
+impl RouteMatchType {
+    pub fn as_str(&self) -> &str {
+        match self {
+            RouteMatchType::Exact => "exact",
+            RouteMatchType::Prefix => "prefix",
+            RouteMatchType::Regex => "regex",
+        }
+    }
+}
+
+impl From<&str> for RouteMatchType {
+    fn from(s: &str) -> Self {
+        match s {
+            "prefix" => RouteMatchType::Prefix,
+            "regex" => RouteMatchType::Regex,
+            _ => RouteMatchType::Exact,
+        }
+    }
+}
+
+fn default_route_enabled() -> bool {
+    true
+}
+
+/// 模型路由规则：请求的模型匹配时转发到指定配置，而不是激活的配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoutingRule {
+    /// 规则 ID
+    pub id: String,
+    /// 匹配模式
+    pub pattern: String,
+    /// 匹配方式
+    #[serde(default)]
+    pub match_type: RouteMatchType,
+    /// 目标配置 ID
+    pub profile_id: String,
+    /// 前缀匹配时是否从模型名称中去掉前缀再转发（如 "task:frontend/claude-sonnet-4" → "claude-sonnet-4"）
+    #[serde(default)]
+    pub strip_prefix: bool,
+    /// 是否启用
+    #[serde(default = "default_route_enabled")]
+    pub enabled: bool,
+}
+
+impl RoutingRule {
+    /// 匹配模型名称，命中时返回路由后的模型名称
+    pub fn matches(&self, model: &str) -> Option<String> {
+        if !self.enabled {
+            return None;
+        }
+
+        match self.match_type {
+            RouteMatchType::Exact => (self.pattern == model).then(|| model.to_string()),
+            RouteMatchType::Prefix => {
+                let rest = model.strip_prefix(self.pattern.as_str())?;
+                if self.strip_prefix && !rest.is_empty() {
+                    Some(rest.to_string())
+                } else {
+                    Some(model.to_string())
+                }
+            }
+            RouteMatchType::Regex => match regex::Regex::new(&self.pattern) {
+                Ok(re) => re.is_match(model).then(|| model.to_string()),
+                Err(_) => {
+                    log::warn!("Invalid regex pattern: {}", self.pattern);
+                    None
+                }
+            },
+        }
+    }
+}
+
+/// 预算周期（按本地时间的自然日/周/月计算）
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum BudgetPeriod {
+    #[default]
+    Daily,
+    /// 从周一开始
+    Weekly,
+    Monthly,
+}
+
+impl BudgetPeriod {
+    pub fn as_str(&self) -> &str {
+        match self {
+            BudgetPeriod::Daily => "daily",
+            BudgetPeriod::Weekly => "weekly",
+            BudgetPeriod::Monthly => "monthly",
+        }
+    }
+}
+
+impl From<&str> for BudgetPeriod {
+    fn from(s: &str) -> Self {
+        match s {
+            "weekly" => BudgetPeriod::Weekly,
+            "monthly" => BudgetPeriod::Monthly,
+            _ => BudgetPeriod::Daily,
+        }
+    }
+}
+
+/// 预算的计量方式
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum BudgetMetric {
+    /// 按 token 总数（输入 + 输出 + 缓存）
+    #[default]
+    Tokens,
+    /// 按价格表估算的成本（美元）
+    Cost,
+}
+
+impl BudgetMetric {
+    pub fn as_str(&self) -> &str {
+        match self {
+            BudgetMetric::Tokens => "tokens",
+            BudgetMetric::Cost => "cost",
+        }
+    }
+}
+
+impl From<&str> for BudgetMetric {
+    fn from(s: &str) -> Self {
+        match s {
+            "cost" => BudgetMetric::Cost,
+            _ => BudgetMetric::Tokens,
+        }
+    }
+}
+
+/// 超出预算后的处理方式
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum BudgetAction {
+    /// 仅记录警告并通知前端，继续使用该配置
+    #[default]
+    Warn,
+    /// 跳过该配置，使用故障转移链中的下一个配置
+    Failover,
+    /// 拒绝请求（429 overloaded_error）
+    Reject,
+}
+
+impl BudgetAction {
+    pub fn as_str(&self) -> &str {
+        match self {
+            BudgetAction::Warn => "warn",
+            BudgetAction::Failover => "failover",
+            BudgetAction::Reject => "reject",
+        }
+    }
+}
+
+impl From<&str> for BudgetAction {
+    fn from(s: &str) -> Self {
+        match s {
+            "failover" => BudgetAction::Failover,
+            "reject" => BudgetAction::Reject,
+            _ => BudgetAction::Warn,
+        }
+    }
+}
+
+/// 配置的用量预算
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileBudget {
+    /// 预算 ID
+    pub id: String,
+    /// 所属配置 ID
+    pub profile_id: String,
+    #[serde(default)]
+    pub period: BudgetPeriod,
+    #[serde(default)]
+    pub metric: BudgetMetric,
+    /// 上限（token 数或美元）
+    pub limit: f64,
+    #[serde(default)]
+    pub action: BudgetAction,
+    /// 是否启用
+    #[serde(default = "default_route_enabled")]
+    pub enabled: bool,
+}
+
+/// 内置代理 API Key（proxy_api_key）在日志中记录的客户端密钥 ID
+pub const DEFAULT_CLIENT_KEY_ID: &str = "default";
+
+/// 最近使用时间的刷新间隔（毫秒），避免每个请求都写数据库
+const CLIENT_KEY_TOUCH_INTERVAL_MS: i64 = 60_000;
+
+/// 命名的客户端 API Key（区分共享代理的不同成员或工具）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClientApiKey {
+    /// 密钥 ID
+    pub id: String,
+    /// 名称（如使用者或工具名）
+    pub name: String,
+    /// 密钥内容
+    pub key: String,
+    /// 创建时间（毫秒时间戳）
+    pub created_at: i64,
+    /// 最近使用时间（毫秒时间戳）
+    #[serde(default)]
+    pub last_used_at: Option<i64>,
+    /// 过期时间（毫秒时间戳），为空表示永不过期
+    #[serde(default)]
+    pub expires_at: Option<i64>,
+    /// 是否已吊销
+    #[serde(default)]
+    pub revoked: bool,
+}
+
+impl ClientApiKey {
+    pub fn new(name: String, expires_at: Option<i64>) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            name,
+            key: ConfigManager::generate_api_key(),
+            created_at: chrono::Utc::now().timestamp_millis(),
+            last_used_at: None,
+            expires_at,
+            revoked: false,
+        }
+    }
+
+    /// 检查密钥当前是否可用，不可用时返回原因
+    pub fn check_usable(&self, now: i64) -> Result<(), &'static str> {
+        if self.revoked {
+            return Err("API key has been revoked");
+        }
+        if self.expires_at.is_some_and(|expires_at| expires_at <= now) {
+            return Err("API key has expired");
+        }
+        Ok(())
+    }
+}
+
 /// 配置管理器
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConfigManager {
     /// 所有配置档案
     profiles: HashMap<String, Profile>,
+    /// 所有负载均衡池
+    #[serde(default)]
+    pools: HashMap<String, ProfilePool>,
+    /// 模型路由表（按顺序匹配，第一条命中的规则生效）
+    #[serde(default)]
+    routing_rules: Vec<RoutingRule>,
+    /// 配置的用量预算
+    #[serde(default)]
+    budgets: Vec<ProfileBudget>,
+    /// 命名的客户端 API Key
+    #[serde(default)]
+    client_keys: HashMap<String, ClientApiKey>,
     /// 代理服务 API Key
     #[serde(default)]
     pub proxy_api_key: Option<String>,
@@ -151,6 +793,10 @@ impl ConfigManager {
     pub fn new() -> Self {
         Self {
             profiles: HashMap::new(),
+            pools: HashMap::new(),
+            routing_rules: Vec::new(),
+            budgets: Vec::new(),
+            client_keys: HashMap::new(),
             proxy_api_key: None,
             enable_auth: false,
         }
@@ -190,18 +836,94 @@ impl ConfigManager {
         self.enable_auth
     }
 
-    /// 验证 API Key
-    pub fn verify_api_key(&self, key: &str) -> bool {
-        if !self.enable_auth {
-            return true; // 未启用授权时，所有请求都通过
+    /// 验证 API Key，通过时返回对应的客户端密钥 ID（内置密钥为 DEFAULT_CLIENT_KEY_ID）
+    ///
+    /// 未启用访问授权时所有请求都通过，但仍会识别携带的密钥用于统计；无法识别时返回 None。
+    /// 密钥使用常量时间比较，且总是与所有密钥逐一比较，避免通过响应时间猜测密钥。
+    pub fn verify_api_key(&self, key: &str, now: i64) -> Result<Option<String>, String> {
+        let mut matched = None;
+        for client_key in self.client_keys.values() {
+            if constant_time_eq(client_key.key.as_bytes(), key.as_bytes()) {
+                matched = Some(client_key);
+            }
+        }
+        let builtin_matched = self.proxy_api_key.as_deref()
+            .is_some_and(|stored_key| constant_time_eq(stored_key.as_bytes(), key.as_bytes()));
+
+        let result = if let Some(client_key) = matched {
+            client_key.check_usable(now)
+                .map(|_| Some(client_key.id.clone()))
+                .map_err(|reason| reason.to_string())
+        } else if builtin_matched {
+            Ok(Some(DEFAULT_CLIENT_KEY_ID.to_string()))
+        } else {
+            Err("Invalid API key".to_string())
+        };
+
+        match result {
+            Err(_) if !self.enable_auth => Ok(None), // 未启用授权时，所有请求都通过
+            result => result,
         }
+    }
+
+    /// 获取所有客户端 API Key
+    pub fn list_client_keys(&self) -> Vec<&ClientApiKey> {
+        self.client_keys.values().collect()
+    }
+
+    /// 获取客户端 API Key
+    pub fn get_client_key(&self, id: &str) -> Option<&ClientApiKey> {
+        self.client_keys.get(id)
+    }
+
+    /// 新增客户端 API Key
+    pub fn create_client_key(&mut self, client_key: ClientApiKey) -> Result<String, String> {
+        if client_key.name.trim().is_empty() {
+            return Err("Key name cannot be empty".to_string());
+        }
+        let id = client_key.id.clone();
+        if self.client_keys.contains_key(&id) {
+            return Err("Client key already exists".to_string());
+        }
+        self.client_keys.insert(id.clone(), client_key);
+        Ok(id)
+    }
+
+    /// 更新客户端 API Key（名称、过期时间、吊销状态）
+    pub fn update_client_key(&mut self, client_key: ClientApiKey) -> Result<(), String> {
+        if !self.client_keys.contains_key(&client_key.id) {
+            return Err("Client key not found".to_string());
+        }
+        self.client_keys.insert(client_key.id.clone(), client_key);
+        Ok(())
+    }
+
+    /// 删除客户端 API Key
+    pub fn delete_client_key(&mut self, id: &str) -> Result<(), String> {
+        self.client_keys.remove(id)
+            .map(|_| ())
+            .ok_or_else(|| "Client key not found".to_string())
+    }
 
-        match &self.proxy_api_key {
-            Some(stored_key) => stored_key == key,
-            None => false,
+    /// 记录客户端 API Key 的使用时间，距上次记录不足刷新间隔时跳过
+    ///
+    /// 返回 true 表示时间已更新，需要保存到数据库。
+    pub fn touch_client_key(&mut self, id: &str, now: i64) -> bool {
+        match self.client_keys.get_mut(id) {
+            Some(client_key) if client_key.last_used_at.map_or(true, |t| now - t >= CLIENT_KEY_TOUCH_INTERVAL_MS) => {
+                client_key.last_used_at = Some(now);
+                true
+            }
+            _ => false,
         }
     }
 
+    /// 客户端 API Key 的最近使用时间是否需要刷新
+    pub fn client_key_needs_touch(&self, id: &str, now: i64) -> bool {
+        self.client_keys.get(id)
+            .is_some_and(|k| k.last_used_at.map_or(true, |t| now - t >= CLIENT_KEY_TOUCH_INTERVAL_MS))
+    }
+
     /// 创建新配置
     pub fn create_profile(&mut self, profile: Profile) -> Result<String, String> {
         let id = profile.id.clone();
@@ -231,6 +953,8 @@ impl ConfigManager {
         if self.profiles.remove(id).is_none() {
             return Err("Profile not found".to_string());
         }
+        self.routing_rules.retain(|rule| rule.profile_id != id);
+        self.budgets.retain(|budget| budget.profile_id != id);
         Ok(())
     }
 
@@ -277,7 +1001,7 @@ impl ConfigManager {
             profiles_map.insert(profile.id.clone(), profile);
         }
 
-        let proxy_api_key = crate::db::load_app_config("proxy_api_key").await?;
+        let proxy_api_key = crate::db::load_secret_app_config("proxy_api_key").await?;
         let enable_auth = crate::db::load_app_config("enable_auth")
             .await?
             .map(|v| v == "true")
@@ -290,15 +1014,32 @@ impl ConfigManager {
                 let new_key = Self::generate_api_key();
                 log::info!("Generated new proxy API key on first initialization");
                 // 保存到数据库
-                if let Err(e) = crate::db::save_app_config("proxy_api_key", &new_key).await {
+                if let Err(e) = crate::db::save_secret_app_config("proxy_api_key", &new_key).await {
                     log::error!("Failed to save generated proxy API key: {}", e);
                 }
                 Some(new_key)
             }
         };
 
+        let pools = crate::db::load_pools_from_db().await?;
+        let pools_map = pools.into_iter()
+            .map(|pool| (pool.id.clone(), pool))
+            .collect();
+
+        let routing_rules = crate::db::load_routing_rules_from_db().await?;
+        let budgets = crate::db::load_budgets_from_db().await?;
+
+        let client_keys = crate::db::load_client_keys_from_db().await?;
+        let client_keys_map = client_keys.into_iter()
+            .map(|client_key| (client_key.id.clone(), client_key))
+            .collect();
+
         Ok(Self {
             profiles: profiles_map,
+            pools: pools_map,
+            routing_rules,
+            budgets,
+            client_keys: client_keys_map,
             proxy_api_key,
             enable_auth,
         })
@@ -311,9 +1052,25 @@ impl ConfigManager {
             crate::db::save_profile_to_db(profile).await?;
         }
 
+        // 保存所有负载均衡池
+        for pool in self.pools.values() {
+            crate::db::save_pool_to_db(pool).await?;
+        }
+
+        // 保存模型路由表
+        crate::db::save_routing_rules_to_db(&self.routing_rules).await?;
+
+        // 保存预算
+        crate::db::save_budgets_to_db(&self.budgets).await?;
+
+        // 保存客户端 API Key
+        for client_key in self.client_keys.values() {
+            crate::db::save_client_key_to_db(client_key).await?;
+        }
+
         // 保存应用配置
         if let Some(key) = &self.proxy_api_key {
-            crate::db::save_app_config("proxy_api_key", key).await?;
+            crate::db::save_secret_app_config("proxy_api_key", key).await?;
         }
         crate::db::save_app_config("enable_auth", &self.enable_auth.to_string()).await?;
 
@@ -326,10 +1083,13 @@ impl ConfigManager {
             return Err("Profile not found".to_string());
         }
 
-        // 先将所有配置设为非激活
+        // 先将所有配置和池设为非激活
         for profile in self.profiles.values_mut() {
             profile.is_active = false;
         }
+        for pool in self.pools.values_mut() {
+            pool.is_active = false;
+        }
 
         // 激活指定配置
         if let Some(profile) = self.profiles.get_mut(id) {
@@ -343,6 +1103,151 @@ impl ConfigManager {
     pub fn get_active_profile(&self) -> Option<&Profile> {
         self.profiles.values().find(|p| p.is_active)
     }
+
+    /// 获取故障转移链：激活的配置在前，随后是其备用配置（跳过不存在或重复的配置）
+    pub fn get_profile_chain(&self) -> Vec<Profile> {
+        match self.get_active_profile() {
+            Some(profile) => self.get_chain_for(&profile.id),
+            None => Vec::new(),
+        }
+    }
+
+    /// 获取指定配置的故障转移链
+    pub fn get_chain_for(&self, id: &str) -> Vec<Profile> {
+        let head = match self.profiles.get(id) {
+            Some(profile) => profile,
+            None => return Vec::new(),
+        };
+
+        let mut chain = vec![head.clone()];
+        for id in &head.fallback_profile_ids {
+            if chain.iter().any(|p| &p.id == id) {
+                continue;
+            }
+            match self.profiles.get(id) {
+                Some(profile) => chain.push(profile.clone()),
+                None => log::warn!("Fallback profile not found: {}", id),
+            }
+        }
+        chain
+    }
+
+    /// 创建负载均衡池
+    pub fn create_pool(&mut self, pool: ProfilePool) -> Result<String, String> {
+        let id = pool.id.clone();
+        if self.pools.contains_key(&id) {
+            return Err("Pool already exists".to_string());
+        }
+        self.pools.insert(id.clone(), pool);
+        Ok(id)
+    }
+
+    /// 获取负载均衡池
+    pub fn get_pool(&self, id: &str) -> Option<&ProfilePool> {
+        self.pools.get(id)
+    }
+
+    /// 更新负载均衡池
+    pub fn update_pool(&mut self, id: &str, pool: ProfilePool) -> Result<(), String> {
+        if !self.pools.contains_key(id) {
+            return Err("Pool not found".to_string());
+        }
+        self.pools.insert(id.to_string(), pool);
+        Ok(())
+    }
+
+    /// 删除负载均衡池
+    pub fn delete_pool(&mut self, id: &str) -> Result<(), String> {
+        if self.pools.remove(id).is_none() {
+            return Err("Pool not found".to_string());
+        }
+        Ok(())
+    }
+
+    /// 获取所有负载均衡池
+    pub fn list_pools(&self) -> Vec<&ProfilePool> {
+        self.pools.values().collect()
+    }
+
+    /// 激活负载均衡池（与配置档案互斥，激活后所有配置档案变为非激活）
+    pub fn activate_pool(&mut self, id: &str) -> Result<(), String> {
+        if !self.pools.contains_key(id) {
+            return Err("Pool not found".to_string());
+        }
+
+        for profile in self.profiles.values_mut() {
+            profile.is_active = false;
+        }
+        for pool in self.pools.values_mut() {
+            pool.is_active = pool.id == id;
+        }
+
+        Ok(())
+    }
+
+    /// 获取当前激活的负载均衡池
+    pub fn get_active_pool(&self) -> Option<&ProfilePool> {
+        self.pools.values().find(|p| p.is_active)
+    }
+
+    /// 获取模型路由表
+    pub fn get_routing_rules(&self) -> &[RoutingRule] {
+        &self.routing_rules
+    }
+
+    /// 替换模型路由表（规则引用的配置必须存在，正则必须合法）
+    pub fn set_routing_rules(&mut self, rules: Vec<RoutingRule>) -> Result<(), String> {
+        for rule in &rules {
+            if !self.profiles.contains_key(&rule.profile_id) {
+                return Err(format!("Profile not found: {}", rule.profile_id));
+            }
+            if rule.match_type == RouteMatchType::Regex {
+                regex::Regex::new(&rule.pattern)
+                    .map_err(|e| format!("Invalid regex pattern '{}': {}", rule.pattern, e))?;
+            }
+        }
+        self.routing_rules = rules;
+        Ok(())
+    }
+
+    /// 按模型路由表查找目标配置，返回 (规则, 路由后的模型名称)
+    ///
+    /// 目标配置已被删除的规则会被跳过。未命中时返回 None，由调用方回退到激活的配置。
+    pub fn route_model(&self, model: &str) -> Option<(&RoutingRule, String)> {
+        self.routing_rules.iter().find_map(|rule| {
+            if !self.profiles.contains_key(&rule.profile_id) {
+                return None;
+            }
+            rule.matches(model).map(|routed| (rule, routed))
+        })
+    }
+
+    /// 获取所有预算
+    pub fn get_budgets(&self) -> &[ProfileBudget] {
+        &self.budgets
+    }
+
+    /// 替换所有预算（预算引用的配置必须存在，上限必须为正数）
+    pub fn set_budgets(&mut self, budgets: Vec<ProfileBudget>) -> Result<(), String> {
+        for budget in &budgets {
+            if !self.profiles.contains_key(&budget.profile_id) {
+                return Err(format!("Profile not found: {}", budget.profile_id));
+            }
+            if !budget.limit.is_finite() || budget.limit <= 0.0 {
+                return Err(format!("Invalid budget limit: {}", budget.limit));
+            }
+        }
+        self.budgets = budgets;
+        Ok(())
+    }
+}
+
+/// 常量时间比较两个字节串（长度不同时直接返回 false，只泄露长度）
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
 }
 
 /// 获取配置文件路径
@@ -357,3 +1262,73 @@ pub fn get_config_path() -> PathBuf {
 
     config_dir.join("config.json")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(pattern: &str, match_type: RouteMatchType, strip_prefix: bool) -> RoutingRule {
+        RoutingRule {
+            id: "rule".to_string(),
+            pattern: pattern.to_string(),
+            match_type,
+            profile_id: "profile".to_string(),
+            strip_prefix,
+            enabled: true,
+        }
+    }
+
+    #[test]
+    fn test_routing_rule_matches() {
+        let exact = rule("claude-opus-4", RouteMatchType::Exact, false);
+        assert_eq!(exact.matches("claude-opus-4"), Some("claude-opus-4".to_string()));
+        assert_eq!(exact.matches("claude-opus-4-1"), None);
+
+        let prefix = rule("task:frontend/", RouteMatchType::Prefix, true);
+        assert_eq!(prefix.matches("task:frontend/claude-sonnet-4"), Some("claude-sonnet-4".to_string()));
+        assert_eq!(prefix.matches("task:backend/gpt-4"), None);
+
+        let keep_prefix = rule("task:frontend/", RouteMatchType::Prefix, false);
+        assert_eq!(
+            keep_prefix.matches("task:frontend/claude-sonnet-4"),
+            Some("task:frontend/claude-sonnet-4".to_string())
+        );
+
+        let regex = rule("^gpt-", RouteMatchType::Regex, false);
+        assert_eq!(regex.matches("gpt-4o"), Some("gpt-4o".to_string()));
+        assert_eq!(regex.matches("claude-haiku"), None);
+
+        let mut disabled = rule("claude-opus-4", RouteMatchType::Exact, false);
+        disabled.enabled = false;
+        assert_eq!(disabled.matches("claude-opus-4"), None);
+    }
+
+    #[test]
+    fn test_verify_api_key() {
+        let mut manager = ConfigManager::new();
+        manager.proxy_api_key = Some("sk-builtin".to_string());
+        manager.enable_auth = true;
+
+        let mut alice = ClientApiKey::new("alice".to_string(), None);
+        alice.key = "sk-alice".to_string();
+        let mut expired = ClientApiKey::new("ci".to_string(), Some(1_000));
+        expired.key = "sk-ci".to_string();
+        let alice_id = manager.create_client_key(alice.clone()).unwrap();
+        manager.create_client_key(expired).unwrap();
+
+        let now = 2_000;
+        assert_eq!(manager.verify_api_key("sk-alice", now), Ok(Some(alice_id.clone())));
+        assert_eq!(manager.verify_api_key("sk-builtin", now), Ok(Some(DEFAULT_CLIENT_KEY_ID.to_string())));
+        assert!(manager.verify_api_key("sk-ci", now).is_err());
+        assert!(manager.verify_api_key("sk-unknown", now).is_err());
+
+        alice.revoked = true;
+        manager.update_client_key(alice).unwrap();
+        assert!(manager.verify_api_key("sk-alice", now).is_err());
+
+        // 未启用授权时放行所有请求，只识别可用的密钥
+        manager.enable_auth = false;
+        assert_eq!(manager.verify_api_key("sk-alice", now), Ok(None));
+        assert_eq!(manager.verify_api_key("sk-builtin", now), Ok(Some(DEFAULT_CLIENT_KEY_ID.to_string())));
+    }
+}
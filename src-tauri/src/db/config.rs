@@ -1,14 +1,27 @@
 // 配置相关的数据库操作
 
-use crate::config::{Profile, MappingRule, ModelMappingMode};
+use crate::config::{
+    Profile, MappingRule, ModelMappingMode, UpstreamProtocol, CountTokensMode, ProfilePool, PoolMember, PoolStrategy,
+    RoutingRule, RouteMatchType, ProfileBudget, BudgetPeriod, BudgetMetric, BudgetAction, ClientApiKey,
+    UpstreamAuthScheme, HeaderRule, HeaderAction, ProfileTimeouts, UpstreamNetwork, RetryPolicy,
+};
 use crate::proxy::{ProxyConfig, ProxyServerStatus};
 use super::schema::get_db_path;
+use super::crypto::{decrypt_secret, encrypt_secret};
 use std::time::{SystemTime, UNIX_EPOCH};
 
 /// 保存 Profile 到数据库
 pub async fn save_profile_to_db(profile: &Profile) -> Result<(), String> {
     let db_path = get_db_path();
     let profile = profile.clone();
+    let encrypted_api_key = encrypt_secret(&profile.api_key)?;
+    let network_json = profile.network
+        .as_ref()
+        .map(|network| {
+            serde_json::to_string(&encrypt_network(network)?)
+                .map_err(|e| format!("Failed to serialize network settings: {}", e))
+        })
+        .transpose()?;
 
     tokio::task::spawn_blocking(move || {
         let conn = rusqlite::Connection::open(&db_path)
@@ -24,8 +37,12 @@ pub async fn save_profile_to_db(profile: &Profile) -> Result<(), String> {
             r#"
             INSERT INTO profiles (
                 id, name, api_base_url, api_key, is_active,
-                model_mapping_mode, override_model, created_at, updated_at
-            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+                model_mapping_mode, override_model, upstream_protocol, count_tokens_mode,
+                auth_scheme, auth_param,
+                connect_timeout_secs, first_byte_timeout_secs, idle_timeout_secs, total_timeout_secs,
+                network, retry_max_attempts, retry_base_delay_ms, retry_max_delay_ms,
+                created_at, updated_at
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21)
             ON CONFLICT(id) DO UPDATE SET
                 name = excluded.name,
                 api_base_url = excluded.api_base_url,
@@ -33,16 +50,40 @@ pub async fn save_profile_to_db(profile: &Profile) -> Result<(), String> {
                 is_active = excluded.is_active,
                 model_mapping_mode = excluded.model_mapping_mode,
                 override_model = excluded.override_model,
+                upstream_protocol = excluded.upstream_protocol,
+                count_tokens_mode = excluded.count_tokens_mode,
+                auth_scheme = excluded.auth_scheme,
+                auth_param = excluded.auth_param,
+                connect_timeout_secs = excluded.connect_timeout_secs,
+                first_byte_timeout_secs = excluded.first_byte_timeout_secs,
+                idle_timeout_secs = excluded.idle_timeout_secs,
+                total_timeout_secs = excluded.total_timeout_secs,
+                network = excluded.network,
+                retry_max_attempts = excluded.retry_max_attempts,
+                retry_base_delay_ms = excluded.retry_base_delay_ms,
+                retry_max_delay_ms = excluded.retry_max_delay_ms,
                 updated_at = excluded.updated_at
             "#,
             rusqlite::params![
                 &profile.id,
                 &profile.name,
                 &profile.api_base_url,
-                &profile.api_key,
+                &encrypted_api_key,
                 if profile.is_active { 1 } else { 0 },
                 profile.model_mapping_mode.as_str(),
                 &profile.override_model,
+                profile.upstream_protocol.as_str(),
+                profile.count_tokens_mode.as_str(),
+                profile.auth_scheme.as_str(),
+                &profile.auth_param,
+                profile.timeouts.connect_secs.map(|s| s as i64),
+                profile.timeouts.first_byte_secs.map(|s| s as i64),
+                profile.timeouts.idle_secs.map(|s| s as i64),
+                profile.timeouts.total_secs.map(|s| s as i64),
+                &network_json,
+                profile.retry.max_attempts as i64,
+                profile.retry.base_delay_ms as i64,
+                profile.retry.max_delay_ms as i64,
                 now,
                 now,
             ],
@@ -75,6 +116,46 @@ pub async fn save_profile_to_db(profile: &Profile) -> Result<(), String> {
             .map_err(|e| format!("Failed to save mapping rule: {}", e))?;
         }
 
+        // 删除旧的故障转移链
+        conn.execute(
+            "DELETE FROM profile_fallbacks WHERE profile_id = ?1",
+            rusqlite::params![&profile.id],
+        )
+        .map_err(|e| format!("Failed to delete old fallbacks: {}", e))?;
+
+        // 插入新的故障转移链
+        for (order, fallback_id) in profile.fallback_profile_ids.iter().enumerate() {
+            conn.execute(
+                r#"
+                INSERT INTO profile_fallbacks (
+                    profile_id, fallback_profile_id, fallback_order
+                ) VALUES (?1, ?2, ?3)
+                "#,
+                rusqlite::params![&profile.id, fallback_id, order as i32],
+            )
+            .map_err(|e| format!("Failed to save fallback profile: {}", e))?;
+        }
+
+        // 删除旧的请求头规则
+        conn.execute(
+            "DELETE FROM profile_header_rules WHERE profile_id = ?1",
+            rusqlite::params![&profile.id],
+        )
+        .map_err(|e| format!("Failed to delete old header rules: {}", e))?;
+
+        // 插入新的请求头规则
+        for (order, rule) in profile.header_rules.iter().enumerate() {
+            conn.execute(
+                r#"
+                INSERT INTO profile_header_rules (
+                    profile_id, action, name, value, rule_order
+                ) VALUES (?1, ?2, ?3, ?4, ?5)
+                "#,
+                rusqlite::params![&profile.id, rule.action.as_str(), &rule.name, &rule.value, order as i32],
+            )
+            .map_err(|e| format!("Failed to save header rule: {}", e))?;
+        }
+
         Ok::<(), String>(())
     })
     .await
@@ -95,7 +176,10 @@ pub async fn load_profiles_from_db() -> Result<Vec<Profile>, String> {
             .prepare(
                 r#"
                 SELECT id, name, api_base_url, api_key, is_active,
-                       model_mapping_mode, override_model
+                       model_mapping_mode, override_model, upstream_protocol, count_tokens_mode,
+                       auth_scheme, auth_param,
+                       connect_timeout_secs, first_byte_timeout_secs, idle_timeout_secs, total_timeout_secs,
+                       network, retry_max_attempts, retry_base_delay_ms, retry_max_delay_ms
                 FROM profiles
                 ORDER BY created_at DESC
                 "#,
@@ -104,40 +188,68 @@ pub async fn load_profiles_from_db() -> Result<Vec<Profile>, String> {
 
         let profiles = stmt
             .query_map([], |row| {
-                let id: String = row.get(0)?;
-                let name: String = row.get(1)?;
-                let api_base_url: String = row.get(2)?;
-                let api_key: String = row.get(3)?;
                 let is_active: i32 = row.get(4)?;
                 let model_mapping_mode: String = row.get(5)?;
-                let override_model: Option<String> = row.get(6)?;
+                let upstream_protocol: String = row.get(7)?;
+                let count_tokens_mode: String = row.get(8)?;
+                let auth_scheme: String = row.get(9)?;
+                let network: Option<String> = row.get(15)?;
 
-                Ok((id, name, api_base_url, api_key, is_active, model_mapping_mode, override_model))
+                // 映射规则、故障转移链和请求头规则存放在单独的表中，稍后加载
+                Ok(Profile {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    api_base_url: row.get(2)?,
+                    api_key: row.get(3)?,
+                    is_active: is_active != 0,
+                    model_mapping_mode: ModelMappingMode::from_str(&model_mapping_mode),
+                    override_model: row.get(6)?,
+                    model_mappings: Vec::new(),
+                    upstream_protocol: UpstreamProtocol::from(upstream_protocol.as_str()),
+                    fallback_profile_ids: Vec::new(),
+                    count_tokens_mode: CountTokensMode::from(count_tokens_mode.as_str()),
+                    auth_scheme: UpstreamAuthScheme::from(auth_scheme.as_str()),
+                    auth_param: row.get(10)?,
+                    header_rules: Vec::new(),
+                    timeouts: ProfileTimeouts {
+                        connect_secs: row.get::<_, Option<i64>>(11)?.map(|s| s as u64),
+                        first_byte_secs: row.get::<_, Option<i64>>(12)?.map(|s| s as u64),
+                        idle_secs: row.get::<_, Option<i64>>(13)?.map(|s| s as u64),
+                        total_secs: row.get::<_, Option<i64>>(14)?.map(|s| s as u64),
+                    },
+                    network: network.and_then(|json| serde_json::from_str(&json).ok()),
+                    retry: RetryPolicy {
+                        max_attempts: row.get::<_, i64>(16)?.max(1) as u32,
+                        base_delay_ms: row.get::<_, i64>(17)?.max(0) as u64,
+                        max_delay_ms: row.get::<_, i64>(18)?.max(0) as u64,
+                    },
+                })
             })
             .map_err(|e| format!("Failed to query profiles: {}", e))?
             .collect::<Result<Vec<_>, _>>()
             .map_err(|e| format!("Failed to collect profiles: {}", e))?;
 
-        Ok::<Vec<_>, String>(profiles)
+        Ok::<Vec<Profile>, String>(profiles)
     })
     .await
     .map_err(|e| format!("Task join error: {}", e))??;
 
-    // 为每个 profile 加载映射规则
+    // 为每个 profile 解密 API Key，并加载映射规则、故障转移链和请求头规则
     let mut result = Vec::new();
-    for (id, name, api_base_url, api_key, is_active, model_mapping_mode, override_model) in profiles {
-        let mappings = load_mappings_for_profile(&id).await?;
-
-        result.push(Profile {
-            id,
-            name,
-            api_base_url,
-            api_key,
-            is_active: is_active != 0,
-            model_mapping_mode: ModelMappingMode::from_str(&model_mapping_mode),
-            override_model,
-            model_mappings: mappings,
-        });
+    for mut profile in profiles {
+        // 无法解密时保留原始密文：不会发送给上游，保存时原样写回，
+        // 恢复密钥文件后即可重新解密；否则需要用户重新填写 API Key
+        let owner = format!("API key of profile {}", profile.name);
+        if let Some(api_key) = decrypt_loaded_secret(&profile.api_key, &owner) {
+            profile.api_key = api_key;
+        }
+        if let Some(network) = profile.network.as_mut() {
+            decrypt_network(network, &profile.name);
+        }
+        profile.model_mappings = load_mappings_for_profile(&profile.id).await?;
+        profile.fallback_profile_ids = load_fallbacks_for_profile(&profile.id).await?;
+        profile.header_rules = load_header_rules_for_profile(&profile.id).await?;
+        result.push(profile);
     }
 
     Ok(result)
@@ -181,6 +293,77 @@ async fn load_mappings_for_profile(profile_id: &str) -> Result<Vec<MappingRule>,
     .map_err(|e| format!("Task join error: {}", e))?
 }
 
+/// 加载指定 Profile 的故障转移链
+async fn load_fallbacks_for_profile(profile_id: &str) -> Result<Vec<String>, String> {
+    let db_path = get_db_path();
+    let profile_id = profile_id.to_string();
+
+    tokio::task::spawn_blocking(move || {
+        let conn = rusqlite::Connection::open(&db_path)
+            .map_err(|e| format!("Failed to open database: {}", e))?;
+
+        let mut stmt = conn
+            .prepare(
+                r#"
+                SELECT fallback_profile_id
+                FROM profile_fallbacks
+                WHERE profile_id = ?1
+                ORDER BY fallback_order ASC
+                "#,
+            )
+            .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+        let fallbacks = stmt
+            .query_map([&profile_id], |row| row.get::<_, String>(0))
+            .map_err(|e| format!("Failed to query fallbacks: {}", e))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to collect fallbacks: {}", e))?;
+
+        Ok::<Vec<String>, String>(fallbacks)
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))?
+}
+
+/// 加载指定 Profile 的请求头规则
+async fn load_header_rules_for_profile(profile_id: &str) -> Result<Vec<HeaderRule>, String> {
+    let db_path = get_db_path();
+    let profile_id = profile_id.to_string();
+
+    tokio::task::spawn_blocking(move || {
+        let conn = rusqlite::Connection::open(&db_path)
+            .map_err(|e| format!("Failed to open database: {}", e))?;
+
+        let mut stmt = conn
+            .prepare(
+                r#"
+                SELECT action, name, value
+                FROM profile_header_rules
+                WHERE profile_id = ?1
+                ORDER BY rule_order ASC
+                "#,
+            )
+            .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+        let rules = stmt
+            .query_map([&profile_id], |row| {
+                let action: String = row.get(0)?;
+                Ok(HeaderRule {
+                    action: HeaderAction::from(action.as_str()),
+                    name: row.get(1)?,
+                    value: row.get(2)?,
+                })
+            })
+            .map_err(|e| format!("Failed to query header rules: {}", e))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to collect header rules: {}", e))?;
+
+        Ok::<Vec<HeaderRule>, String>(rules)
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))?
+}
+
 /// 删除 Profile
 pub async fn delete_profile_from_db(profile_id: &str) -> Result<(), String> {
     let db_path = get_db_path();
@@ -196,6 +379,516 @@ pub async fn delete_profile_from_db(profile_id: &str) -> Result<(), String> {
         )
         .map_err(|e| format!("Failed to delete profile: {}", e))?;
 
+        // 删除指向该配置的路由规则
+        conn.execute(
+            "DELETE FROM routing_rules WHERE profile_id = ?1",
+            rusqlite::params![&profile_id],
+        )
+        .map_err(|e| format!("Failed to delete routing rules: {}", e))?;
+
+        // 删除该配置的预算
+        conn.execute(
+            "DELETE FROM profile_budgets WHERE profile_id = ?1",
+            rusqlite::params![&profile_id],
+        )
+        .map_err(|e| format!("Failed to delete budgets: {}", e))?;
+
+        // 删除该配置的请求头规则
+        conn.execute(
+            "DELETE FROM profile_header_rules WHERE profile_id = ?1",
+            rusqlite::params![&profile_id],
+        )
+        .map_err(|e| format!("Failed to delete header rules: {}", e))?;
+
+        Ok::<(), String>(())
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))??;
+
+    Ok(())
+}
+
+/// 保存负载均衡池到数据库
+pub async fn save_pool_to_db(pool: &ProfilePool) -> Result<(), String> {
+    let db_path = get_db_path();
+    let pool = pool.clone();
+
+    tokio::task::spawn_blocking(move || {
+        let conn = rusqlite::Connection::open(&db_path)
+            .map_err(|e| format!("Failed to open database: {}", e))?;
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|e| format!("Failed to get timestamp: {}", e))?
+            .as_secs() as i64;
+
+        // 插入或更新池
+        conn.execute(
+            r#"
+            INSERT INTO pools (id, name, strategy, is_active, created_at, updated_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+            ON CONFLICT(id) DO UPDATE SET
+                name = excluded.name,
+                strategy = excluded.strategy,
+                is_active = excluded.is_active,
+                updated_at = excluded.updated_at
+            "#,
+            rusqlite::params![
+                &pool.id,
+                &pool.name,
+                pool.strategy.as_str(),
+                if pool.is_active { 1 } else { 0 },
+                now,
+                now,
+            ],
+        )
+        .map_err(|e| format!("Failed to save pool: {}", e))?;
+
+        // 删除旧的成员
+        conn.execute(
+            "DELETE FROM pool_members WHERE pool_id = ?1",
+            rusqlite::params![&pool.id],
+        )
+        .map_err(|e| format!("Failed to delete old pool members: {}", e))?;
+
+        // 插入新的成员
+        for (order, member) in pool.members.iter().enumerate() {
+            conn.execute(
+                r#"
+                INSERT INTO pool_members (pool_id, profile_id, weight, member_order)
+                VALUES (?1, ?2, ?3, ?4)
+                "#,
+                rusqlite::params![&pool.id, &member.profile_id, member.weight, order as i32],
+            )
+            .map_err(|e| format!("Failed to save pool member: {}", e))?;
+        }
+
+        Ok::<(), String>(())
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))??;
+
+    Ok(())
+}
+
+/// 从数据库加载所有负载均衡池
+pub async fn load_pools_from_db() -> Result<Vec<ProfilePool>, String> {
+    let db_path = get_db_path();
+
+    tokio::task::spawn_blocking(move || {
+        let conn = rusqlite::Connection::open(&db_path)
+            .map_err(|e| format!("Failed to open database: {}", e))?;
+
+        let mut stmt = conn
+            .prepare(
+                r#"
+                SELECT id, name, strategy, is_active
+                FROM pools
+                ORDER BY created_at DESC
+                "#,
+            )
+            .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+        let rows = stmt
+            .query_map([], |row| {
+                let id: String = row.get(0)?;
+                let name: String = row.get(1)?;
+                let strategy: String = row.get(2)?;
+                let is_active: i32 = row.get(3)?;
+                Ok((id, name, strategy, is_active))
+            })
+            .map_err(|e| format!("Failed to query pools: {}", e))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to collect pools: {}", e))?;
+
+        let mut member_stmt = conn
+            .prepare(
+                r#"
+                SELECT profile_id, weight
+                FROM pool_members
+                WHERE pool_id = ?1
+                ORDER BY member_order ASC
+                "#,
+            )
+            .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+        let mut pools = Vec::new();
+        for (id, name, strategy, is_active) in rows {
+            let members = member_stmt
+                .query_map([&id], |row| {
+                    Ok(PoolMember {
+                        profile_id: row.get(0)?,
+                        weight: row.get(1)?,
+                    })
+                })
+                .map_err(|e| format!("Failed to query pool members: {}", e))?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| format!("Failed to collect pool members: {}", e))?;
+
+            pools.push(ProfilePool {
+                id,
+                name,
+                strategy: PoolStrategy::from(strategy.as_str()),
+                members,
+                is_active: is_active != 0,
+            });
+        }
+
+        Ok::<Vec<ProfilePool>, String>(pools)
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))?
+}
+
+/// 删除负载均衡池
+pub async fn delete_pool_from_db(pool_id: &str) -> Result<(), String> {
+    let db_path = get_db_path();
+    let pool_id = pool_id.to_string();
+
+    tokio::task::spawn_blocking(move || {
+        let conn = rusqlite::Connection::open(&db_path)
+            .map_err(|e| format!("Failed to open database: {}", e))?;
+
+        conn.execute(
+            "DELETE FROM pool_members WHERE pool_id = ?1",
+            rusqlite::params![&pool_id],
+        )
+        .map_err(|e| format!("Failed to delete pool members: {}", e))?;
+
+        conn.execute(
+            "DELETE FROM pools WHERE id = ?1",
+            rusqlite::params![&pool_id],
+        )
+        .map_err(|e| format!("Failed to delete pool: {}", e))?;
+
+        Ok::<(), String>(())
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))??;
+
+    Ok(())
+}
+
+/// 保存模型路由表（整体替换，规则顺序即匹配顺序）
+pub async fn save_routing_rules_to_db(rules: &[RoutingRule]) -> Result<(), String> {
+    let db_path = get_db_path();
+    let rules = rules.to_vec();
+
+    tokio::task::spawn_blocking(move || {
+        let mut conn = rusqlite::Connection::open(&db_path)
+            .map_err(|e| format!("Failed to open database: {}", e))?;
+
+        let tx = conn.transaction()
+            .map_err(|e| format!("Failed to begin transaction: {}", e))?;
+
+        tx.execute("DELETE FROM routing_rules", [])
+            .map_err(|e| format!("Failed to delete old routing rules: {}", e))?;
+
+        for (order, rule) in rules.iter().enumerate() {
+            tx.execute(
+                r#"
+                INSERT INTO routing_rules (id, pattern, match_type, profile_id, strip_prefix, enabled, rule_order)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+                "#,
+                rusqlite::params![
+                    &rule.id,
+                    &rule.pattern,
+                    rule.match_type.as_str(),
+                    &rule.profile_id,
+                    if rule.strip_prefix { 1 } else { 0 },
+                    if rule.enabled { 1 } else { 0 },
+                    order as i32,
+                ],
+            )
+            .map_err(|e| format!("Failed to save routing rule: {}", e))?;
+        }
+
+        tx.commit()
+            .map_err(|e| format!("Failed to commit routing rules: {}", e))?;
+
+        Ok::<(), String>(())
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))??;
+
+    Ok(())
+}
+
+/// 从数据库加载模型路由表
+pub async fn load_routing_rules_from_db() -> Result<Vec<RoutingRule>, String> {
+    let db_path = get_db_path();
+
+    tokio::task::spawn_blocking(move || {
+        let conn = rusqlite::Connection::open(&db_path)
+            .map_err(|e| format!("Failed to open database: {}", e))?;
+
+        let mut stmt = conn
+            .prepare(
+                r#"
+                SELECT id, pattern, match_type, profile_id, strip_prefix, enabled
+                FROM routing_rules
+                ORDER BY rule_order ASC
+                "#,
+            )
+            .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+        let rules = stmt
+            .query_map([], |row| {
+                let match_type: String = row.get(2)?;
+                let strip_prefix: i32 = row.get(4)?;
+                let enabled: i32 = row.get(5)?;
+                Ok(RoutingRule {
+                    id: row.get(0)?,
+                    pattern: row.get(1)?,
+                    match_type: RouteMatchType::from(match_type.as_str()),
+                    profile_id: row.get(3)?,
+                    strip_prefix: strip_prefix != 0,
+                    enabled: enabled != 0,
+                })
+            })
+            .map_err(|e| format!("Failed to query routing rules: {}", e))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to collect routing rules: {}", e))?;
+
+        Ok::<Vec<RoutingRule>, String>(rules)
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))?
+}
+
+/// 保存所有预算（整体替换）
+pub async fn save_budgets_to_db(budgets: &[ProfileBudget]) -> Result<(), String> {
+    let db_path = get_db_path();
+    let budgets = budgets.to_vec();
+
+    tokio::task::spawn_blocking(move || {
+        let mut conn = rusqlite::Connection::open(&db_path)
+            .map_err(|e| format!("Failed to open database: {}", e))?;
+
+        let tx = conn.transaction()
+            .map_err(|e| format!("Failed to begin transaction: {}", e))?;
+
+        tx.execute("DELETE FROM profile_budgets", [])
+            .map_err(|e| format!("Failed to delete old budgets: {}", e))?;
+
+        for (order, budget) in budgets.iter().enumerate() {
+            tx.execute(
+                r#"
+                INSERT INTO profile_budgets (id, profile_id, period, metric, limit_value, action, enabled, budget_order)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+                "#,
+                rusqlite::params![
+                    &budget.id,
+                    &budget.profile_id,
+                    budget.period.as_str(),
+                    budget.metric.as_str(),
+                    budget.limit,
+                    budget.action.as_str(),
+                    if budget.enabled { 1 } else { 0 },
+                    order as i32,
+                ],
+            )
+            .map_err(|e| format!("Failed to save budget: {}", e))?;
+        }
+
+        tx.commit()
+            .map_err(|e| format!("Failed to commit budgets: {}", e))?;
+
+        Ok::<(), String>(())
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))??;
+
+    Ok(())
+}
+
+/// 从数据库加载所有预算
+pub async fn load_budgets_from_db() -> Result<Vec<ProfileBudget>, String> {
+    let db_path = get_db_path();
+
+    tokio::task::spawn_blocking(move || {
+        let conn = rusqlite::Connection::open(&db_path)
+            .map_err(|e| format!("Failed to open database: {}", e))?;
+
+        let mut stmt = conn
+            .prepare(
+                r#"
+                SELECT id, profile_id, period, metric, limit_value, action, enabled
+                FROM profile_budgets
+                ORDER BY budget_order ASC
+                "#,
+            )
+            .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+        let budgets = stmt
+            .query_map([], |row| {
+                let period: String = row.get(2)?;
+                let metric: String = row.get(3)?;
+                let action: String = row.get(5)?;
+                let enabled: i32 = row.get(6)?;
+                Ok(ProfileBudget {
+                    id: row.get(0)?,
+                    profile_id: row.get(1)?,
+                    period: BudgetPeriod::from(period.as_str()),
+                    metric: BudgetMetric::from(metric.as_str()),
+                    limit: row.get(4)?,
+                    action: BudgetAction::from(action.as_str()),
+                    enabled: enabled != 0,
+                })
+            })
+            .map_err(|e| format!("Failed to query budgets: {}", e))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to collect budgets: {}", e))?;
+
+        Ok::<Vec<ProfileBudget>, String>(budgets)
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))?
+}
+
+/// 保存客户端 API Key 到数据库
+pub async fn save_client_key_to_db(client_key: &ClientApiKey) -> Result<(), String> {
+    let db_path = get_db_path();
+    let client_key = client_key.clone();
+    let encrypted_key = encrypt_secret(&client_key.key)?;
+
+    tokio::task::spawn_blocking(move || {
+        let conn = rusqlite::Connection::open(&db_path)
+            .map_err(|e| format!("Failed to open database: {}", e))?;
+
+        // 密文带随机 nonce，无法依赖数据库唯一约束，解密后逐个比较
+        let mut stmt = conn
+            .prepare("SELECT api_key FROM client_api_keys WHERE id != ?1")
+            .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+        let existing_keys = stmt
+            .query_map(rusqlite::params![&client_key.id], |row| row.get::<_, String>(0))
+            .map_err(|e| format!("Failed to query client keys: {}", e))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to collect client keys: {}", e))?;
+        if existing_keys.iter().any(|stored| decrypt_secret(stored).is_ok_and(|key| key == client_key.key)) {
+            return Err(format!("Client key {} duplicates an existing key", client_key.name));
+        }
+
+        conn.execute(
+            r#"
+            INSERT INTO client_api_keys (id, name, api_key, created_at, last_used_at, expires_at, revoked)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+            ON CONFLICT(id) DO UPDATE SET
+                name = excluded.name,
+                api_key = excluded.api_key,
+                last_used_at = excluded.last_used_at,
+                expires_at = excluded.expires_at,
+                revoked = excluded.revoked
+            "#,
+            rusqlite::params![
+                &client_key.id,
+                &client_key.name,
+                &encrypted_key,
+                client_key.created_at,
+                client_key.last_used_at,
+                client_key.expires_at,
+                if client_key.revoked { 1 } else { 0 },
+            ],
+        )
+        .map_err(|e| format!("Failed to save client key: {}", e))?;
+
+        Ok::<(), String>(())
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))??;
+
+    Ok(())
+}
+
+/// 从数据库加载所有客户端 API Key
+pub async fn load_client_keys_from_db() -> Result<Vec<ClientApiKey>, String> {
+    let db_path = get_db_path();
+
+    let client_keys = tokio::task::spawn_blocking(move || {
+        let conn = rusqlite::Connection::open(&db_path)
+            .map_err(|e| format!("Failed to open database: {}", e))?;
+
+        let mut stmt = conn
+            .prepare(
+                r#"
+                SELECT id, name, api_key, created_at, last_used_at, expires_at, revoked
+                FROM client_api_keys
+                ORDER BY created_at ASC
+                "#,
+            )
+            .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+        let client_keys = stmt
+            .query_map([], |row| {
+                let revoked: i32 = row.get(6)?;
+                Ok(ClientApiKey {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    key: row.get(2)?,
+                    created_at: row.get(3)?,
+                    last_used_at: row.get(4)?,
+                    expires_at: row.get(5)?,
+                    revoked: revoked != 0,
+                })
+            })
+            .map_err(|e| format!("Failed to query client keys: {}", e))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to collect client keys: {}", e))?;
+
+        Ok::<Vec<ClientApiKey>, String>(client_keys)
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))??;
+
+    // 无法解密的密钥直接跳过（密文不能用于鉴权）
+    Ok(client_keys
+        .into_iter()
+        .filter_map(|mut client_key| {
+            let owner = format!("client key {}", client_key.name);
+            client_key.key = decrypt_loaded_secret(&client_key.key, &owner)?;
+            Some(client_key)
+        })
+        .collect())
+}
+
+/// 删除客户端 API Key（历史日志中的 client_key_id 保留）
+pub async fn delete_client_key_from_db(id: &str) -> Result<(), String> {
+    let db_path = get_db_path();
+    let id = id.to_string();
+
+    tokio::task::spawn_blocking(move || {
+        let conn = rusqlite::Connection::open(&db_path)
+            .map_err(|e| format!("Failed to open database: {}", e))?;
+
+        conn.execute(
+            "DELETE FROM client_api_keys WHERE id = ?1",
+            rusqlite::params![&id],
+        )
+        .map_err(|e| format!("Failed to delete client key: {}", e))?;
+
+        Ok::<(), String>(())
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))??;
+
+    Ok(())
+}
+
+/// 更新客户端 API Key 的最近使用时间
+pub async fn touch_client_key_in_db(id: &str, last_used_at: i64) -> Result<(), String> {
+    let db_path = get_db_path();
+    let id = id.to_string();
+
+    tokio::task::spawn_blocking(move || {
+        let conn = rusqlite::Connection::open(&db_path)
+            .map_err(|e| format!("Failed to open database: {}", e))?;
+
+        conn.execute(
+            "UPDATE client_api_keys SET last_used_at = ?1 WHERE id = ?2",
+            rusqlite::params![last_used_at, &id],
+        )
+        .map_err(|e| format!("Failed to update client key: {}", e))?;
+
         Ok::<(), String>(())
     })
     .await
@@ -264,9 +957,54 @@ pub async fn load_app_config(key: &str) -> Result<Option<String>, String> {
     .map_err(|e| format!("Task join error: {}", e))?
 }
 
+/// 加密网络设置中的代理密码
+fn encrypt_network(network: &UpstreamNetwork) -> Result<UpstreamNetwork, String> {
+    let mut network = network.clone();
+    if let Some(password) = network.proxy_password.as_deref() {
+        network.proxy_password = Some(encrypt_secret(password)?);
+    }
+    Ok(network)
+}
+
+/// 解密网络设置中的代理密码（失败时保留原始密文，保存时原样写回）
+fn decrypt_network(network: &mut UpstreamNetwork, owner: &str) {
+    if let Some(password) = network.proxy_password.as_deref() {
+        let owner = format!("proxy password of {}", owner);
+        if let Some(password) = decrypt_loaded_secret(password, &owner) {
+            network.proxy_password = Some(password);
+        }
+    }
+}
+
+/// 解密从数据库加载的密钥，所有加载路径共用
+///
+/// 失败时（如密钥文件丢失）记录错误并返回 None，由调用方决定保留密文还是丢弃该条目
+fn decrypt_loaded_secret(stored: &str, owner: &str) -> Option<String> {
+    match decrypt_secret(stored) {
+        Ok(plaintext) => Some(plaintext),
+        Err(e) => {
+            log::error!("Failed to decrypt {}: {}", owner, e);
+            None
+        }
+    }
+}
+
+/// 加密保存敏感的应用配置（如 proxy_api_key）
+pub async fn save_secret_app_config(key: &str, value: &str) -> Result<(), String> {
+    save_app_config(key, &encrypt_secret(value)?).await
+}
+
+/// 加载并解密敏感的应用配置（无法解密时按未配置处理）
+pub async fn load_secret_app_config(key: &str) -> Result<Option<String>, String> {
+    Ok(load_app_config(key).await?
+        .and_then(|value| decrypt_loaded_secret(&value, key)))
+}
+
 /// 保存代理服务器配置
 pub async fn save_proxy_config(config: &ProxyConfig) -> Result<(), String> {
-    let config_json = serde_json::to_string(config)
+    let mut config = config.clone();
+    config.upstream_network = encrypt_network(&config.upstream_network)?;
+    let config_json = serde_json::to_string(&config)
         .map_err(|e| format!("Failed to serialize config: {}", e))?;
 
     save_app_config(ProxyConfig::config_key(), &config_json).await
@@ -276,8 +1014,9 @@ pub async fn save_proxy_config(config: &ProxyConfig) -> Result<(), String> {
 pub async fn load_proxy_config() -> Result<ProxyConfig, String> {
     match load_app_config(ProxyConfig::config_key()).await? {
         Some(config_json) => {
-            let config: ProxyConfig = serde_json::from_str(&config_json)
+            let mut config: ProxyConfig = serde_json::from_str(&config_json)
                 .map_err(|e| format!("Failed to deserialize config: {}", e))?;
+            decrypt_network(&mut config.upstream_network, "default");
             Ok(config)
         }
         None => Ok(ProxyConfig::default()),
@@ -0,0 +1,196 @@
+// API Key 加密存储（AES-256-GCM，密钥保存在本地密钥文件中）
+
+use std::io::Write;
+use std::path::PathBuf;
+use aes_gcm::aead::{Aead, KeyInit, OsRng};
+use aes_gcm::{AeadCore, Aes256Gcm, Key, Nonce};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use lazy_static::lazy_static;
+use super::schema::get_db_path;
+
+/// 加密值的前缀（用于区分旧版明文值，并为以后更换算法预留版本号）
+const ENCRYPTED_PREFIX: &str = "enc:v1:";
+
+/// AES-GCM nonce 长度（字节）
+const NONCE_LEN: usize = 12;
+
+lazy_static! {
+    /// 进程内只读取一次密钥文件；读取失败时保留错误，避免以错误的密钥写入数据
+    static ref CIPHER: Result<Aes256Gcm, String> = load_or_create_cipher();
+}
+
+/// 获取密钥文件路径（与数据库文件在同一目录）
+fn get_key_path() -> PathBuf {
+    get_db_path().with_file_name("secret.key")
+}
+
+/// 读取密钥文件，不存在时生成新的随机密钥
+fn load_or_create_cipher() -> Result<Aes256Gcm, String> {
+    let path = get_key_path();
+
+    if path.exists() {
+        let encoded = std::fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read key file: {}", e))?;
+        let key = BASE64
+            .decode(encoded.trim())
+            .map_err(|e| format!("Invalid key file: {}", e))?;
+        if key.len() != 32 {
+            return Err(format!("Invalid key file: expected 32 bytes, got {}", key.len()));
+        }
+        return Ok(Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key)));
+    }
+
+    log::info!("Creating encryption key file at: {:?}", path);
+    let key = Aes256Gcm::generate_key(OsRng);
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        // 密钥文件只允许当前用户读写
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options
+        .open(&path)
+        .map_err(|e| format!("Failed to create key file: {}", e))?;
+    file.write_all(BASE64.encode(key).as_bytes())
+        .map_err(|e| format!("Failed to write key file: {}", e))?;
+
+    Ok(Aes256Gcm::new(&key))
+}
+
+fn cipher() -> Result<&'static Aes256Gcm, String> {
+    CIPHER.as_ref().map_err(|e| e.clone())
+}
+
+/// 判断存储的值是否已加密
+pub fn is_encrypted(stored: &str) -> bool {
+    stored.starts_with(ENCRYPTED_PREFIX)
+}
+
+fn encrypt_with(cipher: &Aes256Gcm, plaintext: &str) -> Result<String, String> {
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext.as_bytes())
+        .map_err(|_| "Failed to encrypt secret".to_string())?;
+
+    let mut data = nonce.to_vec();
+    data.extend_from_slice(&ciphertext);
+    Ok(format!("{}{}", ENCRYPTED_PREFIX, BASE64.encode(data)))
+}
+
+fn decrypt_with(cipher: &Aes256Gcm, stored: &str) -> Result<String, String> {
+    let Some(encoded) = stored.strip_prefix(ENCRYPTED_PREFIX) else {
+        // 迁移前的明文值
+        return Ok(stored.to_string());
+    };
+
+    let data = BASE64
+        .decode(encoded)
+        .map_err(|e| format!("Invalid encrypted secret: {}", e))?;
+    if data.len() < NONCE_LEN {
+        return Err("Invalid encrypted secret: too short".to_string());
+    }
+
+    let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+    let plaintext = cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| "Failed to decrypt secret (key file changed or data corrupted)".to_string())?;
+    String::from_utf8(plaintext).map_err(|e| format!("Invalid decrypted secret: {}", e))
+}
+
+/// 加密要写入数据库的密钥（空值保持为空）
+///
+/// 已是密文的值（解密失败时保留在内存中的原始密文）原样写回，避免覆盖无法解密的密钥。
+pub fn encrypt_secret(plaintext: &str) -> Result<String, String> {
+    if plaintext.is_empty() || is_encrypted(plaintext) {
+        return Ok(plaintext.to_string());
+    }
+    encrypt_with(cipher()?, plaintext)
+}
+
+/// 解密从数据库读取的密钥（未加密的旧值原样返回）
+pub fn decrypt_secret(stored: &str) -> Result<String, String> {
+    if !is_encrypted(stored) {
+        return Ok(stored.to_string());
+    }
+    decrypt_with(cipher()?, stored)
+}
+
+/// 迁移：加密数据库中仍为明文的密钥
+///
+/// 包括 profiles.api_key、client_api_keys.api_key 和 app_config 中的 proxy_api_key。
+pub(super) fn encrypt_plaintext_secrets(conn: &mut rusqlite::Connection) -> Result<(), String> {
+    let targets = [
+        ("profiles", "id", "api_key", ""),
+        ("client_api_keys", "id", "api_key", ""),
+        ("app_config", "key", "value", "AND key = 'proxy_api_key'"),
+    ];
+
+    let tx = conn.transaction()
+        .map_err(|e| format!("Failed to begin transaction: {}", e))?;
+    let mut migrated = 0;
+
+    for (table, id_column, value_column, filter) in targets {
+        let rows: Vec<(String, String)> = {
+            let mut stmt = tx
+                .prepare(&format!(
+                    "SELECT {id_column}, {value_column} FROM {table} \
+                     WHERE {value_column} != '' AND {value_column} NOT LIKE '{ENCRYPTED_PREFIX}%' {filter}"
+                ))
+                .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+            let rows = stmt
+                .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+                .map_err(|e| format!("Failed to query {}: {}", table, e))?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| format!("Failed to collect {}: {}", table, e))?;
+            rows
+        };
+
+        for (id, plaintext) in rows {
+            tx.execute(
+                &format!("UPDATE {table} SET {value_column} = ?1 WHERE {id_column} = ?2"),
+                rusqlite::params![encrypt_secret(&plaintext)?, id],
+            )
+            .map_err(|e| format!("Failed to encrypt {}: {}", table, e))?;
+            migrated += 1;
+        }
+    }
+
+    tx.commit()
+        .map_err(|e| format!("Failed to commit encrypted secrets: {}", e))?;
+
+    if migrated > 0 {
+        log::info!("Encrypted {} plaintext secrets in database", migrated);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypt_roundtrip() {
+        let cipher = Aes256Gcm::new(&Aes256Gcm::generate_key(OsRng));
+
+        let stored = encrypt_with(&cipher, "sk-ant-secret").unwrap();
+        assert!(is_encrypted(&stored));
+        assert!(!stored.contains("sk-ant-secret"));
+        // 每次加密使用新的 nonce
+        assert_ne!(stored, encrypt_with(&cipher, "sk-ant-secret").unwrap());
+        assert_eq!(decrypt_with(&cipher, &stored).unwrap(), "sk-ant-secret");
+
+        // 旧版明文值原样返回
+        assert_eq!(decrypt_with(&cipher, "sk-plain").unwrap(), "sk-plain");
+
+        // 换了密钥无法解密
+        let other = Aes256Gcm::new(&Aes256Gcm::generate_key(OsRng));
+        assert!(decrypt_with(&other, &stored).is_err());
+
+        // 无法解密而保留的密文原样写回
+        assert_eq!(encrypt_secret(&stored).unwrap(), stored);
+    }
+}
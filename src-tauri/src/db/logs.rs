@@ -30,8 +30,9 @@ pub async fn save_log_to_db(log: &RequestLog) -> Result<bool, String> {
                 input_tokens, output_tokens, cache_creation_input_tokens, cache_read_input_tokens,
                 duration_ms, upstream_duration_ms,
                 status_code, error_message, is_stream,
-                request_size_bytes, response_size_bytes, response_body
-            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20)
+                request_size_bytes, response_size_bytes, response_body,
+                parent_request_id, pool_id, endpoint, tokenizer, token_source, cost, client_key_id, attempts, completion_reason, error_type, outcome, cache_status, cache_source_request_id
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23, ?24, ?25, ?26, ?27, ?28, ?29, ?30, ?31, ?32, ?33)
             "#,
             rusqlite::params![
                 &log.request_id,
@@ -54,6 +55,19 @@ pub async fn save_log_to_db(log: &RequestLog) -> Result<bool, String> {
                 log.request_size_bytes,
                 log.response_size_bytes,
                 &log.response_body,
+                &log.parent_request_id,
+                &log.pool_id,
+                &log.endpoint,
+                &log.tokenizer,
+                &log.token_source,
+                log.cost,
+                &log.client_key_id,
+                log.attempts,
+                &log.completion_reason,
+                &log.error_type,
+                &log.outcome,
+                &log.cache_status,
+                &log.cache_source_request_id,
             ],
         )
         .map_err(|e| format!("Failed to insert log: {}", e))?;
@@ -67,8 +81,8 @@ pub async fn save_log_to_db(log: &RequestLog) -> Result<bool, String> {
     Ok(is_new)
 }
 
-/// 更新日志到数据库（用于流式响应的 Token 统计更新）
-pub async fn update_log_to_db(log: &RequestLog) -> Result<(), String> {
+/// 更新日志到数据库（用于流式响应的 Token 统计更新），返回是否找到了要更新的记录
+pub async fn update_log_to_db(log: &RequestLog) -> Result<bool, String> {
     let db_path = get_db_path();
     let log = log.clone(); // 克隆 log 以避免生命周期问题
 
@@ -76,7 +90,7 @@ pub async fn update_log_to_db(log: &RequestLog) -> Result<(), String> {
         let conn = rusqlite::Connection::open(&db_path)
             .map_err(|e| format!("Failed to open database: {}", e))?;
 
-        conn.execute(
+        let updated = conn.execute(
             r#"
             UPDATE request_logs SET
                 input_tokens = ?1,
@@ -84,8 +98,12 @@ pub async fn update_log_to_db(log: &RequestLog) -> Result<(), String> {
                 cache_creation_input_tokens = ?3,
                 cache_read_input_tokens = ?4,
                 duration_ms = ?5,
-                response_body = ?6
-            WHERE request_id = ?7
+                response_body = ?6,
+                tokenizer = ?7,
+                token_source = ?8,
+                cost = ?9,
+                completion_reason = ?10
+            WHERE request_id = ?11
             "#,
             rusqlite::params![
                 log.input_tokens,
@@ -94,17 +112,19 @@ pub async fn update_log_to_db(log: &RequestLog) -> Result<(), String> {
                 log.cache_read_input_tokens,
                 log.duration_ms,
                 &log.response_body,
+                &log.tokenizer,
+                &log.token_source,
+                log.cost,
+                &log.completion_reason,
                 &log.request_id,
             ],
         )
         .map_err(|e| format!("Failed to update log: {}", e))?;
 
-        Ok::<(), String>(())
+        Ok::<bool, String>(updated > 0)
     })
     .await
-    .map_err(|e| format!("Task join error: {}", e))??;
-
-    Ok(())
+    .map_err(|e| format!("Task join error: {}", e))?
 }
 
 /// 从数据库查询日志
@@ -128,7 +148,8 @@ pub async fn get_logs_from_db(limit: usize, offset: usize) -> Result<Vec<Request
                     rl.input_tokens, rl.output_tokens, rl.cache_creation_input_tokens, rl.cache_read_input_tokens,
                     rl.duration_ms, rl.upstream_duration_ms,
                     rl.status_code, rl.error_message, rl.is_stream,
-                    rl.request_size_bytes, rl.response_size_bytes, rl.response_body
+                    rl.request_size_bytes, rl.response_size_bytes, rl.response_body,
+                    rl.parent_request_id, rl.pool_id, rl.endpoint, rl.tokenizer, rl.token_source, rl.cost, rl.client_key_id, rl.attempts, rl.completion_reason, rl.error_type, rl.outcome, rl.cache_status, rl.cache_source_request_id
                 FROM request_logs rl
                 LEFT JOIN profiles p ON rl.profile_id = p.id
                 ORDER BY rl.timestamp DESC
@@ -173,6 +194,19 @@ pub async fn get_logs_from_db(limit: usize, offset: usize) -> Result<Vec<Request
                     request_size_bytes: row.get(17).ok(),
                     response_size_bytes: row.get(18).ok(),
                     response_body: row.get(19).ok(),
+                    parent_request_id: row.get(20).ok(),
+                    pool_id: row.get(21).ok(),
+                    endpoint: row.get(22).ok(),
+                    tokenizer: row.get(23).ok(),
+                    token_source: row.get(24)?,
+                    cost: row.get(25).ok(),
+                    client_key_id: row.get(26).ok(),
+                    attempts: row.get(27).unwrap_or(1),
+                    completion_reason: row.get(28).ok(),
+                    error_type: row.get(29).ok(),
+                    outcome: row.get(30)?,
+                    cache_status: row.get(31).ok(),
+                    cache_source_request_id: row.get(32).ok(),
                 })
             })
             .map_err(|e| format!("Failed to query logs: {}", e))?
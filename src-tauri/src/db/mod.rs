@@ -4,17 +4,26 @@ mod schema;
 mod logs;
 mod stats;
 mod config;
+mod pricing;
+mod crypto;
 
 // 重新导出公共 API
 pub use schema::{get_db_path, init_database};
+pub use crypto::is_encrypted;
 pub use logs::{save_log_to_db, update_log_to_db, get_logs_from_db, cleanup_old_logs, deduplicate_logs};
 pub use stats::{
-    DashboardStats, TokenDataPoint, ProfileConsumption,
-    get_dashboard_stats, get_token_stats, get_profile_consumption_ranking
+    DashboardStats, TokenDataPoint, ProfileConsumption, ClientKeyConsumption,
+    get_dashboard_stats, get_token_stats, get_profile_consumption_ranking,
+    get_profile_usage_since, get_client_key_consumption
 };
 pub use config::{
     save_profile_to_db, load_profiles_from_db, delete_profile_from_db,
-    save_app_config, load_app_config,
+    save_pool_to_db, load_pools_from_db, delete_pool_from_db,
+    save_routing_rules_to_db, load_routing_rules_from_db,
+    save_budgets_to_db, load_budgets_from_db,
+    save_client_key_to_db, load_client_keys_from_db, delete_client_key_from_db, touch_client_key_in_db,
+    save_app_config, load_app_config, save_secret_app_config, load_secret_app_config,
     save_proxy_config, load_proxy_config,
     save_proxy_status, load_proxy_status
 };
+pub use pricing::{ModelPrice, calculate_log_cost, init_model_prices, save_model_prices_to_db, load_model_prices_from_db};
@@ -0,0 +1,283 @@
+// 模型价格表相关的数据库操作
+
+use std::sync::RwLock;
+use lazy_static::lazy_static;
+use crate::config::RouteMatchType;
+use crate::logger::RequestLog;
+use super::schema::get_db_path;
+
+lazy_static! {
+    /// 内存中的价格表：启动时从数据库加载，保存时整体替换，计算成本时不访问数据库
+    static ref PRICES: RwLock<PriceTable> = RwLock::new(PriceTable::new(Vec::new()));
+}
+
+/// 价格表及预编译的正则匹配模式（与 prices 一一对应，非正则模式为 None）
+struct PriceTable {
+    prices: Vec<ModelPrice>,
+    regexes: Vec<Option<regex::Regex>>,
+}
+
+impl PriceTable {
+    fn new(prices: Vec<ModelPrice>) -> Self {
+        let regexes = prices.iter().map(ModelPrice::compile_regex).collect();
+        Self { prices, regexes }
+    }
+}
+
+/// 模型价格（单位：美元 / 百万 token）
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelPrice {
+    pub id: String,
+    /// 供应商（与日志中的 provider 一致，如 Anthropic、OpenAI、Custom），为空表示任意供应商
+    #[serde(default)]
+    pub provider: Option<String>,
+    /// 只对指定 Profile 生效，为空表示任意 Profile
+    #[serde(default)]
+    pub profile_id: Option<String>,
+    /// 模型匹配模式（按 match_type 解释）
+    pub model_pattern: String,
+    #[serde(default)]
+    pub match_type: RouteMatchType,
+    pub input_price: f64,
+    pub output_price: f64,
+    pub cache_creation_price: f64,
+    pub cache_read_price: f64,
+}
+
+impl ModelPrice {
+    /// 编译正则匹配模式，非正则模式或模式无效时返回 None
+    fn compile_regex(&self) -> Option<regex::Regex> {
+        if !matches!(self.match_type, RouteMatchType::Regex) {
+            return None;
+        }
+        regex::Regex::new(&self.model_pattern)
+            .map_err(|_| log::warn!("Invalid price regex pattern: {}", self.model_pattern))
+            .ok()
+    }
+
+    /// 判断模型名称是否命中该价格的匹配模式（regex 为 compile_regex 的结果）
+    fn matches_model(&self, model: &str, regex: Option<&regex::Regex>) -> bool {
+        match self.match_type {
+            RouteMatchType::Exact => self.model_pattern == model,
+            RouteMatchType::Prefix => model.starts_with(self.model_pattern.as_str()),
+            RouteMatchType::Regex => regex.is_some_and(|re| re.is_match(model)),
+        }
+    }
+
+    /// 计算一次请求的成本（美元）
+    pub fn cost(&self, log: &RequestLog) -> f64 {
+        (log.input_tokens as f64 * self.input_price
+            + log.output_tokens as f64 * self.output_price
+            + log.cache_creation_input_tokens as f64 * self.cache_creation_price
+            + log.cache_read_input_tokens as f64 * self.cache_read_price)
+            / 1_000_000.0
+    }
+}
+
+/// 为请求查找适用的价格
+///
+/// 指定了 Profile 的价格优先于指定了供应商的价格，再次是通用价格；
+/// 同一优先级按列表顺序取第一个命中的。
+fn find_price<'a>(
+    table: &'a PriceTable,
+    profile_id: &str,
+    provider: &str,
+    model: &str,
+) -> Option<&'a ModelPrice> {
+    let mut best: Option<(u8, &ModelPrice)> = None;
+
+    for (price, regex) in table.prices.iter().zip(&table.regexes) {
+        if price.profile_id.as_deref().is_some_and(|id| id != profile_id)
+            || price.provider.as_deref().is_some_and(|p| !p.eq_ignore_ascii_case(provider))
+            || !price.matches_model(model, regex.as_ref())
+        {
+            continue;
+        }
+
+        let score = if price.profile_id.is_some() { 2 } else { 0 }
+            + if price.provider.is_some() { 1 } else { 0 };
+        if best.map_or(true, |(best_score, _)| score > best_score) {
+            best = Some((score, price));
+        }
+    }
+
+    best.map(|(_, price)| price)
+}
+
+/// 计算日志对应请求的成本，没有匹配的价格时返回 None
+pub fn calculate_log_cost(log: &RequestLog) -> Option<f64> {
+    let prices = PRICES.read().ok()?;
+
+    // 按实际转发的模型计费，没有转发模型时（如透传请求）使用原始模型
+    let model = if log.forwarded_model.is_empty() { &log.original_model } else { &log.forwarded_model };
+    find_price(&prices, &log.profile_id, &log.provider, model).map(|price| price.cost(log))
+}
+
+/// 启动时将价格表加载到内存，返回价格条数
+pub async fn init_model_prices() -> Result<usize, String> {
+    let prices = load_model_prices_from_db().await?;
+    let count = prices.len();
+    replace_cached_prices(prices);
+    Ok(count)
+}
+
+fn replace_cached_prices(prices: Vec<ModelPrice>) {
+    if let Ok(mut cached) = PRICES.write() {
+        *cached = PriceTable::new(prices);
+    }
+}
+
+/// 保存价格表（整体替换，列表顺序即同优先级下的匹配顺序）
+pub async fn save_model_prices_to_db(prices: &[ModelPrice]) -> Result<(), String> {
+    let db_path = get_db_path();
+    let prices = prices.to_vec();
+
+    let saved = tokio::task::spawn_blocking(move || {
+        let mut conn = rusqlite::Connection::open(&db_path)
+            .map_err(|e| format!("Failed to open database: {}", e))?;
+
+        let tx = conn.transaction()
+            .map_err(|e| format!("Failed to begin transaction: {}", e))?;
+
+        tx.execute("DELETE FROM model_prices", [])
+            .map_err(|e| format!("Failed to delete old model prices: {}", e))?;
+
+        for (order, price) in prices.iter().enumerate() {
+            tx.execute(
+                r#"
+                INSERT INTO model_prices (
+                    id, provider, profile_id, model_pattern, match_type,
+                    input_price, output_price, cache_creation_price, cache_read_price, price_order
+                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+                "#,
+                rusqlite::params![
+                    &price.id,
+                    &price.provider,
+                    &price.profile_id,
+                    &price.model_pattern,
+                    price.match_type.as_str(),
+                    price.input_price,
+                    price.output_price,
+                    price.cache_creation_price,
+                    price.cache_read_price,
+                    order as i32,
+                ],
+            )
+            .map_err(|e| format!("Failed to save model price: {}", e))?;
+        }
+
+        tx.commit()
+            .map_err(|e| format!("Failed to commit model prices: {}", e))?;
+
+        Ok::<Vec<ModelPrice>, String>(prices)
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))??;
+
+    replace_cached_prices(saved);
+    Ok(())
+}
+
+/// 从数据库加载价格表
+pub async fn load_model_prices_from_db() -> Result<Vec<ModelPrice>, String> {
+    let db_path = get_db_path();
+
+    tokio::task::spawn_blocking(move || {
+        let conn = rusqlite::Connection::open(&db_path)
+            .map_err(|e| format!("Failed to open database: {}", e))?;
+
+        let mut stmt = conn
+            .prepare(
+                r#"
+                SELECT id, provider, profile_id, model_pattern, match_type,
+                       input_price, output_price, cache_creation_price, cache_read_price
+                FROM model_prices
+                ORDER BY price_order ASC
+                "#,
+            )
+            .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+        let prices = stmt
+            .query_map([], |row| {
+                let match_type: String = row.get(4)?;
+                Ok(ModelPrice {
+                    id: row.get(0)?,
+                    provider: row.get(1)?,
+                    profile_id: row.get(2)?,
+                    model_pattern: row.get(3)?,
+                    match_type: RouteMatchType::from(match_type.as_str()),
+                    input_price: row.get(5)?,
+                    output_price: row.get(6)?,
+                    cache_creation_price: row.get(7)?,
+                    cache_read_price: row.get(8)?,
+                })
+            })
+            .map_err(|e| format!("Failed to query model prices: {}", e))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to collect model prices: {}", e))?;
+
+        Ok::<Vec<ModelPrice>, String>(prices)
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn price(id: &str, provider: Option<&str>, profile_id: Option<&str>, pattern: &str, match_type: RouteMatchType) -> ModelPrice {
+        ModelPrice {
+            id: id.to_string(),
+            provider: provider.map(|s| s.to_string()),
+            profile_id: profile_id.map(|s| s.to_string()),
+            model_pattern: pattern.to_string(),
+            match_type,
+            input_price: 3.0,
+            output_price: 15.0,
+            cache_creation_price: 3.75,
+            cache_read_price: 0.3,
+        }
+    }
+
+    #[test]
+    fn test_find_price_prefers_most_specific() {
+        let prices = PriceTable::new(vec![
+            price("global", None, None, "claude-", RouteMatchType::Prefix),
+            price("provider", Some("anthropic"), None, "claude-sonnet-4", RouteMatchType::Prefix),
+            price("profile", None, Some("p1"), "^claude-.*sonnet", RouteMatchType::Regex),
+            price("invalid", None, Some("p1"), "claude-(", RouteMatchType::Regex),
+        ]);
+
+        let id = |profile: &str, provider: &str, model: &str| {
+            find_price(&prices, profile, provider, model).map(|p| p.id.as_str())
+        };
+        assert_eq!(id("p1", "Anthropic", "claude-sonnet-4-5"), Some("profile"));
+        assert_eq!(id("p2", "Anthropic", "claude-sonnet-4-5"), Some("provider"));
+        assert_eq!(id("p2", "Custom", "claude-sonnet-4-5"), Some("global"));
+        assert_eq!(id("p1", "Custom", "claude-haiku-4-5"), Some("global"));
+        assert_eq!(id("p1", "Custom", "gpt-4o"), None);
+    }
+
+    #[test]
+    fn test_cost_uses_separate_rates() {
+        let price = price("p", None, None, "claude-sonnet-4-5", RouteMatchType::Exact);
+        let mut log = RequestLog::new(
+            "p1".to_string(),
+            "Profile".to_string(),
+            "claude-sonnet-4-5".to_string(),
+            crate::logger::ModelMode::Passthrough,
+            "claude-sonnet-4-5".to_string(),
+            "https://api.anthropic.com".to_string(),
+            0,
+        );
+        log.input_tokens = 1_000;
+        log.output_tokens = 2_000;
+        log.cache_creation_input_tokens = 10_000;
+        log.cache_read_input_tokens = 100_000;
+
+        // 1000*3 + 2000*15 + 10000*3.75 + 100000*0.3 = 100500 → $0.1005
+        assert!((price.cost(&log) - 0.1005).abs() < 1e-9);
+    }
+}
@@ -17,7 +17,7 @@ pub async fn init_database() -> Result<(), String> {
     log::info!("Initializing database at: {:?}", db_path);
 
     // 使用 rusqlite 直接操作数据库
-    let conn = rusqlite::Connection::open(&db_path)
+    let mut conn = rusqlite::Connection::open(&db_path)
         .map_err(|e| format!("Failed to open database: {}", e))?;
 
     // 创建日志表（如果不存在）
@@ -56,7 +56,38 @@ pub async fn init_database() -> Result<(), String> {
             is_stream INTEGER NOT NULL,
             request_size_bytes INTEGER,
             response_size_bytes INTEGER,
-            response_body TEXT
+            response_body TEXT,
+
+            -- 故障转移与负载均衡
+            parent_request_id TEXT,
+            pool_id TEXT,
+
+            -- 入站请求
+            endpoint TEXT,
+
+            -- Token 计数
+            tokenizer TEXT,
+            token_source TEXT NOT NULL DEFAULT 'none',
+
+            -- 成本
+            cost REAL,
+
+            -- 客户端
+            client_key_id TEXT,
+
+            -- 重试
+            attempts INTEGER NOT NULL DEFAULT 1,
+
+            -- 流式响应
+            completion_reason TEXT,
+
+            -- 错误
+            error_type TEXT,
+            outcome TEXT NOT NULL DEFAULT 'ok',
+
+            -- 响应缓存
+            cache_status TEXT,
+            cache_source_request_id TEXT
         )
         "#,
         [],
@@ -162,6 +193,260 @@ pub async fn init_database() -> Result<(), String> {
         log::info!("Successfully added UNIQUE constraint and cleaned up duplicates");
     }
 
+    // 迁移：添加 parent_request_id 字段（故障转移时多次尝试共享同一个父请求 ID）
+    let column_exists: bool = conn
+        .query_row(
+            "SELECT COUNT(*) FROM pragma_table_info('request_logs') WHERE name='parent_request_id'",
+            [],
+            |row| row.get::<_, i32>(0).map(|count| count > 0),
+        )
+        .unwrap_or(false);
+
+    if !column_exists {
+        log::info!("Adding parent_request_id column to request_logs table");
+        conn.execute(
+            "ALTER TABLE request_logs ADD COLUMN parent_request_id TEXT",
+            [],
+        )
+        .map_err(|e| format!("Failed to add parent_request_id column: {}", e))?;
+    }
+
+    // 迁移：添加 pool_id 字段（记录请求由哪个负载均衡池分配）
+    let column_exists: bool = conn
+        .query_row(
+            "SELECT COUNT(*) FROM pragma_table_info('request_logs') WHERE name='pool_id'",
+            [],
+            |row| row.get::<_, i32>(0).map(|count| count > 0),
+        )
+        .unwrap_or(false);
+
+    if !column_exists {
+        log::info!("Adding pool_id column to request_logs table");
+        conn.execute(
+            "ALTER TABLE request_logs ADD COLUMN pool_id TEXT",
+            [],
+        )
+        .map_err(|e| format!("Failed to add pool_id column: {}", e))?;
+    }
+
+    // 迁移：添加 endpoint 字段（记录请求的方法和路径，区分 /v1/messages 与透传的其他接口）
+    let column_exists: bool = conn
+        .query_row(
+            "SELECT COUNT(*) FROM pragma_table_info('request_logs') WHERE name='endpoint'",
+            [],
+            |row| row.get::<_, i32>(0).map(|count| count > 0),
+        )
+        .unwrap_or(false);
+
+    if !column_exists {
+        log::info!("Adding endpoint column to request_logs table");
+        conn.execute(
+            "ALTER TABLE request_logs ADD COLUMN endpoint TEXT",
+            [],
+        )
+        .map_err(|e| format!("Failed to add endpoint column: {}", e))?;
+    }
+
+    // 迁移：添加 tokenizer 字段（记录本地计数使用的分词器）
+    let column_exists: bool = conn
+        .query_row(
+            "SELECT COUNT(*) FROM pragma_table_info('request_logs') WHERE name='tokenizer'",
+            [],
+            |row| row.get::<_, i32>(0).map(|count| count > 0),
+        )
+        .unwrap_or(false);
+
+    if !column_exists {
+        log::info!("Adding tokenizer column to request_logs table");
+        conn.execute(
+            "ALTER TABLE request_logs ADD COLUMN tokenizer TEXT",
+            [],
+        )
+        .map_err(|e| format!("Failed to add tokenizer column: {}", e))?;
+    }
+
+    // 迁移：添加 token_source 字段（Token 数量来源：upstream/local_estimate/partial/none）
+    let column_exists: bool = conn
+        .query_row(
+            "SELECT COUNT(*) FROM pragma_table_info('request_logs') WHERE name='token_source'",
+            [],
+            |row| row.get::<_, i32>(0).map(|count| count > 0),
+        )
+        .unwrap_or(false);
+
+    if !column_exists {
+        log::info!("Adding token_source column to request_logs table");
+        conn.execute(
+            "ALTER TABLE request_logs ADD COLUMN token_source TEXT NOT NULL DEFAULT 'none'",
+            [],
+        )
+        .map_err(|e| format!("Failed to add token_source column: {}", e))?;
+
+        // 回填历史记录：记录了分词器的是本地估算，其余有 token 数的视为上游返回
+        conn.execute(
+            r#"
+            UPDATE request_logs SET token_source = CASE
+                WHEN tokenizer IS NOT NULL THEN 'local_estimate'
+                WHEN input_tokens + output_tokens > 0 THEN 'upstream'
+                ELSE 'none'
+            END
+            "#,
+            [],
+        )
+        .map_err(|e| format!("Failed to backfill token_source: {}", e))?;
+    }
+
+    // 迁移：添加 cost 字段（按价格表计算的请求成本）
+    let column_exists: bool = conn
+        .query_row(
+            "SELECT COUNT(*) FROM pragma_table_info('request_logs') WHERE name='cost'",
+            [],
+            |row| row.get::<_, i32>(0).map(|count| count > 0),
+        )
+        .unwrap_or(false);
+
+    if !column_exists {
+        log::info!("Adding cost column to request_logs table");
+        conn.execute(
+            "ALTER TABLE request_logs ADD COLUMN cost REAL",
+            [],
+        )
+        .map_err(|e| format!("Failed to add cost column: {}", e))?;
+    }
+
+    // 迁移：添加 client_key_id 字段（发起请求的客户端 API Key）
+    let column_exists: bool = conn
+        .query_row(
+            "SELECT COUNT(*) FROM pragma_table_info('request_logs') WHERE name='client_key_id'",
+            [],
+            |row| row.get::<_, i32>(0).map(|count| count > 0),
+        )
+        .unwrap_or(false);
+
+    if !column_exists {
+        log::info!("Adding client_key_id column to request_logs table");
+        conn.execute(
+            "ALTER TABLE request_logs ADD COLUMN client_key_id TEXT",
+            [],
+        )
+        .map_err(|e| format!("Failed to add client_key_id column: {}", e))?;
+    }
+
+    // 迁移：添加 attempts 字段（发送到上游的次数）
+    let column_exists: bool = conn
+        .query_row(
+            "SELECT COUNT(*) FROM pragma_table_info('request_logs') WHERE name='attempts'",
+            [],
+            |row| row.get::<_, i32>(0).map(|count| count > 0),
+        )
+        .unwrap_or(false);
+
+    if !column_exists {
+        log::info!("Adding attempts column to request_logs table");
+        conn.execute(
+            "ALTER TABLE request_logs ADD COLUMN attempts INTEGER NOT NULL DEFAULT 1",
+            [],
+        )
+        .map_err(|e| format!("Failed to add attempts column: {}", e))?;
+    }
+
+    // 迁移：添加 completion_reason 字段（流式响应的结束原因）
+    let column_exists: bool = conn
+        .query_row(
+            "SELECT COUNT(*) FROM pragma_table_info('request_logs') WHERE name='completion_reason'",
+            [],
+            |row| row.get::<_, i32>(0).map(|count| count > 0),
+        )
+        .unwrap_or(false);
+
+    if !column_exists {
+        log::info!("Adding completion_reason column to request_logs table");
+        conn.execute(
+            "ALTER TABLE request_logs ADD COLUMN completion_reason TEXT",
+            [],
+        )
+        .map_err(|e| format!("Failed to add completion_reason column: {}", e))?;
+    }
+
+    // 迁移：添加 error_type 字段（代理自身错误的类型）
+    let column_exists: bool = conn
+        .query_row(
+            "SELECT COUNT(*) FROM pragma_table_info('request_logs') WHERE name='error_type'",
+            [],
+            |row| row.get::<_, i32>(0).map(|count| count > 0),
+        )
+        .unwrap_or(false);
+
+    if !column_exists {
+        log::info!("Adding error_type column to request_logs table");
+        conn.execute(
+            "ALTER TABLE request_logs ADD COLUMN error_type TEXT",
+            [],
+        )
+        .map_err(|e| format!("Failed to add error_type column: {}", e))?;
+    }
+
+    // 迁移：添加 outcome 字段（请求的处理结果）
+    let column_exists: bool = conn
+        .query_row(
+            "SELECT COUNT(*) FROM pragma_table_info('request_logs') WHERE name='outcome'",
+            [],
+            |row| row.get::<_, i32>(0).map(|count| count > 0),
+        )
+        .unwrap_or(false);
+
+    if !column_exists {
+        log::info!("Adding outcome column to request_logs table");
+        conn.execute(
+            "ALTER TABLE request_logs ADD COLUMN outcome TEXT NOT NULL DEFAULT 'ok'",
+            [],
+        )
+        .map_err(|e| format!("Failed to add outcome column: {}", e))?;
+
+        // 已有记录中的错误状态码都来自上游
+        conn.execute(
+            "UPDATE request_logs SET outcome = 'upstream_status' WHERE status_code >= 400",
+            [],
+        )
+        .map_err(|e| format!("Failed to backfill outcome column: {}", e))?;
+    }
+
+    // 迁移：添加 cache_status 字段（响应缓存状态）
+    let column_exists: bool = conn
+        .query_row(
+            "SELECT COUNT(*) FROM pragma_table_info('request_logs') WHERE name='cache_status'",
+            [],
+            |row| row.get::<_, i32>(0).map(|count| count > 0),
+        )
+        .unwrap_or(false);
+
+    if !column_exists {
+        log::info!("Adding cache_status column to request_logs table");
+        conn.execute(
+            "ALTER TABLE request_logs ADD COLUMN cache_status TEXT",
+            [],
+        )
+        .map_err(|e| format!("Failed to add cache_status column: {}", e))?;
+    }
+
+    // 迁移：添加 cache_source_request_id 字段（命中缓存时对应的原始请求 ID）
+    let column_exists: bool = conn
+        .query_row(
+            "SELECT COUNT(*) FROM pragma_table_info('request_logs') WHERE name='cache_source_request_id'",
+            [],
+            |row| row.get::<_, i32>(0).map(|count| count > 0),
+        )
+        .unwrap_or(false);
+
+    if !column_exists {
+        log::info!("Adding cache_source_request_id column to request_logs table");
+        conn.execute(
+            "ALTER TABLE request_logs ADD COLUMN cache_source_request_id TEXT",
+            [],
+        )
+        .map_err(|e| format!("Failed to add cache_source_request_id column: {}", e))?;
+    }
+
     // 创建索引以提高查询性能
     conn.execute(
         "CREATE INDEX IF NOT EXISTS idx_timestamp ON request_logs(timestamp DESC)",
@@ -169,6 +454,13 @@ pub async fn init_database() -> Result<(), String> {
     )
     .map_err(|e| format!("Failed to create index: {}", e))?;
 
+    // 预算按配置和时间段汇总用量
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_profile_timestamp ON request_logs(profile_id, timestamp)",
+        [],
+    )
+    .map_err(|e| format!("Failed to create index: {}", e))?;
+
     // 创建配置表（Profiles）
     conn.execute(
         r#"
@@ -180,6 +472,18 @@ pub async fn init_database() -> Result<(), String> {
             is_active INTEGER NOT NULL DEFAULT 0,
             model_mapping_mode TEXT NOT NULL DEFAULT 'passthrough',
             override_model TEXT,
+            upstream_protocol TEXT NOT NULL DEFAULT 'anthropic',
+            count_tokens_mode TEXT NOT NULL DEFAULT 'auto',
+            auth_scheme TEXT NOT NULL DEFAULT 'bearer',
+            auth_param TEXT,
+            connect_timeout_secs INTEGER,
+            first_byte_timeout_secs INTEGER,
+            idle_timeout_secs INTEGER,
+            total_timeout_secs INTEGER,
+            network TEXT,
+            retry_max_attempts INTEGER NOT NULL DEFAULT 3,
+            retry_base_delay_ms INTEGER NOT NULL DEFAULT 500,
+            retry_max_delay_ms INTEGER NOT NULL DEFAULT 8000,
             created_at INTEGER NOT NULL,
             updated_at INTEGER NOT NULL
         )
@@ -188,6 +492,140 @@ pub async fn init_database() -> Result<(), String> {
     )
     .map_err(|e| format!("Failed to create profiles table: {}", e))?;
 
+    // 迁移：添加 upstream_protocol 字段（如果不存在）
+    let column_exists: bool = conn
+        .query_row(
+            "SELECT COUNT(*) FROM pragma_table_info('profiles') WHERE name='upstream_protocol'",
+            [],
+            |row| row.get::<_, i32>(0).map(|count| count > 0),
+        )
+        .unwrap_or(false);
+
+    if !column_exists {
+        log::info!("Adding upstream_protocol column to profiles table");
+        conn.execute(
+            "ALTER TABLE profiles ADD COLUMN upstream_protocol TEXT NOT NULL DEFAULT 'anthropic'",
+            [],
+        )
+        .map_err(|e| format!("Failed to add upstream_protocol column: {}", e))?;
+    }
+
+    // 迁移：添加 count_tokens_mode 字段（如果不存在）
+    let column_exists: bool = conn
+        .query_row(
+            "SELECT COUNT(*) FROM pragma_table_info('profiles') WHERE name='count_tokens_mode'",
+            [],
+            |row| row.get::<_, i32>(0).map(|count| count > 0),
+        )
+        .unwrap_or(false);
+
+    if !column_exists {
+        log::info!("Adding count_tokens_mode column to profiles table");
+        conn.execute(
+            "ALTER TABLE profiles ADD COLUMN count_tokens_mode TEXT NOT NULL DEFAULT 'auto'",
+            [],
+        )
+        .map_err(|e| format!("Failed to add count_tokens_mode column: {}", e))?;
+    }
+
+    // 迁移：添加 auth_scheme、auth_param 字段（如果不存在）
+    let column_exists: bool = conn
+        .query_row(
+            "SELECT COUNT(*) FROM pragma_table_info('profiles') WHERE name='auth_scheme'",
+            [],
+            |row| row.get::<_, i32>(0).map(|count| count > 0),
+        )
+        .unwrap_or(false);
+
+    if !column_exists {
+        log::info!("Adding auth_scheme and auth_param columns to profiles table");
+        conn.execute(
+            "ALTER TABLE profiles ADD COLUMN auth_scheme TEXT NOT NULL DEFAULT 'bearer'",
+            [],
+        )
+        .map_err(|e| format!("Failed to add auth_scheme column: {}", e))?;
+        conn.execute(
+            "ALTER TABLE profiles ADD COLUMN auth_param TEXT",
+            [],
+        )
+        .map_err(|e| format!("Failed to add auth_param column: {}", e))?;
+    }
+
+    // 迁移：添加超时设置字段（如果不存在）
+    let column_exists: bool = conn
+        .query_row(
+            "SELECT COUNT(*) FROM pragma_table_info('profiles') WHERE name='connect_timeout_secs'",
+            [],
+            |row| row.get::<_, i32>(0).map(|count| count > 0),
+        )
+        .unwrap_or(false);
+
+    if !column_exists {
+        log::info!("Adding timeout columns to profiles table");
+        for column in ["connect_timeout_secs", "first_byte_timeout_secs", "idle_timeout_secs", "total_timeout_secs"] {
+            conn.execute(
+                &format!("ALTER TABLE profiles ADD COLUMN {} INTEGER", column),
+                [],
+            )
+            .map_err(|e| format!("Failed to add {} column: {}", column, e))?;
+        }
+    }
+
+    // 迁移：添加 network 字段（如果不存在）
+    let column_exists: bool = conn
+        .query_row(
+            "SELECT COUNT(*) FROM pragma_table_info('profiles') WHERE name='network'",
+            [],
+            |row| row.get::<_, i32>(0).map(|count| count > 0),
+        )
+        .unwrap_or(false);
+
+    if !column_exists {
+        log::info!("Adding network column to profiles table");
+        conn.execute(
+            "ALTER TABLE profiles ADD COLUMN network TEXT",
+            [],
+        )
+        .map_err(|e| format!("Failed to add network column: {}", e))?;
+    }
+
+    // 迁移：添加重试策略字段（如果不存在）
+    let column_exists: bool = conn
+        .query_row(
+            "SELECT COUNT(*) FROM pragma_table_info('profiles') WHERE name='retry_max_attempts'",
+            [],
+            |row| row.get::<_, i32>(0).map(|count| count > 0),
+        )
+        .unwrap_or(false);
+
+    if !column_exists {
+        log::info!("Adding retry policy columns to profiles table");
+        for (column, default) in [("retry_max_attempts", 3), ("retry_base_delay_ms", 500), ("retry_max_delay_ms", 8000)] {
+            conn.execute(
+                &format!("ALTER TABLE profiles ADD COLUMN {} INTEGER NOT NULL DEFAULT {}", column, default),
+                [],
+            )
+            .map_err(|e| format!("Failed to add {} column: {}", column, e))?;
+        }
+    }
+
+    // 创建请求头规则表
+    conn.execute(
+        r#"
+        CREATE TABLE IF NOT EXISTS profile_header_rules (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            profile_id TEXT NOT NULL,
+            action TEXT NOT NULL DEFAULT 'override',
+            name TEXT NOT NULL,
+            value TEXT NOT NULL DEFAULT '',
+            rule_order INTEGER NOT NULL DEFAULT 0,
+            FOREIGN KEY (profile_id) REFERENCES profiles(id) ON DELETE CASCADE
+        )
+        "#,
+        [],
+    )
+    .map_err(|e| format!("Failed to create profile_header_rules table: {}", e))?;
+
     // 创建模型映射规则表
     conn.execute(
         r#"
@@ -205,6 +643,127 @@ pub async fn init_database() -> Result<(), String> {
     )
     .map_err(|e| format!("Failed to create model_mappings table: {}", e))?;
 
+    // 创建故障转移链表
+    conn.execute(
+        r#"
+        CREATE TABLE IF NOT EXISTS profile_fallbacks (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            profile_id TEXT NOT NULL,
+            fallback_profile_id TEXT NOT NULL,
+            fallback_order INTEGER NOT NULL DEFAULT 0,
+            FOREIGN KEY (profile_id) REFERENCES profiles(id) ON DELETE CASCADE
+        )
+        "#,
+        [],
+    )
+    .map_err(|e| format!("Failed to create profile_fallbacks table: {}", e))?;
+
+    // 创建负载均衡池表
+    conn.execute(
+        r#"
+        CREATE TABLE IF NOT EXISTS pools (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            strategy TEXT NOT NULL DEFAULT 'weighted_round_robin',
+            is_active INTEGER NOT NULL DEFAULT 0,
+            created_at INTEGER NOT NULL,
+            updated_at INTEGER NOT NULL
+        )
+        "#,
+        [],
+    )
+    .map_err(|e| format!("Failed to create pools table: {}", e))?;
+
+    // 创建负载均衡池成员表
+    conn.execute(
+        r#"
+        CREATE TABLE IF NOT EXISTS pool_members (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            pool_id TEXT NOT NULL,
+            profile_id TEXT NOT NULL,
+            weight INTEGER NOT NULL DEFAULT 1,
+            member_order INTEGER NOT NULL DEFAULT 0,
+            FOREIGN KEY (pool_id) REFERENCES pools(id) ON DELETE CASCADE
+        )
+        "#,
+        [],
+    )
+    .map_err(|e| format!("Failed to create pool_members table: {}", e))?;
+
+    // 创建模型路由表
+    conn.execute(
+        r#"
+        CREATE TABLE IF NOT EXISTS routing_rules (
+            id TEXT PRIMARY KEY,
+            pattern TEXT NOT NULL,
+            match_type TEXT NOT NULL DEFAULT 'exact',
+            profile_id TEXT NOT NULL,
+            strip_prefix INTEGER NOT NULL DEFAULT 0,
+            enabled INTEGER NOT NULL DEFAULT 1,
+            rule_order INTEGER NOT NULL DEFAULT 0,
+            FOREIGN KEY (profile_id) REFERENCES profiles(id) ON DELETE CASCADE
+        )
+        "#,
+        [],
+    )
+    .map_err(|e| format!("Failed to create routing_rules table: {}", e))?;
+
+    // 创建模型价格表（价格单位：美元 / 百万 token）
+    conn.execute(
+        r#"
+        CREATE TABLE IF NOT EXISTS model_prices (
+            id TEXT PRIMARY KEY,
+            provider TEXT,
+            profile_id TEXT,
+            model_pattern TEXT NOT NULL,
+            match_type TEXT NOT NULL DEFAULT 'exact',
+            input_price REAL NOT NULL DEFAULT 0,
+            output_price REAL NOT NULL DEFAULT 0,
+            cache_creation_price REAL NOT NULL DEFAULT 0,
+            cache_read_price REAL NOT NULL DEFAULT 0,
+            price_order INTEGER NOT NULL DEFAULT 0
+        )
+        "#,
+        [],
+    )
+    .map_err(|e| format!("Failed to create model_prices table: {}", e))?;
+
+    // 创建配置预算表
+    conn.execute(
+        r#"
+        CREATE TABLE IF NOT EXISTS profile_budgets (
+            id TEXT PRIMARY KEY,
+            profile_id TEXT NOT NULL,
+            period TEXT NOT NULL DEFAULT 'daily',
+            metric TEXT NOT NULL DEFAULT 'tokens',
+            limit_value REAL NOT NULL,
+            action TEXT NOT NULL DEFAULT 'warn',
+            enabled INTEGER NOT NULL DEFAULT 1,
+            budget_order INTEGER NOT NULL DEFAULT 0,
+            FOREIGN KEY (profile_id) REFERENCES profiles(id) ON DELETE CASCADE
+        )
+        "#,
+        [],
+    )
+    .map_err(|e| format!("Failed to create profile_budgets table: {}", e))?;
+
+    // 创建客户端 API Key 表
+    conn.execute(
+        r#"
+        CREATE TABLE IF NOT EXISTS client_api_keys (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            api_key TEXT NOT NULL,
+            created_at INTEGER NOT NULL,
+            last_used_at INTEGER,
+            expires_at INTEGER,
+            revoked INTEGER NOT NULL DEFAULT 0
+        )
+        "#,
+        [],
+    )
+    .map_err(|e| format!("Failed to create client_api_keys table: {}", e))?;
+
     // 创建应用配置表（存储全局配置）
     conn.execute(
         r#"
@@ -218,6 +777,11 @@ pub async fn init_database() -> Result<(), String> {
     )
     .map_err(|e| format!("Failed to create app_config table: {}", e))?;
 
+    // 迁移：加密仍以明文保存的 API Key（失败时保留明文，不影响启动，下次启动重试）
+    if let Err(e) = super::crypto::encrypt_plaintext_secrets(&mut conn) {
+        log::error!("Failed to encrypt plaintext secrets: {}", e);
+    }
+
     log::info!("Database initialized successfully");
     Ok(())
 }
@@ -0,0 +1,83 @@
+// 客户端 API Key 用量统计模块
+
+use crate::db::schema::get_db_path;
+use super::time_range::get_timestamp_for_range;
+use super::types::ClientKeyConsumption;
+
+/// 获取各客户端 API Key 的用量（按总 Token 消耗排序）
+///
+/// 未携带可识别密钥的请求（未启用访问授权时）归入 client_key_id 为空的一项。
+pub async fn get_client_key_consumption(
+    time_range: Option<&str>,
+    limit: Option<i32>,
+) -> Result<Vec<ClientKeyConsumption>, String> {
+    let db_path = get_db_path();
+    let time_range = time_range.map(|s| s.to_string());
+    let limit = limit.unwrap_or(10).clamp(1, 100);
+
+    tokio::task::spawn_blocking(move || {
+        let conn = rusqlite::Connection::open(&db_path)
+            .map_err(|e| format!("Failed to open database: {}", e))?;
+
+        let timestamp_filter = match time_range {
+            Some(ref tr) => get_timestamp_for_range(tr)?,
+            None => None,
+        };
+
+        // 已删除的密钥显示其 ID，内置密钥显示为 "默认密钥"
+        let mut stmt = conn
+            .prepare(
+                r#"
+                SELECT
+                    rl.client_key_id,
+                    COALESCE(k.name, CASE
+                        WHEN rl.client_key_id IS NULL THEN '未识别'
+                        WHEN rl.client_key_id = 'default' THEN '默认密钥'
+                        ELSE '已删除的密钥 (' || rl.client_key_id || ')'
+                    END) as client_key_name,
+                    COUNT(*) as total_requests,
+                    SUM(rl.input_tokens + rl.output_tokens + rl.cache_creation_input_tokens + rl.cache_read_input_tokens) as total_tokens,
+                    COALESCE(SUM(rl.cost), 0.0) as total_cost
+                FROM request_logs rl
+                LEFT JOIN client_api_keys k ON rl.client_key_id = k.id
+                WHERE (?1 IS NULL OR rl.timestamp >= ?1)
+                GROUP BY rl.client_key_id
+                ORDER BY total_tokens DESC
+                LIMIT ?2
+                "#,
+            )
+            .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+        let results: Vec<(Option<String>, String, i32, i32, f64)> = stmt
+            .query_map(rusqlite::params![timestamp_filter, limit as i64], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?))
+            })
+            .map_err(|e| format!("Failed to query client key consumption: {}", e))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to collect client key consumption: {}", e))?;
+
+        let total_tokens: i32 = results.iter().map(|(_, _, _, tokens, _)| tokens).sum();
+
+        let consumption = results
+            .into_iter()
+            .enumerate()
+            .map(|(index, (client_key_id, client_key_name, requests, tokens, cost))| ClientKeyConsumption {
+                client_key_id,
+                client_key_name,
+                total_requests: requests,
+                total_tokens: tokens,
+                total_cost: cost,
+                percentage: if total_tokens > 0 {
+                    (tokens as f32 / total_tokens as f32) * 100.0
+                } else {
+                    0.0
+                },
+                rank: (index + 1) as i32,
+            })
+            .collect();
+
+        Ok::<Vec<ClientKeyConsumption>, String>(consumption)
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))?
+}
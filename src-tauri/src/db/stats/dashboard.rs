@@ -4,6 +4,32 @@ use crate::db::schema::get_db_path;
 use super::time_range::get_today_start;
 use super::types::DashboardStats;
 
+/// 某个时间段内的汇总数据
+struct PeriodStats {
+    requests: i32,
+    tokens: i32,
+    /// 其中本地估算的 token 数
+    estimated_tokens: i32,
+    cost: f64,
+    /// 失败的请求数
+    errors: i32,
+    /// 命中响应缓存的请求数
+    cache_hits: i32,
+    /// 查询过响应缓存的请求数（命中 + 未命中）
+    cache_lookups: i32,
+}
+
+impl PeriodStats {
+    /// 响应缓存命中率（0-1，没有查询过缓存时为 0）
+    fn cache_hit_rate(&self) -> f64 {
+        if self.cache_lookups == 0 {
+            0.0
+        } else {
+            self.cache_hits as f64 / self.cache_lookups as f64
+        }
+    }
+}
+
 /// 获取仪表盘统计数据
 pub async fn get_dashboard_stats() -> Result<DashboardStats, String> {
     let db_path = get_db_path();
@@ -16,16 +42,24 @@ pub async fn get_dashboard_stats() -> Result<DashboardStats, String> {
         let today_start = get_today_start()?;
 
         // 查询今日请求数和 Token 使用量
-        let (today_requests, today_tokens) = query_stats_by_time(&conn, Some(today_start))?;
+        let today = query_stats_by_time(&conn, Some(today_start))?;
 
         // 查询总请求数和总 Token 使用量
-        let (total_requests, total_tokens) = query_stats_by_time(&conn, None)?;
+        let total = query_stats_by_time(&conn, None)?;
 
         Ok::<DashboardStats, String>(DashboardStats {
-            today_requests,
-            today_tokens,
-            total_requests,
-            total_tokens,
+            today_requests: today.requests,
+            today_tokens: today.tokens,
+            total_requests: total.requests,
+            total_tokens: total.tokens,
+            today_estimated_tokens: today.estimated_tokens,
+            total_estimated_tokens: total.estimated_tokens,
+            today_cost: today.cost,
+            total_cost: total.cost,
+            today_errors: today.errors,
+            total_errors: total.errors,
+            today_cache_hit_rate: today.cache_hit_rate(),
+            total_cache_hit_rate: total.cache_hit_rate(),
         })
     })
     .await
@@ -38,13 +72,20 @@ pub async fn get_dashboard_stats() -> Result<DashboardStats, String> {
 fn query_stats_by_time(
     conn: &rusqlite::Connection,
     timestamp_filter: Option<i64>,
-) -> Result<(i32, i32), String> {
+) -> Result<PeriodStats, String> {
     let (sql, params): (&str, Vec<i64>) = if let Some(ts) = timestamp_filter {
         (
             r#"
             SELECT
                 COUNT(*) as request_count,
-                COALESCE(SUM(input_tokens + output_tokens + cache_creation_input_tokens + cache_read_input_tokens), 0) as token_count
+                COALESCE(SUM(input_tokens + output_tokens + cache_creation_input_tokens + cache_read_input_tokens), 0) as token_count,
+                COALESCE(SUM(CASE WHEN token_source IN ('local_estimate', 'partial')
+                    THEN input_tokens + output_tokens + cache_creation_input_tokens + cache_read_input_tokens
+                    ELSE 0 END), 0) as estimated_token_count,
+                COALESCE(SUM(cost), 0.0) as total_cost,
+                COALESCE(SUM(CASE WHEN outcome != 'ok' THEN 1 ELSE 0 END), 0) as error_count,
+                COALESCE(SUM(CASE WHEN cache_status = 'hit' THEN 1 ELSE 0 END), 0) as cache_hits,
+                COALESCE(SUM(CASE WHEN cache_status IN ('hit', 'miss') THEN 1 ELSE 0 END), 0) as cache_lookups
             FROM request_logs
             WHERE timestamp >= ?1
             "#,
@@ -55,7 +96,14 @@ fn query_stats_by_time(
             r#"
             SELECT
                 COUNT(*) as request_count,
-                COALESCE(SUM(input_tokens + output_tokens + cache_creation_input_tokens + cache_read_input_tokens), 0) as token_count
+                COALESCE(SUM(input_tokens + output_tokens + cache_creation_input_tokens + cache_read_input_tokens), 0) as token_count,
+                COALESCE(SUM(CASE WHEN token_source IN ('local_estimate', 'partial')
+                    THEN input_tokens + output_tokens + cache_creation_input_tokens + cache_read_input_tokens
+                    ELSE 0 END), 0) as estimated_token_count,
+                COALESCE(SUM(cost), 0.0) as total_cost,
+                COALESCE(SUM(CASE WHEN outcome != 'ok' THEN 1 ELSE 0 END), 0) as error_count,
+                COALESCE(SUM(CASE WHEN cache_status = 'hit' THEN 1 ELSE 0 END), 0) as cache_hits,
+                COALESCE(SUM(CASE WHEN cache_status IN ('hit', 'miss') THEN 1 ELSE 0 END), 0) as cache_lookups
             FROM request_logs
             "#,
             vec![],
@@ -66,10 +114,22 @@ fn query_stats_by_time(
         .prepare(sql)
         .map_err(|e| format!("Failed to prepare statement: {}", e))?;
 
+    let map_row = |row: &rusqlite::Row| {
+        Ok(PeriodStats {
+            requests: row.get(0)?,
+            tokens: row.get(1)?,
+            estimated_tokens: row.get(2)?,
+            cost: row.get(3)?,
+            errors: row.get(4)?,
+            cache_hits: row.get(5)?,
+            cache_lookups: row.get(6)?,
+        })
+    };
+
     let result = if params.is_empty() {
-        stmt.query_row([], |row| Ok((row.get(0)?, row.get(1)?)))
+        stmt.query_row([], map_row)
     } else {
-        stmt.query_row([params[0]], |row| Ok((row.get(0)?, row.get(1)?)))
+        stmt.query_row([params[0]], map_row)
     }
     .map_err(|e| format!("Failed to query stats: {}", e))?;
 
@@ -5,11 +5,15 @@ mod time_range;
 mod dashboard;
 mod token_stats;
 mod ranking;
+mod usage;
+mod client_keys;
 
 // 重新导出公共类型
-pub use types::{DashboardStats, TokenDataPoint, ProfileConsumption};
+pub use types::{DashboardStats, TokenDataPoint, ProfileConsumption, ClientKeyConsumption};
 
 // 重新导出公共函数
 pub use dashboard::get_dashboard_stats;
 pub use token_stats::get_token_stats;
 pub use ranking::get_profile_consumption_ranking;
+pub use usage::get_profile_usage_since;
+pub use client_keys::get_client_key_consumption;
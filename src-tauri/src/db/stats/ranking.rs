@@ -5,12 +5,16 @@ use super::time_range::get_timestamp_for_range;
 use super::types::ProfileConsumption;
 
 /// 获取配置消耗排名（按总Token消耗）
+///
+/// token_source 不为空时只统计该来源（upstream/local_estimate/partial/none）的记录
 pub async fn get_profile_consumption_ranking(
     time_range: Option<&str>,
     limit: Option<i32>,
+    token_source: Option<&str>,
 ) -> Result<Vec<ProfileConsumption>, String> {
     let db_path = get_db_path();
     let time_range = time_range.map(|s| s.to_string());
+    let token_source = token_source.map(|s| s.to_string());
     let limit = limit.unwrap_or(10).max(1).min(100);
 
     let rankings = tokio::task::spawn_blocking(move || {
@@ -25,13 +29,13 @@ pub async fn get_profile_consumption_ranking(
         };
 
         // 查询排名数据
-        let results = query_profile_rankings(&conn, timestamp_filter, limit)?;
+        let results = query_profile_rankings(&conn, timestamp_filter, token_source.as_deref(), limit)?;
 
         // 计算总 token 数和百分比
-        let total_tokens: i32 = results.iter().map(|(_, _, tokens)| tokens).sum();
+        let total_tokens: i32 = results.iter().map(|(_, _, tokens, ..)| tokens).sum();
 
         let mut rankings = Vec::new();
-        for (index, (profile_id, profile_name, tokens)) in results.into_iter().enumerate() {
+        for (index, (profile_id, profile_name, tokens, cost, requests, retries)) in results.into_iter().enumerate() {
             let percentage = if total_tokens > 0 {
                 (tokens as f32 / total_tokens as f32) * 100.0
             } else {
@@ -42,6 +46,9 @@ pub async fn get_profile_consumption_ranking(
                 profile_id,
                 profile_name,
                 total_tokens: tokens,
+                total_cost: cost,
+                total_requests: requests,
+                total_retries: retries,
                 percentage,
                 rank: (index + 1) as i32,
             });
@@ -55,40 +62,55 @@ pub async fn get_profile_consumption_ranking(
     Ok(rankings)
 }
 
+/// 排名查询结果：(配置 ID, 配置名称, Token 数, 成本, 请求数, 重试次数)
+type RankingRow = (String, String, i32, f64, i32, i32);
+
 /// 查询配置排名数据
 fn query_profile_rankings(
     conn: &rusqlite::Connection,
     timestamp_filter: Option<i64>,
+    token_source: Option<&str>,
     limit: i32,
-) -> Result<Vec<(String, String, i32)>, String> {
+) -> Result<Vec<RankingRow>, String> {
     // 构建 SQL 查询
     // 只按 profile_id 分组，避免同一配置因名称变化而重复
     // 使用 LEFT JOIN profiles 表获取当前配置名称
     // 如果配置已删除，显示 "已删除的配置 (ID: xxx)"
+    // 未选中 Profile 的请求（鉴权失败、没有可用配置等）不参与排行
     let sql = if timestamp_filter.is_some() {
         r#"
         SELECT
             rl.profile_id,
             COALESCE(p.name, '已删除的配置 (' || rl.profile_id || ')') as profile_name,
-            SUM(rl.input_tokens + rl.output_tokens + rl.cache_creation_input_tokens + rl.cache_read_input_tokens) as total_tokens
+            SUM(rl.input_tokens + rl.output_tokens + rl.cache_creation_input_tokens + rl.cache_read_input_tokens) as total_tokens,
+            COALESCE(SUM(rl.cost), 0.0) as total_cost,
+            COUNT(*) as total_requests,
+            SUM(rl.attempts - 1) as total_retries
         FROM request_logs rl
         LEFT JOIN profiles p ON rl.profile_id = p.id
         WHERE rl.timestamp >= ?1
+          AND rl.profile_id != ''
+          AND (?2 IS NULL OR rl.token_source = ?2)
         GROUP BY rl.profile_id
         ORDER BY total_tokens DESC
-        LIMIT ?2
+        LIMIT ?3
         "#
     } else {
         r#"
         SELECT
             rl.profile_id,
             COALESCE(p.name, '已删除的配置 (' || rl.profile_id || ')') as profile_name,
-            SUM(rl.input_tokens + rl.output_tokens + rl.cache_creation_input_tokens + rl.cache_read_input_tokens) as total_tokens
+            SUM(rl.input_tokens + rl.output_tokens + rl.cache_creation_input_tokens + rl.cache_read_input_tokens) as total_tokens,
+            COALESCE(SUM(rl.cost), 0.0) as total_cost,
+            COUNT(*) as total_requests,
+            SUM(rl.attempts - 1) as total_retries
         FROM request_logs rl
         LEFT JOIN profiles p ON rl.profile_id = p.id
+        WHERE rl.profile_id != ''
+          AND (?1 IS NULL OR rl.token_source = ?1)
         GROUP BY rl.profile_id
         ORDER BY total_tokens DESC
-        LIMIT ?1
+        LIMIT ?2
         "#
     };
 
@@ -97,16 +119,16 @@ fn query_profile_rankings(
         .map_err(|e| format!("Failed to prepare statement: {}", e))?;
 
     // 执行查询
-    let results: Vec<(String, String, i32)> = if let Some(ts) = timestamp_filter {
-        stmt.query_map([ts, limit as i64], |row| {
-            Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+    let results: Vec<RankingRow> = if let Some(ts) = timestamp_filter {
+        stmt.query_map(rusqlite::params![ts, token_source, limit as i64], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?, row.get(5)?))
         })
         .map_err(|e| format!("Failed to query rankings: {}", e))?
         .collect::<Result<Vec<_>, _>>()
         .map_err(|e| format!("Failed to collect rankings: {}", e))?
     } else {
-        stmt.query_map([limit as i64], |row| {
-            Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+        stmt.query_map(rusqlite::params![token_source, limit as i64], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?, row.get(5)?))
         })
         .map_err(|e| format!("Failed to query rankings: {}", e))?
         .collect::<Result<Vec<_>, _>>()
@@ -6,19 +6,22 @@ use super::types::TokenDataPoint;
 use chrono::{Datelike, Local, TimeZone, Timelike};
 
 /// 获取 Token 使用量统计数据（按时间范围）
-pub async fn get_token_stats(time_range: &str) -> Result<Vec<TokenDataPoint>, String> {
+///
+/// token_source 不为空时只统计该来源（upstream/local_estimate/partial/none）的记录
+pub async fn get_token_stats(time_range: &str, token_source: Option<&str>) -> Result<Vec<TokenDataPoint>, String> {
     let db_path = get_db_path();
     let time_range = time_range.to_string();
+    let token_source = token_source.map(|s| s.to_string());
 
     let data_points = tokio::task::spawn_blocking(move || {
         let conn = rusqlite::Connection::open(&db_path)
             .map_err(|e| format!("Failed to open database: {}", e))?;
 
         match time_range.as_str() {
-            "hour" => get_hourly_stats(&conn),
-            "day" => get_daily_stats(&conn),
-            "week" => get_weekly_stats(&conn),
-            "month" => get_monthly_stats(&conn),
+            "hour" => get_hourly_stats(&conn, token_source.as_deref()),
+            "day" => get_daily_stats(&conn, token_source.as_deref()),
+            "week" => get_weekly_stats(&conn, token_source.as_deref()),
+            "month" => get_monthly_stats(&conn, token_source.as_deref()),
             _ => Err(format!("Invalid time range: {}", time_range)),
         }
     })
@@ -29,7 +32,7 @@ pub async fn get_token_stats(time_range: &str) -> Result<Vec<TokenDataPoint>, St
 }
 
 /// 获取按小时统计的数据（当前时间往前5小时 + 往后7小时，动态时间轴）
-fn get_hourly_stats(conn: &rusqlite::Connection) -> Result<Vec<TokenDataPoint>, String> {
+fn get_hourly_stats(conn: &rusqlite::Connection, token_source: Option<&str>) -> Result<Vec<TokenDataPoint>, String> {
     let now = Local::now();
 
     // 计算当前小时的时间戳（精确到小时，分钟、秒、纳秒设为0）
@@ -60,6 +63,7 @@ fn get_hourly_stats(conn: &rusqlite::Connection) -> Result<Vec<TokenDataPoint>,
             label: format!("{:02}:00", hour_time.hour()),
             tokens: 0,
             cache_read_tokens: 0,
+            cost: 0.0,
         });
     }
 
@@ -70,28 +74,31 @@ fn get_hourly_stats(conn: &rusqlite::Connection) -> Result<Vec<TokenDataPoint>,
             SELECT
                 CAST((timestamp - ?1) / 3600000 AS INTEGER) as hour,
                 SUM(input_tokens + output_tokens + cache_creation_input_tokens + cache_read_input_tokens) as tokens,
-                SUM(cache_read_input_tokens) as cache_read_tokens
+                SUM(cache_read_input_tokens) as cache_read_tokens,
+                COALESCE(SUM(cost), 0.0) as cost
             FROM request_logs
             WHERE timestamp >= ?1 AND timestamp < ?2
+              AND (?3 IS NULL OR token_source = ?3)
             GROUP BY hour
             "#,
         )
         .map_err(|e| format!("Failed to prepare hourly stats: {}", e))?;
 
     let rows = stmt
-        .query_map([start_timestamp, end_timestamp], |row| {
-            Ok((row.get::<_, i32>(0)?, row.get::<_, i32>(1)?, row.get::<_, i32>(2)?))
+        .query_map(rusqlite::params![start_timestamp, end_timestamp, token_source], |row| {
+            Ok((row.get::<_, i32>(0)?, row.get::<_, i32>(1)?, row.get::<_, i32>(2)?, row.get::<_, f64>(3)?))
         })
         .map_err(|e| format!("Failed to query hourly stats: {}", e))?;
 
     for row in rows {
-        let (hour, tokens, cache_read_tokens) = row.map_err(|e| format!("Failed to read row: {}", e))?;
+        let (hour, tokens, cache_read_tokens, cost) = row.map_err(|e| format!("Failed to read row: {}", e))?;
         // hour 是相对于 start_timestamp 的小时数，直接作为数组索引
         if hour >= 0 && hour < total_hours {
             let index = hour as usize;
             if index < data_points.len() {
                 data_points[index].tokens = tokens;
                 data_points[index].cache_read_tokens = cache_read_tokens;
+                data_points[index].cost = cost;
             }
         }
     }
@@ -100,7 +107,7 @@ fn get_hourly_stats(conn: &rusqlite::Connection) -> Result<Vec<TokenDataPoint>,
 }
 
 /// 获取按天统计的数据（前6天+今天，共7天）
-fn get_daily_stats(conn: &rusqlite::Connection) -> Result<Vec<TokenDataPoint>, String> {
+fn get_daily_stats(conn: &rusqlite::Connection, token_source: Option<&str>) -> Result<Vec<TokenDataPoint>, String> {
     let today_start = get_today_start()?;
     let six_days_ago = today_start - (6 * 86400000);
 
@@ -116,6 +123,7 @@ fn get_daily_stats(conn: &rusqlite::Connection) -> Result<Vec<TokenDataPoint>, S
             label: format!("{}月{}日", day_date.month(), day_date.day()),
             tokens: 0,
             cache_read_tokens: 0,
+            cost: 0.0,
         });
     }
 
@@ -126,25 +134,28 @@ fn get_daily_stats(conn: &rusqlite::Connection) -> Result<Vec<TokenDataPoint>, S
             SELECT
                 CAST((timestamp - ?1) / 86400000 AS INTEGER) as day,
                 SUM(input_tokens + output_tokens + cache_creation_input_tokens + cache_read_input_tokens) as tokens,
-                SUM(cache_read_input_tokens) as cache_read_tokens
+                SUM(cache_read_input_tokens) as cache_read_tokens,
+                COALESCE(SUM(cost), 0.0) as cost
             FROM request_logs
             WHERE timestamp >= ?1 AND timestamp < ?1 + 604800000
+              AND (?2 IS NULL OR token_source = ?2)
             GROUP BY day
             "#,
         )
         .map_err(|e| format!("Failed to prepare daily stats: {}", e))?;
 
     let rows = stmt
-        .query_map([six_days_ago], |row| {
-            Ok((row.get::<_, i32>(0)?, row.get::<_, i32>(1)?, row.get::<_, i32>(2)?))
+        .query_map(rusqlite::params![six_days_ago, token_source], |row| {
+            Ok((row.get::<_, i32>(0)?, row.get::<_, i32>(1)?, row.get::<_, i32>(2)?, row.get::<_, f64>(3)?))
         })
         .map_err(|e| format!("Failed to query daily stats: {}", e))?;
 
     for row in rows {
-        let (day, tokens, cache_read_tokens) = row.map_err(|e| format!("Failed to read row: {}", e))?;
+        let (day, tokens, cache_read_tokens, cost) = row.map_err(|e| format!("Failed to read row: {}", e))?;
         if day >= 0 && day < 7 {
             data_points[day as usize].tokens = tokens;
             data_points[day as usize].cache_read_tokens = cache_read_tokens;
+            data_points[day as usize].cost = cost;
         }
     }
 
@@ -152,7 +163,7 @@ fn get_daily_stats(conn: &rusqlite::Connection) -> Result<Vec<TokenDataPoint>, S
 }
 
 /// 获取按周统计的数据（最近4周）
-fn get_weekly_stats(conn: &rusqlite::Connection) -> Result<Vec<TokenDataPoint>, String> {
+fn get_weekly_stats(conn: &rusqlite::Connection, token_source: Option<&str>) -> Result<Vec<TokenDataPoint>, String> {
     let today_end = get_today_end()?;
     let four_weeks_ago = today_end - (28 * 86400000);
 
@@ -162,6 +173,7 @@ fn get_weekly_stats(conn: &rusqlite::Connection) -> Result<Vec<TokenDataPoint>,
             label: format!("第{}周", week),
             tokens: 0,
             cache_read_tokens: 0,
+            cost: 0.0,
         })
         .collect();
 
@@ -172,25 +184,28 @@ fn get_weekly_stats(conn: &rusqlite::Connection) -> Result<Vec<TokenDataPoint>,
             SELECT
                 CAST((timestamp - ?1) / 604800000 AS INTEGER) as week,
                 SUM(input_tokens + output_tokens + cache_creation_input_tokens + cache_read_input_tokens) as tokens,
-                SUM(cache_read_input_tokens) as cache_read_tokens
+                SUM(cache_read_input_tokens) as cache_read_tokens,
+                COALESCE(SUM(cost), 0.0) as cost
             FROM request_logs
             WHERE timestamp >= ?1 AND timestamp <= ?2
+              AND (?3 IS NULL OR token_source = ?3)
             GROUP BY week
             "#,
         )
         .map_err(|e| format!("Failed to prepare weekly stats: {}", e))?;
 
     let rows = stmt
-        .query_map([four_weeks_ago, today_end], |row| {
-            Ok((row.get::<_, i32>(0)?, row.get::<_, i32>(1)?, row.get::<_, i32>(2)?))
+        .query_map(rusqlite::params![four_weeks_ago, today_end, token_source], |row| {
+            Ok((row.get::<_, i32>(0)?, row.get::<_, i32>(1)?, row.get::<_, i32>(2)?, row.get::<_, f64>(3)?))
         })
         .map_err(|e| format!("Failed to query weekly stats: {}", e))?;
 
     for row in rows {
-        let (week, tokens, cache_read_tokens) = row.map_err(|e| format!("Failed to read row: {}", e))?;
+        let (week, tokens, cache_read_tokens, cost) = row.map_err(|e| format!("Failed to read row: {}", e))?;
         if week >= 0 && week <= 3 {
             data_points[week as usize].tokens = tokens;
             data_points[week as usize].cache_read_tokens = cache_read_tokens;
+            data_points[week as usize].cost = cost;
         }
     }
 
@@ -198,7 +213,7 @@ fn get_weekly_stats(conn: &rusqlite::Connection) -> Result<Vec<TokenDataPoint>,
 }
 
 /// 获取按月统计的数据（今年12个月）
-fn get_monthly_stats(conn: &rusqlite::Connection) -> Result<Vec<TokenDataPoint>, String> {
+fn get_monthly_stats(conn: &rusqlite::Connection, token_source: Option<&str>) -> Result<Vec<TokenDataPoint>, String> {
     let year_start = get_year_start()?;
 
     // 初始化12个月的数据点
@@ -207,6 +222,7 @@ fn get_monthly_stats(conn: &rusqlite::Connection) -> Result<Vec<TokenDataPoint>,
             label: format!("{}月", month),
             tokens: 0,
             cache_read_tokens: 0,
+            cost: 0.0,
         })
         .collect();
 
@@ -217,25 +233,28 @@ fn get_monthly_stats(conn: &rusqlite::Connection) -> Result<Vec<TokenDataPoint>,
             SELECT
                 CAST((timestamp - ?1) / 2592000000 AS INTEGER) as month,
                 SUM(input_tokens + output_tokens + cache_creation_input_tokens + cache_read_input_tokens) as tokens,
-                SUM(cache_read_input_tokens) as cache_read_tokens
+                SUM(cache_read_input_tokens) as cache_read_tokens,
+                COALESCE(SUM(cost), 0.0) as cost
             FROM request_logs
             WHERE timestamp >= ?1
+              AND (?2 IS NULL OR token_source = ?2)
             GROUP BY month
             "#,
         )
         .map_err(|e| format!("Failed to prepare monthly stats: {}", e))?;
 
     let rows = stmt
-        .query_map([year_start], |row| {
-            Ok((row.get::<_, i32>(0)?, row.get::<_, i32>(1)?, row.get::<_, i32>(2)?))
+        .query_map(rusqlite::params![year_start, token_source], |row| {
+            Ok((row.get::<_, i32>(0)?, row.get::<_, i32>(1)?, row.get::<_, i32>(2)?, row.get::<_, f64>(3)?))
         })
         .map_err(|e| format!("Failed to query monthly stats: {}", e))?;
 
     for row in rows {
-        let (month, tokens, cache_read_tokens) = row.map_err(|e| format!("Failed to read row: {}", e))?;
+        let (month, tokens, cache_read_tokens, cost) = row.map_err(|e| format!("Failed to read row: {}", e))?;
         if month >= 0 && month < 12 {
             data_points[month as usize].tokens = tokens;
             data_points[month as usize].cache_read_tokens = cache_read_tokens;
+            data_points[month as usize].cost = cost;
         }
     }
 
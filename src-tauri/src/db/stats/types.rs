@@ -8,6 +8,14 @@ pub struct DashboardStats {
     pub today_tokens: i32,
     pub total_requests: i32,
     pub total_tokens: i32,
+    pub today_estimated_tokens: i32,  // 今日 token 中本地估算（local_estimate/partial）的部分
+    pub total_estimated_tokens: i32,  // 总 token 中本地估算（local_estimate/partial）的部分
+    pub today_cost: f64,              // 今日成本（美元）
+    pub total_cost: f64,              // 总成本（美元）
+    pub today_errors: i32,            // 今日失败的请求数（包括未到达上游的请求）
+    pub total_errors: i32,            // 失败的请求总数
+    pub today_cache_hit_rate: f64,    // 今日响应缓存命中率（0-1）
+    pub total_cache_hit_rate: f64,    // 总响应缓存命中率（0-1）
 }
 
 /// Token 使用量数据点
@@ -16,6 +24,7 @@ pub struct TokenDataPoint {
     pub label: String,
     pub tokens: i32,
     pub cache_read_tokens: i32,  // 缓存命中的 token 数
+    pub cost: f64,               // 成本（美元）
 }
 
 /// 配置消耗排名数据
@@ -24,6 +33,21 @@ pub struct ProfileConsumption {
     pub profile_id: String,
     pub profile_name: String,
     pub total_tokens: i32,
+    pub total_cost: f64,  // 成本（美元）
+    pub total_requests: i32,
+    pub total_retries: i32,  // 重试次数（上游不稳定的配置会更高）
+    pub percentage: f32,
+    pub rank: i32,
+}
+
+/// 客户端 API Key 用量数据
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ClientKeyConsumption {
+    pub client_key_id: Option<String>,  // 为空表示未识别的客户端
+    pub client_key_name: String,
+    pub total_requests: i32,
+    pub total_tokens: i32,
+    pub total_cost: f64,
     pub percentage: f32,
     pub rank: i32,
 }
@@ -0,0 +1,29 @@
+// 配置用量汇总模块（用于预算检查）
+
+use crate::db::schema::get_db_path;
+
+/// 获取配置自指定时间起的用量，返回 (token 总数, 成本)
+pub async fn get_profile_usage_since(profile_id: &str, since: i64) -> Result<(i64, f64), String> {
+    let db_path = get_db_path();
+    let profile_id = profile_id.to_string();
+
+    tokio::task::spawn_blocking(move || {
+        let conn = rusqlite::Connection::open(&db_path)
+            .map_err(|e| format!("Failed to open database: {}", e))?;
+
+        conn.query_row(
+            r#"
+            SELECT
+                COALESCE(SUM(input_tokens + output_tokens + cache_creation_input_tokens + cache_read_input_tokens), 0),
+                COALESCE(SUM(cost), 0.0)
+            FROM request_logs
+            WHERE profile_id = ?1 AND timestamp >= ?2
+            "#,
+            rusqlite::params![&profile_id, since],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(|e| format!("Failed to query profile usage: {}", e))
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))?
+}
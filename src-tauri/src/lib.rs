@@ -42,6 +42,11 @@ pub fn run() {
           }
         }
 
+        // 加载价格表到内存（计算请求成本时使用）
+        if let Err(e) = db::init_model_prices().await {
+          log::warn!("Failed to load model prices: {}", e);
+        }
+
         // 清理超过30天的旧日志
         match db::cleanup_old_logs(30).await {
           Ok(count) => {
@@ -165,12 +170,30 @@ pub fn run() {
       commands::update_profile,
       commands::delete_profile,
       commands::activate_profile,
+      commands::get_all_pools,
+      commands::create_pool,
+      commands::update_pool,
+      commands::delete_pool,
+      commands::activate_pool,
+      commands::get_routing_rules,
+      commands::set_routing_rules,
+      commands::get_budgets,
+      commands::set_budgets,
+      commands::get_budget_status,
+      commands::get_model_prices,
+      commands::set_model_prices,
       commands::get_logs,
       commands::get_dashboard_stats,
       commands::get_token_stats,
       commands::get_profile_consumption_ranking,
       commands::get_proxy_api_key,
       commands::refresh_proxy_api_key,
+      commands::get_client_keys,
+      commands::create_client_key,
+      commands::update_client_key,
+      commands::revoke_client_key,
+      commands::delete_client_key,
+      commands::get_client_key_consumption,
       commands::get_auth_enabled,
       commands::set_auth_enabled,
       commands::get_proxy_server_url,
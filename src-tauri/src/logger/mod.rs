@@ -42,6 +42,133 @@ impl ModelMode {
     }
 }
 
+/// Token 数量来源
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenSource {
+    /// 上游返回的 usage（计费依据）
+    Upstream,
+    /// 上游未返回 usage，全部由本地分词器估算
+    LocalEstimate,
+    /// 上游只返回了部分 usage，缺失部分由本地估算补齐
+    Partial,
+    /// 没有 token 数（错误响应、透传请求等）
+    #[default]
+    None,
+}
+
+impl TokenSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TokenSource::Upstream => "upstream",
+            TokenSource::LocalEstimate => "local_estimate",
+            TokenSource::Partial => "partial",
+            TokenSource::None => "none",
+        }
+    }
+}
+
+impl From<&str> for TokenSource {
+    fn from(s: &str) -> Self {
+        match s {
+            "upstream" => TokenSource::Upstream,
+            "local_estimate" => TokenSource::LocalEstimate,
+            "partial" => TokenSource::Partial,
+            _ => TokenSource::None,
+        }
+    }
+}
+
+/// 流式响应的结束原因
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum CompletionReason {
+    /// 上游正常结束流
+    #[default]
+    Completed,
+    /// 上游流读取出错（连接中断等）
+    UpstreamError,
+    /// 上游长时间没有发送数据，超过空闲超时
+    IdleTimeout,
+    /// 流结束前客户端断开连接（如中途取消的对话）
+    ClientCancelled,
+}
+
+impl CompletionReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CompletionReason::Completed => "completed",
+            CompletionReason::UpstreamError => "upstream_error",
+            CompletionReason::IdleTimeout => "idle_timeout",
+            CompletionReason::ClientCancelled => "client_cancelled",
+        }
+    }
+}
+
+impl From<&str> for CompletionReason {
+    fn from(s: &str) -> Self {
+        match s {
+            "upstream_error" => CompletionReason::UpstreamError,
+            "idle_timeout" => CompletionReason::IdleTimeout,
+            "client_cancelled" => CompletionReason::ClientCancelled,
+            _ => CompletionReason::Completed,
+        }
+    }
+}
+
+/// 请求的处理结果（请求在哪个阶段结束）
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RequestOutcome {
+    /// 上游返回成功响应
+    #[default]
+    Ok,
+    /// 上游返回错误状态码
+    UpstreamStatus,
+    /// 无法连接上游（或读取上游响应失败）
+    ConnectError,
+    /// 上游响应超时
+    Timeout,
+    /// 客户端未通过访问授权
+    RejectedAuth,
+    /// 没有可用的 Profile
+    NoProfile,
+    /// 超出预算被拒绝
+    RejectedBudget,
+    /// 代理内部错误
+    InternalError,
+}
+
+impl RequestOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RequestOutcome::Ok => "ok",
+            RequestOutcome::UpstreamStatus => "upstream_status",
+            RequestOutcome::ConnectError => "connect_error",
+            RequestOutcome::Timeout => "timeout",
+            RequestOutcome::RejectedAuth => "rejected_auth",
+            RequestOutcome::NoProfile => "no_profile",
+            RequestOutcome::RejectedBudget => "rejected_budget",
+            RequestOutcome::InternalError => "internal_error",
+        }
+    }
+}
+
+impl From<&str> for RequestOutcome {
+    fn from(s: &str) -> Self {
+        match s {
+            "upstream_status" => RequestOutcome::UpstreamStatus,
+            "connect_error" => RequestOutcome::ConnectError,
+            "timeout" => RequestOutcome::Timeout,
+            "rejected_auth" => RequestOutcome::RejectedAuth,
+            "no_profile" => RequestOutcome::NoProfile,
+            "rejected_budget" => RequestOutcome::RejectedBudget,
+            "internal_error" => RequestOutcome::InternalError,
+            _ => RequestOutcome::Ok,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct RequestLog {
@@ -78,6 +205,37 @@ pub struct RequestLog {
     pub request_size_bytes: Option<i64>,    // 请求体大小
     pub response_size_bytes: Option<i64>,   // 响应体大小
     pub response_body: Option<String>,      // 响应体内容（仅在 output_tokens=0 时记录，用于调试）
+
+    // 故障转移与负载均衡
+    pub parent_request_id: Option<String>,  // 同一入站请求的多次尝试共享的父请求 ID
+    pub pool_id: Option<String>,            // 分配该请求的负载均衡池 ID（profile_id 即被选中的成员）
+
+    // 入站请求
+    pub endpoint: Option<String>,           // 请求的方法和路径（如 "GET /v1/models"）
+
+    // Token 计数
+    pub tokenizer: Option<String>,          // 本地计数使用的分词器（上游返回 usage 时为空）
+    pub token_source: String,               // Token 数量来源（upstream/local_estimate/partial/none）
+
+    // 成本
+    pub cost: Option<f64>,                  // 请求成本（美元，按价格表计算，无匹配价格时为空）
+
+    // 客户端
+    pub client_key_id: Option<String>,      // 发起请求的客户端 API Key ID（内置密钥为 "default"）
+
+    // 重试
+    pub attempts: i32,                      // 发送到上游的次数（含重试，1 表示未重试）
+
+    // 流式响应
+    pub completion_reason: Option<String>,  // 流式响应的结束原因（completed/upstream_error/idle_timeout/client_cancelled）
+
+    // 错误
+    pub error_type: Option<String>,         // 代理自身错误的类型（为空表示错误来自上游或请求成功）
+    pub outcome: String,                    // 请求的处理结果（ok/upstream_status/connect_error/timeout/rejected_auth/no_profile 等）
+
+    // 响应缓存
+    pub cache_status: Option<String>,       // 响应缓存状态（hit/miss/bypass，未启用缓存时为空）
+    pub cache_source_request_id: Option<String>,  // 命中缓存时，缓存响应来自的原始请求 ID
 }
 
 impl RequestLog {
@@ -122,6 +280,19 @@ impl RequestLog {
             request_size_bytes: Some(request_size as i64),
             response_size_bytes: None,
             response_body: None,
+            parent_request_id: None,
+            pool_id: None,
+            endpoint: None,
+            tokenizer: None,
+            token_source: TokenSource::None.as_str().to_string(),
+            cost: None,
+            client_key_id: None,
+            attempts: 1,
+            completion_reason: None,
+            error_type: None,
+            outcome: RequestOutcome::Ok.as_str().to_string(),
+            cache_status: None,
+            cache_source_request_id: None,
         }
     }
 }
@@ -137,7 +308,13 @@ fn extract_provider(api_base_url: &str) -> String {
 }
 
 // 保存日志到数据库并发送事件
-pub async fn save_log(log: RequestLog, app_handle: Option<&tauri::AppHandle>) {
+pub async fn save_log(mut log: RequestLog, app_handle: Option<&tauri::AppHandle>) {
+    // 代理自身的错误已标记处理结果，其余的错误状态码来自上游
+    if log.outcome == RequestOutcome::Ok.as_str() && log.status_code >= 400 {
+        log.outcome = RequestOutcome::UpstreamStatus.as_str().to_string();
+    }
+    log.cost = crate::db::calculate_log_cost(&log);
+
     let is_new = match crate::db::save_log_to_db(&log).await {
         Ok(is_new) => is_new,
         Err(e) => {
@@ -146,6 +323,11 @@ pub async fn save_log(log: RequestLog, app_handle: Option<&tauri::AppHandle>) {
         }
     };
 
+    // 重复保存的记录不重复计入预算用量
+    if is_new {
+        crate::proxy::record_budget_usage(&log);
+    }
+
     // 根据是否是新记录发送不同的事件
     if let Some(app) = app_handle {
         if is_new {
@@ -164,9 +346,15 @@ pub async fn save_log(log: RequestLog, app_handle: Option<&tauri::AppHandle>) {
 }
 
 // 更新日志到数据库（用于流式响应的 Token 统计更新）并发送事件
-pub async fn update_log(log: RequestLog, app_handle: Option<&tauri::AppHandle>) {
-    if let Err(e) = crate::db::update_log_to_db(&log).await {
-        log::error!("Failed to update log to database: {}", e);
+pub async fn update_log(mut log: RequestLog, app_handle: Option<&tauri::AppHandle>) {
+    // Token 数已更新，重新计算成本
+    log.cost = crate::db::calculate_log_cost(&log);
+
+    match crate::db::update_log_to_db(&log).await {
+        // 流式请求首次保存时 token 为 0，更新时计入完整用量
+        Ok(true) => crate::proxy::record_budget_usage(&log),
+        Ok(false) => log::error!("Log {} not found, token usage was not updated", log.request_id),
+        Err(e) => log::error!("Failed to update log to database: {}", e),
     }
 
     // 发送日志更新事件到前端
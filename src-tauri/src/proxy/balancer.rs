@@ -0,0 +1,215 @@
+// 负载均衡：为激活的负载均衡池选择处理请求的成员
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use lazy_static::lazy_static;
+use crate::config::{PoolMember, PoolStrategy, ProfilePool};
+
+/// 延迟指数加权移动平均的平滑系数
+const LATENCY_ALPHA: f64 = 0.3;
+
+/// 请求失败时计入的延迟惩罚（毫秒），避免快速失败的成员被最低延迟策略优先选中
+const FAILURE_PENALTY_MS: f64 = 30_000.0;
+
+lazy_static! {
+    static ref BALANCER: PoolBalancer = PoolBalancer::default();
+}
+
+/// 单个成员的运行时状态
+#[derive(Debug, Default)]
+struct MemberState {
+    /// 平滑加权轮询的当前权重
+    current_weight: i64,
+    /// 进行中的请求数
+    in_flight: u32,
+    /// 最近延迟（毫秒，指数加权移动平均）
+    latency_ms: Option<f64>,
+}
+
+/// 负载均衡器（运行时状态按 (池 ID, 配置 ID) 保存，不持久化）
+#[derive(Default)]
+pub(super) struct PoolBalancer {
+    states: Mutex<HashMap<(String, String), MemberState>>,
+}
+
+impl PoolBalancer {
+    /// 按池的策略选择一个成员，并占用一个并发名额
+    ///
+    /// 权重为 0 的成员视为停用，不参与选择。
+    fn select(&'static self, pool: &ProfilePool, members: &[PoolMember]) -> Option<PoolGuard> {
+        let candidates: Vec<&PoolMember> = members.iter().filter(|m| m.weight > 0).collect();
+        if candidates.is_empty() {
+            return None;
+        }
+
+        let mut states = self.states.lock().ok()?;
+        let key = |member: &PoolMember| (pool.id.clone(), member.profile_id.clone());
+
+        let chosen = match pool.strategy {
+            PoolStrategy::WeightedRoundRobin => {
+                // 平滑加权轮询（与 nginx 相同）：每轮所有成员累加自身权重，选出当前权重最大者后减去总权重
+                let total: i64 = candidates.iter().map(|m| m.weight as i64).sum();
+                let mut best: Option<(&PoolMember, i64)> = None;
+                for member in &candidates {
+                    let state = states.entry(key(member)).or_default();
+                    state.current_weight += member.weight as i64;
+                    if best.map_or(true, |(_, weight)| state.current_weight > weight) {
+                        best = Some((member, state.current_weight));
+                    }
+                }
+                let (member, _) = best?;
+                states.entry(key(member)).or_default().current_weight -= total;
+                member
+            }
+            PoolStrategy::LeastInFlight => {
+                // 并发相同时优先选择权重更高的成员
+                *candidates.iter().min_by_key(|member| {
+                    let in_flight = states.get(&key(member)).map_or(0, |s| s.in_flight);
+                    (in_flight, std::cmp::Reverse(member.weight))
+                })?
+            }
+            PoolStrategy::LowestLatency => {
+                // 尚无延迟样本的成员优先，以便尽快收集数据
+                *candidates.iter().min_by(|a, b| {
+                    let latency_a = states.get(&key(a)).and_then(|s| s.latency_ms).unwrap_or(0.0);
+                    let latency_b = states.get(&key(b)).and_then(|s| s.latency_ms).unwrap_or(0.0);
+                    latency_a.total_cmp(&latency_b)
+                })?
+            }
+        };
+
+        let chosen_key = key(chosen);
+        states.entry(chosen_key.clone()).or_default().in_flight += 1;
+
+        Some(PoolGuard {
+            balancer: self,
+            pool_id: chosen_key.0,
+            profile_id: chosen_key.1,
+        })
+    }
+
+    /// 更新成员延迟
+    fn record_latency(&self, pool_id: &str, profile_id: &str, latency_ms: f64) {
+        if let Ok(mut states) = self.states.lock() {
+            let state = states
+                .entry((pool_id.to_string(), profile_id.to_string()))
+                .or_default();
+            state.latency_ms = Some(match state.latency_ms {
+                Some(previous) => previous * (1.0 - LATENCY_ALPHA) + latency_ms * LATENCY_ALPHA,
+                None => latency_ms,
+            });
+        }
+    }
+
+    /// 释放并发名额
+    fn release(&self, pool_id: &str, profile_id: &str) {
+        if let Ok(mut states) = self.states.lock() {
+            if let Some(state) = states.get_mut(&(pool_id.to_string(), profile_id.to_string())) {
+                state.in_flight = state.in_flight.saturating_sub(1);
+            }
+        }
+    }
+}
+
+/// 被选中成员的并发占用凭证，释放时自动减少进行中的请求数
+pub(super) struct PoolGuard {
+    balancer: &'static PoolBalancer,
+    pool_id: String,
+    profile_id: String,
+}
+
+impl PoolGuard {
+    /// 分配该请求的池 ID
+    pub(super) fn pool_id(&self) -> &str {
+        &self.pool_id
+    }
+
+    /// 被选中的成员配置 ID
+    pub(super) fn profile_id(&self) -> &str {
+        &self.profile_id
+    }
+
+    /// 记录一次成功响应的延迟
+    pub(super) fn record_latency(&self, latency: Duration) {
+        self.balancer.record_latency(&self.pool_id, &self.profile_id, latency.as_millis() as f64);
+    }
+
+    /// 记录一次失败（计入延迟惩罚）
+    pub(super) fn record_failure(&self) {
+        self.balancer.record_latency(&self.pool_id, &self.profile_id, FAILURE_PENALTY_MS);
+    }
+}
+
+impl Drop for PoolGuard {
+    fn drop(&mut self) {
+        self.balancer.release(&self.pool_id, &self.profile_id);
+    }
+}
+
+/// 从激活的池中选择一个成员（members 应只包含仍然存在的配置）
+pub(super) fn select_member(pool: &ProfilePool, members: &[PoolMember]) -> Option<PoolGuard> {
+    BALANCER.select(pool, members)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool(strategy: PoolStrategy, weights: &[u32]) -> (ProfilePool, Vec<PoolMember>) {
+        let members: Vec<PoolMember> = weights
+            .iter()
+            .enumerate()
+            .map(|(i, weight)| PoolMember { profile_id: format!("p{}", i), weight: *weight })
+            .collect();
+        let pool = ProfilePool::new("test".to_string(), strategy, members.clone());
+        (pool, members)
+    }
+
+    fn balancer() -> &'static PoolBalancer {
+        Box::leak(Box::new(PoolBalancer::default()))
+    }
+
+    #[test]
+    fn test_weighted_round_robin() {
+        let balancer = balancer();
+        let (pool, members) = pool(PoolStrategy::WeightedRoundRobin, &[3, 1, 0]);
+
+        let picks: Vec<String> = (0..8)
+            .map(|_| balancer.select(&pool, &members).unwrap().profile_id().to_string())
+            .collect();
+
+        assert_eq!(picks.iter().filter(|p| *p == "p0").count(), 6);
+        assert_eq!(picks.iter().filter(|p| *p == "p1").count(), 2);
+        // 平滑轮询不会连续把低权重成员的份额挤在一起
+        assert_eq!(&picks[..4], &["p0", "p0", "p1", "p0"]);
+    }
+
+    #[test]
+    fn test_least_in_flight() {
+        let balancer = balancer();
+        let (pool, members) = pool(PoolStrategy::LeastInFlight, &[1, 1]);
+
+        let first = balancer.select(&pool, &members).unwrap();
+        let second = balancer.select(&pool, &members).unwrap();
+        assert_ne!(first.profile_id(), second.profile_id());
+
+        let released = first.profile_id().to_string();
+        drop(first);
+        let third = balancer.select(&pool, &members).unwrap();
+        assert_eq!(third.profile_id(), released);
+    }
+
+    #[test]
+    fn test_lowest_latency() {
+        let balancer = balancer();
+        let (pool, members) = pool(PoolStrategy::LowestLatency, &[1, 1]);
+
+        balancer.select(&pool, &members).unwrap().record_latency(Duration::from_millis(900));
+        balancer.select(&pool, &members).unwrap().record_latency(Duration::from_millis(100));
+
+        for _ in 0..3 {
+            assert_eq!(balancer.select(&pool, &members).unwrap().profile_id(), "p1");
+        }
+    }
+}
@@ -0,0 +1,240 @@
+// 用量预算：按配置累计当前周期的用量，超出预算时警告、故障转移或拒绝请求
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use chrono::{DateTime, Datelike, Duration, Local, TimeZone};
+use lazy_static::lazy_static;
+use tauri::Emitter;
+use crate::config::{BudgetAction, BudgetMetric, BudgetPeriod, Profile, ProfileBudget};
+use crate::logger::RequestLog;
+
+lazy_static! {
+    static ref TRACKER: BudgetTracker = BudgetTracker::default();
+}
+
+/// 某个配置在一个周期内的累计用量
+#[derive(Debug, Clone, Copy)]
+struct UsageWindow {
+    /// 周期起点（毫秒时间戳）
+    start: i64,
+    tokens: i64,
+    cost: f64,
+}
+
+/// 用量缓存（按 (配置 ID, 周期) 保存）
+///
+/// 每个周期首次检查时从 request_logs 汇总一次，之后随日志保存增量累加，不必每次请求都扫描日志表。
+#[derive(Default)]
+struct BudgetTracker {
+    windows: Mutex<HashMap<(String, BudgetPeriod), UsageWindow>>,
+    /// 已发送过超出事件的预算：预算 ID → 周期起点（每个周期只通知一次）
+    notified: Mutex<HashMap<String, i64>>,
+}
+
+/// 预算的当前状态
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BudgetStatus {
+    pub budget_id: String,
+    pub profile_id: String,
+    pub period: BudgetPeriod,
+    pub metric: BudgetMetric,
+    pub action: BudgetAction,
+    pub limit: f64,
+    /// 当前周期已用量（token 数或美元）
+    pub used: f64,
+    /// 当前周期起点（毫秒时间戳）
+    pub period_start: i64,
+    pub exceeded: bool,
+}
+
+/// 计算周期起点（本地时间）
+fn period_start(period: BudgetPeriod, now: DateTime<Local>) -> Result<i64, String> {
+    let date = now.date_naive();
+    let first_day = match period {
+        BudgetPeriod::Daily => date,
+        BudgetPeriod::Weekly => date - Duration::days(date.weekday().num_days_from_monday() as i64),
+        BudgetPeriod::Monthly => date.with_day(1).unwrap_or(date),
+    };
+    let midnight = first_day.and_hms_opt(0, 0, 0)
+        .ok_or_else(|| "Failed to create period start".to_string())?;
+    Local
+        .from_local_datetime(&midnight)
+        .earliest()
+        .map(|dt| dt.timestamp_millis())
+        .ok_or_else(|| "Failed to create period start timestamp".to_string())
+}
+
+impl BudgetTracker {
+    /// 获取配置在当前周期的用量，缓存缺失或已跨周期时从数据库重新汇总
+    async fn usage(&self, profile_id: &str, period: BudgetPeriod) -> Result<UsageWindow, String> {
+        let start = period_start(period, Local::now())?;
+        let key = (profile_id.to_string(), period);
+
+        if let Some(window) = self.windows.lock().map_err(|e| e.to_string())?.get(&key) {
+            if window.start == start {
+                return Ok(*window);
+            }
+        }
+
+        let (tokens, cost) = crate::db::get_profile_usage_since(profile_id, start).await?;
+        let window = UsageWindow { start, tokens, cost };
+
+        let mut windows = self.windows.lock().map_err(|e| e.to_string())?;
+        // 汇总期间其他请求可能已经建立了同一周期的缓存，以先建立的为准
+        let entry = windows.entry(key).or_insert(window);
+        if entry.start != start {
+            *entry = window;
+        }
+        Ok(*entry)
+    }
+
+    /// 累加一条日志的用量（只更新已缓存的当前周期）
+    fn record(&self, log: &RequestLog) {
+        let tokens = (log.input_tokens
+            + log.output_tokens
+            + log.cache_creation_input_tokens
+            + log.cache_read_input_tokens) as i64;
+        let cost = log.cost.unwrap_or(0.0);
+        if tokens == 0 && cost == 0.0 {
+            return;
+        }
+
+        if let Ok(mut windows) = self.windows.lock() {
+            for ((profile_id, _), window) in windows.iter_mut() {
+                if profile_id == &log.profile_id && log.timestamp >= window.start {
+                    window.tokens += tokens;
+                    window.cost += cost;
+                }
+            }
+        }
+    }
+
+    /// 计算预算的当前状态
+    async fn status(&self, budget: &ProfileBudget) -> Result<BudgetStatus, String> {
+        let window = self.usage(&budget.profile_id, budget.period).await?;
+        let used = match budget.metric {
+            BudgetMetric::Tokens => window.tokens as f64,
+            BudgetMetric::Cost => window.cost,
+        };
+
+        Ok(BudgetStatus {
+            budget_id: budget.id.clone(),
+            profile_id: budget.profile_id.clone(),
+            period: budget.period,
+            metric: budget.metric,
+            action: budget.action,
+            limit: budget.limit,
+            used,
+            period_start: window.start,
+            exceeded: used >= budget.limit,
+        })
+    }
+
+    /// 每个预算在每个周期只返回一次 true
+    fn should_notify(&self, status: &BudgetStatus) -> bool {
+        match self.notified.lock() {
+            Ok(mut notified) => notified.insert(status.budget_id.clone(), status.period_start) != Some(status.period_start),
+            Err(_) => false,
+        }
+    }
+}
+
+/// 日志保存后累加用量（由 logger 调用）
+pub fn record_budget_usage(log: &RequestLog) {
+    TRACKER.record(log);
+}
+
+/// 获取预算的当前状态
+pub async fn get_budget_status(budgets: &[ProfileBudget]) -> Result<Vec<BudgetStatus>, String> {
+    let mut statuses = Vec::with_capacity(budgets.len());
+    for budget in budgets {
+        statuses.push(TRACKER.status(budget).await?);
+    }
+    Ok(statuses)
+}
+
+/// 按预算过滤候选配置链
+///
+/// 超出 failover 预算的配置被跳过；链首配置超出 reject 预算，或所有配置都被跳过时返回超出的预算，
+/// 由调用方拒绝请求。超出 warn 预算只记录警告。首次超出时发送 budget-exceeded 事件。
+pub(super) async fn apply_budgets(
+    budgets: &[ProfileBudget],
+    chain: Vec<Profile>,
+    app_handle: &tauri::AppHandle,
+) -> Result<Vec<Profile>, BudgetStatus> {
+    let budgets: Vec<&ProfileBudget> = budgets.iter().filter(|b| b.enabled).collect();
+    if budgets.is_empty() {
+        return Ok(chain);
+    }
+
+    let mut allowed = Vec::with_capacity(chain.len());
+    let mut last_breach = None;
+
+    for profile in chain {
+        let mut breach: Option<BudgetStatus> = None;
+
+        for budget in budgets.iter().filter(|b| b.profile_id == profile.id) {
+            let status = match TRACKER.status(budget).await {
+                Ok(status) => status,
+                Err(e) => {
+                    // 用量查询失败时不阻塞请求
+                    log::error!("Failed to check budget {}: {}", budget.id, e);
+                    continue;
+                }
+            };
+            if !status.exceeded {
+                continue;
+            }
+
+            if TRACKER.should_notify(&status) {
+                log::warn!("💸 Budget exceeded: {} {} {} ({:.2}/{:.2}) → {}",
+                    profile.name, status.period.as_str(), status.metric.as_str(),
+                    status.used, status.limit, status.action.as_str());
+                if let Err(e) = app_handle.emit("budget-exceeded", &status) {
+                    log::error!("Failed to emit budget-exceeded event: {}", e);
+                }
+            }
+            // 同一配置有多个预算超出时取最严格的处理方式
+            if breach.as_ref().map_or(true, |b| status.action > b.action) {
+                breach = Some(status);
+            }
+        }
+
+        match breach {
+            None => allowed.push(profile),
+            Some(status) if status.action == BudgetAction::Warn => {
+                log::warn!("⚠️  Profile {} is over budget ({} {})", profile.name, status.period.as_str(), status.metric.as_str());
+                allowed.push(profile);
+            }
+            Some(status) if status.action == BudgetAction::Reject && allowed.is_empty() => {
+                return Err(status);
+            }
+            Some(status) => {
+                log::warn!("⏭️  Skipping over-budget profile: {}", profile.name);
+                last_breach = Some(status);
+            }
+        }
+    }
+
+    match last_breach {
+        Some(status) if allowed.is_empty() => Err(status),
+        _ => Ok(allowed),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_period_start() {
+        // 2025-06-18 是周三
+        let now = Local.with_ymd_and_hms(2025, 6, 18, 15, 30, 0).unwrap();
+        let expect = |y, m, d| Local.with_ymd_and_hms(y, m, d, 0, 0, 0).unwrap().timestamp_millis();
+
+        assert_eq!(period_start(BudgetPeriod::Daily, now).unwrap(), expect(2025, 6, 18));
+        assert_eq!(period_start(BudgetPeriod::Weekly, now).unwrap(), expect(2025, 6, 16));
+        assert_eq!(period_start(BudgetPeriod::Monthly, now).unwrap(), expect(2025, 6, 1));
+    }
+}
@@ -0,0 +1,142 @@
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use std::time::Instant;
+use crate::config::{CountTokensMode, SharedConfigManager, UpstreamProtocol};
+use crate::logger::RequestLog;
+use super::error::{unrouted_log, ProxyError, ProxyErrorKind};
+use super::handler::{authorize, build_upstream_headers, extract_error_message, no_active_profile_error, select_chain, spawn_save_log, upstream_auth_query};
+use super::http_client::{self, RequestTimeouts};
+use super::request::{body_for_model, MessagesRequest};
+use super::token_counter::TokenCounter;
+
+const ENDPOINT: &str = "POST /v1/messages/count_tokens";
+
+/// 处理 /v1/messages/count_tokens 请求
+///
+/// 按 Profile 的 count_tokens_mode 决定转发到上游还是在本地用 TokenCounter 估算。
+/// 自动模式下上游返回 404/501（不支持该接口）时回退到本地计算；
+/// OpenAI 协议的上游没有对应接口，始终在本地计算。
+pub(super) async fn handle_count_tokens(
+    State((config, app_handle)): State<(SharedConfigManager, tauri::AppHandle)>,
+    headers: HeaderMap,
+    body: String,
+) -> Result<Response, ProxyError> {
+    let start_time = Instant::now();
+    log::info!("🔢 Count tokens request");
+
+    let request = MessagesRequest::parse(&body);
+    let original_model = request.as_ref()
+        .map(|request| request.model().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+
+    let unrouted = |error: ProxyError| {
+        error.save_log(unrouted_log(ENDPOINT, &original_model, body.len()), start_time, &app_handle);
+        error
+    };
+
+    let client_key_id = authorize(&config, &headers).map_err(unrouted)?;
+
+    let (chain, _pool_guard, routed_model) = select_chain(&config, &original_model).map_err(unrouted)?;
+    let profile = chain.into_iter().next().ok_or_else(|| unrouted(no_active_profile_error()))?;
+
+    // 应用模型映射，保证上游看到的是实际转发的模型
+    let mapped_model = profile.resolve_model(&routed_model);
+    let upstream_body = body_for_model(request.as_ref(), &body, &mapped_model);
+
+    let mut request_log = RequestLog::new(
+        profile.id.clone(),
+        profile.name.clone(),
+        original_model,
+        crate::logger::ModelMode::from_mapping_mode(&profile.model_mapping_mode),
+        mapped_model,
+        profile.api_base_url.clone(),
+        upstream_body.len(),
+    );
+    request_log.endpoint = Some(ENDPOINT.to_string());
+    request_log.client_key_id = client_key_id;
+
+    let forward = match profile.count_tokens_mode {
+        CountTokensMode::Local => false,
+        CountTokensMode::Upstream => true,
+        CountTokensMode::Auto => profile.upstream_protocol == UpstreamProtocol::Anthropic,
+    };
+
+    if forward {
+        let client = match http_client::client_for(&profile) {
+            Ok(client) => client,
+            Err(e) => {
+                log::error!("{}", e);
+                let error = ProxyError::new(ProxyErrorKind::Internal, e);
+                error.save_log(request_log, start_time, &app_handle);
+                return Err(error);
+            }
+        };
+        // count_tokens 很快返回，上游无响应时尽快回退到本地计数
+        let timeouts = RequestTimeouts {
+            total: Some(std::time::Duration::from_secs(30)),
+            ..RequestTimeouts::for_profile(&profile.timeouts, false)
+        };
+
+        let upstream_url = format!("{}/v1/messages/count_tokens", profile.api_base_url);
+        let request = client
+            .post(&upstream_url)
+            .query(&upstream_auth_query(&profile))
+            .headers(build_upstream_headers(&headers, &profile))
+            .body(upstream_body.clone());
+        let result = client.send(request, timeouts).await;
+
+        match result {
+            Ok(response) => {
+                let status = response.status();
+                let unsupported = status == StatusCode::NOT_FOUND || status == StatusCode::NOT_IMPLEMENTED;
+
+                if !(unsupported && profile.count_tokens_mode == CountTokensMode::Auto) {
+                    let mut response_headers = HeaderMap::new();
+                    if let Some(content_type) = response.headers().get(reqwest::header::CONTENT_TYPE) {
+                        response_headers.insert(axum::http::header::CONTENT_TYPE, content_type.clone());
+                    }
+                    let response_body = response.text().await.unwrap_or_default();
+
+                    request_log.duration_ms = start_time.elapsed().as_millis() as i64;
+                    request_log.status_code = status.as_u16() as i32;
+                    if !status.is_success() {
+                        request_log.error_message = Some(extract_error_message(&response_body));
+                        request_log.response_body = Some(response_body.clone());
+                    }
+                    spawn_save_log(request_log, &app_handle);
+
+                    return Ok((status, response_headers, response_body).into_response());
+                }
+
+                log::info!("Upstream does not support count_tokens ({}), counting locally", status);
+            }
+            Err(e) => {
+                log::error!("Failed to forward count_tokens request: {}", e);
+                let error = ProxyError::upstream(e.is_timeout(), e.to_string());
+                error.save_log(request_log, start_time, &app_handle);
+                return Err(error);
+            }
+        }
+    }
+
+    // 本地计算（按转发的模型选择分词器）
+    let Some(counter) = TokenCounter::for_model(&request_log.forwarded_model) else {
+        log::error!("Failed to initialize token counter");
+        let error = ProxyError::new(ProxyErrorKind::Internal, "Failed to initialize token counter");
+        error.save_log(request_log, start_time, &app_handle);
+        return Err(error);
+    };
+    let input_tokens = counter.count_input_tokens(&upstream_body);
+    log::info!("🔢 Local count ({}): {} input tokens", counter.tokenizer().as_str(), input_tokens);
+
+    request_log.tokenizer = Some(counter.tokenizer().as_str().to_string());
+    request_log.duration_ms = start_time.elapsed().as_millis() as i64;
+    request_log.status_code = StatusCode::OK.as_u16() as i32;
+    spawn_save_log(request_log, &app_handle);
+
+    Ok(Json(serde_json::json!({ "input_tokens": input_tokens })).into_response())
+}
@@ -0,0 +1,163 @@
+// 代理自身产生的错误：以 Anthropic 格式返回给客户端，并写入请求日志
+
+use axum::{
+    http::{HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+use std::time::Instant;
+use crate::logger::{ModelMode, RequestLog, RequestOutcome};
+use super::handler::spawn_save_log;
+
+/// 标记错误由代理生成（而不是上游返回）的响应头，值为错误类型
+pub(super) const PROXY_ERROR_HEADER: &str = "x-prism-error";
+
+/// 代理自身错误的类型
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum ProxyErrorKind {
+    /// 客户端未通过访问授权
+    Unauthorized,
+    /// 没有可用的 Profile（未激活配置或负载均衡池没有可用成员）
+    NoActiveProfile,
+    /// 超出 Profile 的预算
+    BudgetExceeded,
+    /// 无法连接上游或读取上游响应失败
+    UpstreamFailed,
+    /// 上游响应超时
+    UpstreamTimeout,
+    /// 代理内部错误（配置锁异常、HTTP 客户端创建失败等）
+    Internal,
+}
+
+impl ProxyErrorKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProxyErrorKind::Unauthorized => "unauthorized",
+            ProxyErrorKind::NoActiveProfile => "no_active_profile",
+            ProxyErrorKind::BudgetExceeded => "budget_exceeded",
+            ProxyErrorKind::UpstreamFailed => "upstream_failed",
+            ProxyErrorKind::UpstreamTimeout => "upstream_timeout",
+            ProxyErrorKind::Internal => "internal",
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ProxyErrorKind::Unauthorized => StatusCode::UNAUTHORIZED,
+            ProxyErrorKind::NoActiveProfile => StatusCode::SERVICE_UNAVAILABLE,
+            ProxyErrorKind::BudgetExceeded => StatusCode::TOO_MANY_REQUESTS,
+            ProxyErrorKind::UpstreamFailed => StatusCode::BAD_GATEWAY,
+            ProxyErrorKind::UpstreamTimeout => StatusCode::GATEWAY_TIMEOUT,
+            ProxyErrorKind::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// 对应的请求处理结果
+    pub fn outcome(&self) -> RequestOutcome {
+        match self {
+            ProxyErrorKind::Unauthorized => RequestOutcome::RejectedAuth,
+            ProxyErrorKind::NoActiveProfile => RequestOutcome::NoProfile,
+            ProxyErrorKind::BudgetExceeded => RequestOutcome::RejectedBudget,
+            ProxyErrorKind::UpstreamFailed => RequestOutcome::ConnectError,
+            ProxyErrorKind::UpstreamTimeout => RequestOutcome::Timeout,
+            ProxyErrorKind::Internal => RequestOutcome::InternalError,
+        }
+    }
+
+    /// 对应的 Anthropic 错误类型
+    fn error_type(&self) -> &'static str {
+        match self {
+            ProxyErrorKind::Unauthorized => "authentication_error",
+            ProxyErrorKind::NoActiveProfile | ProxyErrorKind::BudgetExceeded => "overloaded_error",
+            ProxyErrorKind::UpstreamFailed
+            | ProxyErrorKind::UpstreamTimeout
+            | ProxyErrorKind::Internal => "api_error",
+        }
+    }
+}
+
+/// 代理自身产生的错误
+#[derive(Debug, Clone)]
+pub(super) struct ProxyError {
+    pub kind: ProxyErrorKind,
+    pub message: String,
+}
+
+impl ProxyError {
+    pub fn new(kind: ProxyErrorKind, message: impl Into<String>) -> Self {
+        Self { kind, message: message.into() }
+    }
+
+    /// 发送上游请求失败（按是否超时区分）
+    pub fn upstream(is_timeout: bool, message: impl Into<String>) -> Self {
+        let kind = if is_timeout { ProxyErrorKind::UpstreamTimeout } else { ProxyErrorKind::UpstreamFailed };
+        Self::new(kind, message)
+    }
+
+    /// 将错误信息写入请求日志
+    fn fill_log(&self, request_log: &mut RequestLog) {
+        request_log.status_code = self.kind.status().as_u16() as i32;
+        request_log.error_message = Some(self.message.clone());
+        request_log.error_type = Some(self.kind.as_str().to_string());
+        request_log.outcome = self.kind.outcome().as_str().to_string();
+    }
+
+    /// 在后台保存该错误的请求日志
+    pub fn save_log(&self, mut request_log: RequestLog, start_time: Instant, app_handle: &tauri::AppHandle) {
+        request_log.duration_ms = start_time.elapsed().as_millis() as i64;
+        self.fill_log(&mut request_log);
+        spawn_save_log(request_log, app_handle);
+    }
+}
+
+impl IntoResponse for ProxyError {
+    fn into_response(self) -> Response {
+        let mut response = error_response(self.kind.status(), self.kind.error_type(), &self.message);
+        response.headers_mut().insert(PROXY_ERROR_HEADER, HeaderValue::from_static(self.kind.as_str()));
+        response
+    }
+}
+
+/// 构建 Anthropic 格式的错误响应：{"type":"error","error":{"type":...,"message":...}}
+fn error_response(status: StatusCode, error_type: &str, message: &str) -> Response {
+    let body = serde_json::json!({
+        "type": "error",
+        "error": {
+            "type": error_type,
+            "message": message,
+        }
+    });
+    (status, axum::Json(body)).into_response()
+}
+
+/// 还未选中 Profile 的请求日志（鉴权失败、没有可用配置等）
+pub(super) fn unrouted_log(endpoint: &str, original_model: &str, request_size: usize) -> RequestLog {
+    let mut request_log = RequestLog::new(
+        String::new(),
+        String::new(),
+        original_model.to_string(),
+        ModelMode::Passthrough,
+        String::new(),
+        String::new(),
+        request_size,
+    );
+    request_log.endpoint = Some(endpoint.to_string());
+    request_log
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_proxy_error_response() {
+        let response = ProxyError::upstream(true, "upstream timed out").into_response();
+        assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+        assert_eq!(response.headers()[PROXY_ERROR_HEADER], "upstream_timeout");
+
+        let mut request_log = unrouted_log("POST /v1/messages", "claude-sonnet-4", 0);
+        ProxyError::new(ProxyErrorKind::Unauthorized, "Invalid API key").fill_log(&mut request_log);
+        assert_eq!(request_log.status_code, 401);
+        assert_eq!(request_log.error_type.as_deref(), Some("unauthorized"));
+        assert_eq!(request_log.outcome, "rejected_auth");
+    }
+}
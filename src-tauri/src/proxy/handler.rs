@@ -4,9 +4,17 @@ use axum::{
     response::{IntoResponse, Response},
 };
 use std::time::Instant;
-use crate::config::SharedConfigManager;
-use crate::logger::RequestLog;
-use super::stream::handle_stream_response;
+use crate::config::{HeaderAction, HeaderRule, Profile, SharedConfigManager, UpstreamAuthScheme, UpstreamProtocol};
+use crate::logger::{RequestLog, TokenSource};
+use super::balancer::{self, PoolGuard};
+use super::http_client::{self, RequestTimeouts};
+use super::budget::{self, BudgetStatus};
+use super::error::{unrouted_log, ProxyError, ProxyErrorKind};
+use super::openai;
+use super::request::{self, MessagesRequest};
+use super::response_cache::{self, CacheStatus};
+use super::stream::{handle_stream_response, StreamContext};
+use super::token_counter::TokenCounter;
 use super::utils::convert_headers;
 
 /// 处理 /v1/messages 请求
@@ -14,212 +22,199 @@ pub(super) async fn handle_messages(
     State((config, app_handle)): State<(SharedConfigManager, tauri::AppHandle)>,
     headers: HeaderMap,
     body: String,
-) -> Result<Response, StatusCode> {
+) -> Result<Response, ProxyError> {
     let start_time = Instant::now();
     log::info!("\n{}\n🚀 New Request to /v1/messages\n{}", "=".repeat(60), "=".repeat(60));
 
-    // API Key 鉴权检查
-    {
-        let config_guard = config.read().map_err(|e| {
-            log::error!("Failed to acquire config read lock: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
-
-        // 如果启用了访问授权，则验证 API Key
-        if config_guard.is_auth_enabled() {
-            let auth_header = headers.get(axum::http::header::AUTHORIZATION);
-
-            let api_key = match auth_header {
-                Some(value) => {
-                    let auth_str = value.to_str().map_err(|_| {
-                        log::warn!("Invalid Authorization header format");
-                        StatusCode::UNAUTHORIZED
-                    })?;
-
-                    // 支持 "Bearer sk-xxx" 格式
-                    if let Some(key) = auth_str.strip_prefix("Bearer ") {
-                        key
-                    } else {
-                        log::warn!("Authorization header missing 'Bearer ' prefix");
-                        return Err(StatusCode::UNAUTHORIZED);
-                    }
-                }
-                None => {
-                    log::warn!("Missing Authorization header");
-                    return Err(StatusCode::UNAUTHORIZED);
-                }
-            };
+    // 解析请求体（只解析一次，路由、流式判断和日志都基于它，每个 Profile 再各自应用模型映射）
+    let request = MessagesRequest::parse(&body);
+    let original_model = request.as_ref()
+        .map(|request| request.model().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
 
-            // 验证 API Key
-            if !config_guard.verify_api_key(api_key) {
-                log::warn!("Invalid API key: {}", api_key);
-                return Err(StatusCode::UNAUTHORIZED);
-            }
-
-            log::debug!("API key verified successfully");
-        }
-    }
-
-    // 从配置中获取激活的 Profile
-    let profile = {
-        let config_guard = config.read().map_err(|e| {
-            log::error!("Failed to acquire config read lock: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
-
-        config_guard.get_active_profile().cloned().ok_or_else(|| {
-            log::error!("No active profile found");
-            StatusCode::SERVICE_UNAVAILABLE
-        })?
+    // 选中 Profile 之前的错误没有对应的上游请求，单独记录日志
+    let unrouted = |error: ProxyError| {
+        error.save_log(unrouted_log("POST /v1/messages", &original_model, body.len()), start_time, &app_handle);
+        error
     };
 
-    log::info!("📋 Profile: {}", profile.name);
-
-    // 解析请求体以获取模型信息并应用模型映射
-    let (original_model, mapped_model, modified_body, user_prompt) = if let Ok(mut json) = serde_json::from_str::<serde_json::Value>(&body) {
-        let original = json.get("model")
-            .and_then(|m| m.as_str())
-            .unwrap_or("unknown")
-            .to_string();
-
-        // 提取用户 prompt（取最后一条用户消息）
-        let prompt = json.get("messages")
-            .and_then(|m| m.as_array())
-            .and_then(|arr| arr.iter().rev().find(|msg| {
-                msg.get("role").and_then(|r| r.as_str()) == Some("user")
-            }))
-            .and_then(|msg| msg.get("content"))
-            .and_then(|c| {
-                if let Some(s) = c.as_str() {
-                    Some(s.to_string())
-                } else if let Some(arr) = c.as_array() {
-                    arr.iter()
-                        .find(|item| item.get("type").and_then(|t| t.as_str()) == Some("text"))
-                        .and_then(|item| item.get("text"))
-                        .and_then(|t| t.as_str())
-                        .map(|s| s.to_string())
-                } else {
-                    None
-                }
-            })
-            .unwrap_or_else(|| "N/A".to_string());
-
-        // 使用 Profile 的 resolve_model 方法进行模型映射
-        let mapped = profile.resolve_model(&original);
+    // API Key 鉴权检查
+    let client_key_id = authorize(&config, &headers).map_err(unrouted)?;
 
-        // 如果模型发生了映射，修改请求体中的 model 字段
-        if original != mapped {
-            json["model"] = serde_json::Value::String(mapped.clone());
-        }
+    // 从配置中获取故障转移链（激活的 Profile 在前，随后是备用配置）
+    let (chain, pool_guard, routed_model) = select_chain(&config, &original_model).map_err(unrouted)?;
 
-        let new_body = serde_json::to_string(&json).unwrap_or(body.clone());
-        (original, mapped, new_body, prompt)
-    } else {
-        let default_model = "unknown".to_string();
-        (default_model.clone(), default_model, body.clone(), "N/A".to_string())
-    };
+    // 按预算过滤故障转移链（超出预算的配置被跳过或直接拒绝请求）
+    let budgets = config.read()
+        .map(|manager| manager.get_budgets().to_vec())
+        .unwrap_or_default();
+    let chain = budget::apply_budgets(&budgets, chain, &app_handle).await
+        .map_err(|status| unrouted(budget_exceeded_error(&status)))?;
 
-    // 输出模型信息
-    if original_model != mapped_model {
-        log::info!("🤖 Model: {} → {}", original_model, mapped_model);
-    } else {
-        log::info!("🤖 Model: {}", original_model);
+    if chain.is_empty() {
+        return Err(unrouted(no_active_profile_error()));
     }
 
+    // 提取用户 prompt（取最后一条用户消息）
+    let user_prompt = request.as_ref()
+        .and_then(|request| request.last_user_prompt())
+        .unwrap_or("N/A");
+
     // 输出用户 prompt（截断显示，使用字节数粗略判断避免遍历整个字符串）
     let prompt_preview = if user_prompt.len() > 600 {
         // 字节数超过 600，安全截取前 200 个字符
         user_prompt.chars().take(200).collect::<String>() + "..."
     } else {
-        user_prompt.clone()
+        user_prompt.to_string()
     };
     log::info!("💬 Prompt: {}", prompt_preview);
 
-    // 计算请求体大小（在移动之前）
-    let request_size = modified_body.len();
-
     // 检查是否是流式请求
-    let is_stream = modified_body.contains("\"stream\":true") || modified_body.contains("\"stream\": true");
+    let is_stream = request.as_ref().is_some_and(|request| request.is_stream());
     log::debug!("Request is streaming: {}", is_stream);
+    if let Some(request) = request.as_ref() {
+        log::debug!("Request: max_tokens={:?}, tools={}, thinking={}, metadata={}",
+            request.max_tokens,
+            request.tools.as_ref().map_or(0, |tools| tools.len()),
+            request.thinking.is_some(),
+            request.metadata.is_some());
+    }
 
-    // 构建上游 API URL
-    let upstream_url = format!("{}/v1/messages", profile.api_base_url);
-    log::debug!("Forwarding to: {}", upstream_url);
-
-    // 创建 HTTP 客户端（设置 60 秒超时）
-    // reqwest 默认启用所有解压功能（gzip, deflate, br, zstd）
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(60))
-        .connect_timeout(std::time::Duration::from_secs(10))
-        .build()
-        .map_err(|e| {
-            log::error!("Failed to create HTTP client: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+    // 响应缓存（流式请求缓存完整的事件序列，命中时回放）
+    let cache_status = response_cache::status_for(&headers);
 
-    // 准备请求头，添加 API Key
-    let mut request_headers = convert_headers(&headers);
+    // 同一入站请求的所有尝试共享父请求 ID，便于在日志中追踪故障转移
+    let context = RequestContext {
+        original_model: original_model.clone(),
+        parent_request_id: uuid::Uuid::new_v4().to_string(),
+        pool_id: pool_guard.as_ref().map(|guard| guard.pool_id().to_string()),
+        client_key_id,
+    };
 
-    // 设置 Authorization 头（Bearer token）
-    if !profile.api_key.is_empty() {
-        let auth_value = format!("Bearer {}", profile.api_key);
-        if let Ok(header_value) = reqwest::header::HeaderValue::from_str(&auth_value) {
-            request_headers.insert(reqwest::header::AUTHORIZATION, header_value);
-        }
-    }
+    // 按顺序尝试故障转移链中的 Profile，直到拿到可以返回给客户端的响应
+    let mut selected = None;
+    for (index, profile) in chain.iter().enumerate() {
+        let has_next = index + 1 < chain.len();
+        log::info!("📋 Profile: {}", profile.name);
 
-    // 确保必要的头存在
-    if !request_headers.contains_key(reqwest::header::CONTENT_TYPE) {
-        request_headers.insert(
-            reqwest::header::CONTENT_TYPE,
-            reqwest::header::HeaderValue::from_static("application/json"),
-        );
-    }
+        let mut prepared = prepare_upstream_request(profile, request.as_ref(), &body, &routed_model);
 
-    // 设置更真实的 User-Agent 以避免被 Cloudflare 拦截
-    if !request_headers.contains_key(reqwest::header::USER_AGENT) {
-        request_headers.insert(
-            reqwest::header::USER_AGENT,
-            reqwest::header::HeaderValue::from_static(
-                "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36"
-            ),
-        );
-    }
+        // 输出模型信息
+        if original_model != prepared.mapped_model {
+            log::info!("🤖 Model: {} → {}", original_model, prepared.mapped_model);
+        } else {
+            log::info!("🤖 Model: {}", original_model);
+        }
+        log::debug!("Forwarding to: {}", prepared.upstream_url);
+
+        // 相同的请求直接返回缓存的响应，不请求上游
+        if cache_status == Some(CacheStatus::Miss) {
+            let key = response_cache::cache_key(&profile.id, &prepared.modified_body);
+            if let Some(cached) = response_cache::get(&key) {
+                log::info!("💾 Served from response cache (original request: {}) | {}ms",
+                    cached.request_id, start_time.elapsed().as_millis());
+                log::info!("{}\n", "=".repeat(60));
+                let mut request_log = new_request_log(profile, &context, &prepared);
+                request_log.duration_ms = start_time.elapsed().as_millis() as i64;
+                request_log.status_code = StatusCode::OK.as_u16() as i32;
+                request_log.is_stream = is_stream;
+                request_log.response_size_bytes = Some(cached.body.size() as i64);
+                request_log.cache_status = Some(CacheStatus::Hit.as_str().to_string());
+                request_log.cache_source_request_id = Some(cached.request_id);
+                spawn_save_log(request_log, &app_handle);
+                return Ok(response_cache::hit_response(cached.body));
+            }
+            prepared.cache_key = Some(key);
+        }
 
-    // 移除可能导致问题的头
-    request_headers.remove(reqwest::header::HOST);
-    request_headers.remove("connection");
-    request_headers.remove("x-api-key");  // 移除测试占位符
-    request_headers.remove("content-length");  // reqwest 会自动计算
+        let request_headers = build_upstream_headers(&headers, profile);
 
-    // 转发请求到上游 API（使用修改后的请求体）
-    log::debug!("Sending request to upstream...");
+        // 转发请求到上游 API（使用修改后的请求体）
+        log::debug!("Sending request to upstream...");
 
-    // 克隆请求体用于后续的 token 计数
-    let request_body_for_counting = modified_body.clone();
-
-    let response = client
-        .post(&upstream_url)
-        .headers(request_headers)
-        .body(modified_body)
-        .send()
-        .await
-        .map_err(|e| {
-            log::error!("Failed to forward request: {}", e);
-            log::error!("Error details: {:?}", e);
-            if e.is_timeout() {
-                log::error!("Request timed out");
+        // 复用该 Profile 的 HTTP 客户端
+        let client = match http_client::client_for(profile) {
+            Ok(client) => client,
+            Err(e) => {
+                log::error!("{}", e);
+                let error = ProxyError::new(ProxyErrorKind::Internal, e);
+                error.save_log(new_request_log(profile, &context, &prepared), start_time, &app_handle);
+                return Err(error);
             }
-            if e.is_connect() {
-                log::error!("Connection error");
+        };
+        let timeouts = RequestTimeouts::for_profile(&profile.timeouts, is_stream);
+
+        let attempt_start = Instant::now();
+        let upstream_request = client
+            .post(&prepared.upstream_url)
+            .query(&upstream_auth_query(profile))
+            .headers(request_headers)
+            .body(prepared.upstream_body.clone());
+        let (result, attempts) = client.send_with_retry(upstream_request, timeouts, &profile.retry).await;
+        prepared.attempts = attempts;
+
+        // 更新被选中的池成员的延迟统计
+        if let Some(guard) = pool_guard.as_ref().filter(|guard| guard.profile_id() == profile.id) {
+            match &result {
+                Ok(response) if !is_failover_status(response.status()) => {
+                    guard.record_latency(attempt_start.elapsed());
+                }
+                _ => guard.record_failure(),
             }
-            StatusCode::BAD_GATEWAY
-        })?;
+        }
+
+        match result {
+            Ok(response) if has_next && is_failover_status(response.status()) => {
+                let status = response.status();
+                let error_body = response.text().await.unwrap_or_default();
+                log::warn!("⚠️  Upstream returned {}, failing over to next profile", status);
+
+                let mut attempt_log = new_request_log(profile, &context, &prepared);
+                attempt_log.duration_ms = start_time.elapsed().as_millis() as i64;
+                attempt_log.status_code = status.as_u16() as i32;
+                attempt_log.is_stream = is_stream;
+                attempt_log.error_message = Some(extract_error_message(&error_body));
+                attempt_log.response_body = Some(error_body);
+                spawn_save_log(attempt_log, &app_handle);
+            }
+            Ok(response) => {
+                selected = Some((profile.clone(), prepared, response));
+                break;
+            }
+            Err(e) => {
+                log::error!("Failed to forward request: {}", e);
+                log::error!("Error details: {:?}", e);
+                if e.is_timeout() {
+                    log::error!("Request timed out");
+                }
+                if e.is_connect() {
+                    log::error!("Connection error");
+                }
+
+                let error = ProxyError::upstream(e.is_timeout(), e.to_string());
+                let mut attempt_log = new_request_log(profile, &context, &prepared);
+                attempt_log.is_stream = is_stream;
+                error.save_log(attempt_log, start_time, &app_handle);
+
+                if !has_next {
+                    return Err(error);
+                }
+                log::warn!("⚠️  Failing over to next profile");
+            }
+        }
+    }
+
+    let (profile, prepared, response) = selected
+        .ok_or_else(|| ProxyError::new(ProxyErrorKind::UpstreamFailed, "No upstream response"))?;
 
     log::debug!("Received response from upstream");
 
     let status = response.status();
 
+    // 克隆请求体用于后续的 token 计数
+    let request_body_for_counting = prepared.modified_body.clone();
+    let mapped_model = prepared.mapped_model.clone();
+
     // 获取响应头（移除压缩和传输编码相关的头）
     let mut response_headers = HeaderMap::new();
     for (key, value) in response.headers().iter() {
@@ -232,43 +227,51 @@ pub(super) async fn handle_messages(
         response_headers.insert(key.clone(), value.clone());
     }
 
+    // OpenAI 上游的流式请求出错时返回的是普通 JSON，走非流式路径转换错误格式
+    let is_openai_stream_error = profile.upstream_protocol == UpstreamProtocol::OpenAI && !status.is_success();
+
     // 如果是流式响应，使用流式处理
-    if is_stream {
+    if is_stream && !is_openai_stream_error {
         log::info!("⚡ Streaming response started...");
 
         // 创建日志记录（流式响应的 Token 统计会在流结束后更新）
-        let mut request_log = RequestLog::new(
-            profile.id.clone(),
-            profile.name.clone(),
-            original_model.clone(),
-            crate::logger::ModelMode::from_mapping_mode(&profile.model_mapping_mode),
-            mapped_model.clone(),
-            profile.api_base_url.clone(),
-            request_size,
-        );
+        let mut request_log = new_request_log(&profile, &context, &prepared);
         request_log.duration_ms = start_time.elapsed().as_millis() as i64;
         request_log.status_code = status.as_u16() as i32;
         request_log.is_stream = true;
+        request_log.cache_status = cache_status.map(|status| status.as_str().to_string());
 
-        // 先保存基础日志（Token 为 0），后续会通过 UPDATE 更新
-        let log_clone = request_log.clone();
-        let app_handle_clone = app_handle.clone();
-        tokio::spawn(async move {
-            crate::logger::save_log(log_clone, Some(&app_handle_clone)).await;
-        });
+        // 先保存基础日志（Token 为 0），流结束后等这次保存完成再 UPDATE
+        let initial_save = spawn_save_log(request_log.clone(), &app_handle);
 
         // 传递 request_log 和 request_body 给 stream handler，它会在流结束后 UPDATE
-        return handle_stream_response(response, request_log, start_time, request_body_for_counting, app_handle).await;
+        let stream_context = StreamContext {
+            request_log,
+            start_time,
+            request_body: request_body_for_counting,
+            initial_save,
+            cache_key: prepared.cache_key.clone().filter(|_| status.is_success()),
+        };
+        let mut response = handle_stream_response(response, stream_context, &profile, pool_guard, app_handle).await;
+        if let Some(status_header) = cache_status {
+            response.headers_mut().insert(response_cache::CACHE_STATUS_HEADER, status_header.header_value());
+        }
+        return Ok(response);
     }
 
     // 非流式响应，直接返回
     log::debug!("Reading response body...");
 
     // 先读取为字节，以便处理可能的编码问题
-    let response_bytes = response.bytes().await.map_err(|e| {
-        log::error!("Failed to read response bytes: {}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
+    let response_bytes = match response.bytes().await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            log::error!("Failed to read response bytes: {}", e);
+            let error = ProxyError::upstream(e.is_timeout(), format!("Failed to read upstream response: {}", e));
+            error.save_log(new_request_log(&profile, &context, &prepared), start_time, &app_handle);
+            return Err(error);
+        }
+    };
 
     log::debug!("Response body length: {} bytes", response_bytes.len());
 
@@ -281,17 +284,34 @@ pub(super) async fn handle_messages(
         }
     };
 
+    // OpenAI 上游的响应转换回 Anthropic 格式，客户端无需感知
+    let response_body = if profile.upstream_protocol == UpstreamProtocol::OpenAI {
+        match serde_json::from_str::<serde_json::Value>(&response_body) {
+            Ok(json) if status.is_success() => openai::convert_response(&json, &mapped_model).to_string(),
+            Ok(json) => openai::convert_error(&json, status.as_u16()).to_string(),
+            Err(_) => response_body,
+        }
+    } else {
+        response_body
+    };
+
     // 克隆响应体用于后台处理，立即返回响应
     let response_body_clone = response_body.clone();
-    let profile_id = profile.id.clone();
-    let profile_name = profile.name.clone();
-    let profile_api_base_url = profile.api_base_url.clone();
-    let model_mapping_mode = profile.model_mapping_mode.clone();
+    let mut request_log = new_request_log(&profile, &context, &prepared);
+    request_log.cache_status = cache_status.map(|status| status.as_str().to_string());
+
+    // 成功的响应写入缓存
+    if let Some(status_header) = cache_status {
+        if let Some(key) = prepared.cache_key.clone().filter(|_| status.is_success()) {
+            response_cache::put_json(key, response_body.clone(), request_log.request_id.clone());
+        }
+        response_headers.insert(response_cache::CACHE_STATUS_HEADER, status_header.header_value());
+    }
     let app_handle_clone = app_handle.clone();
 
     // 在后台异步解析 token 和保存日志，完全不阻塞响应返回
     tokio::spawn(async move {
-        let (input_tokens, output_tokens, response_body_to_save, error_message) = if let Ok(json) = serde_json::from_str::<serde_json::Value>(&response_body_clone) {
+        let (input_tokens, output_tokens, token_source, response_body_to_save, error_message) = if let Ok(json) = serde_json::from_str::<serde_json::Value>(&response_body_clone) {
             // 检查是否是错误响应
             if status.is_client_error() || status.is_server_error() {
                 // 提取错误信息
@@ -304,7 +324,7 @@ pub(super) async fn handle_messages(
                 log::error!("❌ Error response: {}", error_msg);
 
                 // 错误响应保存完整响应体
-                (0, 0, Some(response_body_clone.clone()), Some(error_msg.to_string()))
+                (0, 0, TokenSource::None, Some(response_body_clone.clone()), Some(error_msg.to_string()))
             } else {
                 // 正常响应，提取响应内容
                 let response_text = json.get("content")
@@ -346,6 +366,24 @@ pub(super) async fn handle_messages(
                     })
                     .unwrap_or(0) as i32;
 
+                // 提示词缓存的 token 单独计价，命中缓存时 input_tokens 可能为 0
+                let cache_token = |field: &str| json.get("usage")
+                    .and_then(|u| u.get(field))
+                    .and_then(|t| t.as_i64())
+                    .unwrap_or(0) as i32;
+                request_log.cache_creation_input_tokens = cache_token("cache_creation_input_tokens");
+                request_log.cache_read_input_tokens = cache_token("cache_read_input_tokens");
+
+                // 上游未返回（或只返回部分）usage 时用本地分词器补齐
+                let has_input = input > 0
+                    || request_log.cache_creation_input_tokens > 0
+                    || request_log.cache_read_input_tokens > 0;
+                let (input, output, source) = if has_input && output > 0 {
+                    (input, output, TokenSource::Upstream)
+                } else {
+                    fill_missing_usage(input, output, has_input, &json, &request_body_for_counting, &mut request_log)
+                };
+
                 // 如果 output_tokens 为 0，保存完整响应体用于调试
                 let body_to_save = if output == 0 {
                     log::warn!("⚠️  Output tokens is 0, saving full response body for debugging");
@@ -354,12 +392,12 @@ pub(super) async fn handle_messages(
                     None
                 };
 
-                (input, output, body_to_save, None)
+                (input, output, source, body_to_save, None)
             }
         } else {
             log::warn!("Failed to parse response body as JSON, saving full response body for debugging");
             // JSON 解析失败，保存完整响应体
-            (0, 0, Some(response_body_clone.clone()), Some("Failed to parse response as JSON".to_string()))
+            (0, 0, TokenSource::None, Some(response_body_clone.clone()), Some("Failed to parse response as JSON".to_string()))
         };
 
         // 计算耗时
@@ -372,17 +410,9 @@ pub(super) async fn handle_messages(
 
         // 记录日志（使用新的字段）
         let response_size = response_body_clone.len();
-        let mut request_log = RequestLog::new(
-            profile_id,
-            profile_name,
-            original_model.clone(),
-            crate::logger::ModelMode::from_mapping_mode(&model_mapping_mode),
-            mapped_model.clone(),
-            profile_api_base_url,
-            request_size,
-        );
         request_log.input_tokens = input_tokens;
         request_log.output_tokens = output_tokens;
+        request_log.token_source = token_source.as_str().to_string();
         request_log.duration_ms = duration_ms;
         request_log.status_code = status.as_u16() as i32;
         request_log.is_stream = false;
@@ -398,3 +428,396 @@ pub(super) async fn handle_messages(
     let response = (status, response_headers, response_body).into_response();
     Ok(response)
 }
+
+/// API Key 鉴权检查（启用访问授权时验证客户端携带的 API Key）
+///
+/// 同时支持 Anthropic SDK 使用的 `x-api-key` 头和 `Authorization: Bearer`。
+/// 返回识别出的客户端密钥 ID，用于在日志中区分不同的使用者；
+/// 鉴权失败时返回 Unauthorized 类型的 ProxyError（Anthropic 格式的 authentication_error 响应）。
+pub(super) fn authorize(config: &SharedConfigManager, headers: &HeaderMap) -> Result<Option<String>, ProxyError> {
+    let now = chrono::Utc::now().timestamp_millis();
+    let config_guard = config.read().map_err(|e| config_lock_error(&e))?;
+
+    let api_key = extract_client_api_key(headers);
+
+    let client_key_id = match api_key {
+        Some((scheme, api_key)) => config_guard.verify_api_key(api_key, now).map_err(|reason| {
+            // 不记录密钥内容
+            log::warn!("API key rejected ({}): {}", scheme, reason);
+            ProxyError::new(ProxyErrorKind::Unauthorized, reason)
+        })?,
+        None if config_guard.is_auth_enabled() => {
+            log::warn!("Missing API key (expected x-api-key or Authorization: Bearer header)");
+            return Err(ProxyError::new(
+                ProxyErrorKind::Unauthorized,
+                "Missing API key: provide it in the x-api-key header or as Authorization: Bearer",
+            ));
+        }
+        None => None,
+    };
+
+    let needs_touch = client_key_id.as_deref()
+        .is_some_and(|id| config_guard.client_key_needs_touch(id, now));
+    drop(config_guard);
+
+    // 记录最近使用时间（有刷新间隔，不会每个请求都写数据库）
+    if let Some(id) = client_key_id.as_deref().filter(|_| needs_touch) {
+        if let Ok(mut manager) = config.write() {
+            if manager.touch_client_key(id, now) {
+                let id = id.to_string();
+                tokio::spawn(async move {
+                    if let Err(e) = crate::db::touch_client_key_in_db(&id, now).await {
+                        log::error!("Failed to update client key last used time: {}", e);
+                    }
+                });
+            }
+        }
+    }
+
+    if client_key_id.is_some() {
+        log::debug!("API key verified successfully");
+    }
+
+    Ok(client_key_id)
+}
+
+/// 获取配置锁失败
+fn config_lock_error(e: &dyn std::fmt::Display) -> ProxyError {
+    log::error!("Failed to acquire config read lock: {}", e);
+    ProxyError::new(ProxyErrorKind::Internal, "Internal server error")
+}
+
+/// 故障转移链为空（没有激活的 Profile，或负载均衡池没有可用成员）
+pub(super) fn no_active_profile_error() -> ProxyError {
+    log::error!("No active profile found");
+    ProxyError::new(ProxyErrorKind::NoActiveProfile, "No active profile: activate a profile or pool in the app")
+}
+
+/// 从请求头中取出客户端 API Key，返回 (来源, 密钥)
+///
+/// 优先使用 `x-api-key`，其次是 `Authorization: Bearer`（scheme 不区分大小写）。
+fn extract_client_api_key(headers: &HeaderMap) -> Option<(&'static str, &str)> {
+    if let Some(key) = headers.get("x-api-key").and_then(|v| v.to_str().ok()) {
+        let key = key.trim();
+        if !key.is_empty() {
+            return Some(("x-api-key", key));
+        }
+    }
+
+    let auth_str = headers.get(axum::http::header::AUTHORIZATION)?.to_str().ok()?;
+    let (scheme, key) = auth_str.trim().split_once(' ')?;
+    if !scheme.eq_ignore_ascii_case("bearer") || key.trim().is_empty() {
+        return None;
+    }
+    Some(("bearer", key.trim()))
+}
+
+/// 选择处理请求的故障转移链，返回 (链, 负载均衡池占用, 路由后的模型名称)
+///
+/// 模型命中路由表时使用规则指定的 Profile；否则如果激活的是负载均衡池，
+/// 则先按策略选出成员，再使用该成员的故障转移链；都没有时使用激活的 Profile。
+pub(super) fn select_chain(
+    config: &SharedConfigManager,
+    model: &str,
+) -> Result<(Vec<Profile>, Option<PoolGuard>, String), ProxyError> {
+    let config_guard = config.read().map_err(|e| config_lock_error(&e))?;
+
+    if let Some((rule, routed_model)) = config_guard.route_model(model) {
+        log::info!("🧭 Route: {} ({}) → {}", rule.pattern, rule.match_type.as_str(), rule.profile_id);
+        return Ok((config_guard.get_chain_for(&rule.profile_id), None, routed_model));
+    }
+
+    let (chain, pool_guard) = match config_guard.get_active_pool() {
+        Some(pool) => {
+            let members: Vec<_> = pool.members.iter()
+                .filter(|m| config_guard.get_profile(&m.profile_id).is_some())
+                .cloned()
+                .collect();
+
+            match balancer::select_member(pool, &members) {
+                Some(guard) => {
+                    log::info!("🎯 Pool: {} ({})", pool.name, pool.strategy.as_str());
+                    (config_guard.get_chain_for(guard.profile_id()), Some(guard))
+                }
+                None => {
+                    log::error!("No available member in pool: {}", pool.name);
+                    (Vec::new(), None)
+                }
+            }
+        }
+        None => (config_guard.get_profile_chain(), None),
+    };
+
+    Ok((chain, pool_guard, model.to_string()))
+}
+
+/// 同一入站请求在多次尝试之间共享的信息
+struct RequestContext {
+    /// 客户端请求的原始模型
+    original_model: String,
+    /// 父请求 ID（故障转移时各次尝试共享）
+    parent_request_id: String,
+    /// 分配该请求的负载均衡池 ID
+    pool_id: Option<String>,
+    /// 发起请求的客户端 API Key ID
+    client_key_id: Option<String>,
+}
+
+/// 针对某个 Profile 准备好的上游请求
+struct PreparedRequest {
+    /// 映射后的模型
+    mapped_model: String,
+    /// 应用模型映射后的 Anthropic 格式请求体（用于本地 token 计数）
+    modified_body: String,
+    /// 上游 API URL
+    upstream_url: String,
+    /// 实际发送给上游的请求体（按上游协议转换）
+    upstream_body: String,
+    /// 发送到上游的次数（含重试）
+    attempts: u32,
+    /// 响应缓存键（未启用缓存或客户端要求跳过时为空）
+    cache_key: Option<String>,
+}
+
+/// 为指定 Profile 应用模型映射，并按上游协议构建 URL 和请求体
+fn prepare_upstream_request(
+    profile: &Profile,
+    request: Option<&MessagesRequest>,
+    body: &str,
+    routed_model: &str,
+) -> PreparedRequest {
+    // 使用 Profile 的 resolve_model 方法进行模型映射
+    let mapped_model = match request {
+        Some(_) => profile.resolve_model(routed_model),
+        None => routed_model.to_string(),
+    };
+    // 只有模型发生了映射（或路由时去掉了前缀）才改写请求体，否则原样转发
+    let modified_body = request::body_for_model(request, body, &mapped_model);
+
+    // 根据上游协议构建 URL 和请求体
+    let (upstream_url, upstream_body) = match profile.upstream_protocol {
+        UpstreamProtocol::Anthropic => {
+            (format!("{}/v1/messages", profile.api_base_url), modified_body.clone())
+        }
+        UpstreamProtocol::OpenAI => {
+            let converted = serde_json::from_str::<serde_json::Value>(&modified_body)
+                .map(|json| openai::convert_request(&json).to_string())
+                .unwrap_or_else(|_| modified_body.clone());
+            (format!("{}/v1/chat/completions", profile.api_base_url), converted)
+        }
+    };
+
+    PreparedRequest {
+        mapped_model,
+        modified_body,
+        upstream_url,
+        upstream_body,
+        attempts: 0,
+        cache_key: None,
+    }
+}
+
+/// 构建发往上游的请求头
+pub(super) fn build_upstream_headers(headers: &HeaderMap, profile: &Profile) -> reqwest::header::HeaderMap {
+    // 准备请求头，添加 API Key
+    let mut request_headers = convert_headers(headers);
+
+    // 客户端用于访问代理的密钥不能转发给上游
+    request_headers.remove(reqwest::header::AUTHORIZATION);
+    request_headers.remove("x-api-key");
+
+    // 按 Profile 的鉴权方式设置 API Key（查询参数方式见 upstream_auth_query）
+    if profile.is_api_key_locked() {
+        log::error!("API key of profile {} cannot be decrypted, re-enter it in the app", profile.name);
+    } else if !profile.api_key.is_empty() {
+        let auth_header = match profile.auth_scheme {
+            UpstreamAuthScheme::Bearer => Some((
+                reqwest::header::AUTHORIZATION.as_str(),
+                format!("Bearer {}", profile.api_key),
+            )),
+            UpstreamAuthScheme::XApiKey => Some(("x-api-key", profile.api_key.clone())),
+            UpstreamAuthScheme::Header => Some((
+                profile.auth_param.as_deref().filter(|p| !p.is_empty()).unwrap_or("api-key"),
+                profile.api_key.clone(),
+            )),
+            UpstreamAuthScheme::Query | UpstreamAuthScheme::None => None,
+        };
+        if let Some((name, value)) = auth_header {
+            match (
+                reqwest::header::HeaderName::from_bytes(name.as_bytes()),
+                reqwest::header::HeaderValue::from_str(&value),
+            ) {
+                (Ok(name), Ok(value)) => {
+                    request_headers.insert(name, value);
+                }
+                _ => log::warn!("Invalid upstream auth header for profile {}", profile.name),
+            }
+        }
+    }
+
+    // Anthropic 官方 API 要求 anthropic-version
+    if profile.auth_scheme == UpstreamAuthScheme::XApiKey && !request_headers.contains_key("anthropic-version") {
+        request_headers.insert("anthropic-version", reqwest::header::HeaderValue::from_static("2023-06-01"));
+    }
+
+    // 确保必要的头存在
+    if !request_headers.contains_key(reqwest::header::CONTENT_TYPE) {
+        request_headers.insert(
+            reqwest::header::CONTENT_TYPE,
+            reqwest::header::HeaderValue::from_static("application/json"),
+        );
+    }
+
+    // 设置更真实的 User-Agent 以避免被 Cloudflare 拦截
+    if !request_headers.contains_key(reqwest::header::USER_AGENT) {
+        request_headers.insert(
+            reqwest::header::USER_AGENT,
+            reqwest::header::HeaderValue::from_static(
+                "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36"
+            ),
+        );
+    }
+
+    // 移除可能导致问题的头
+    request_headers.remove(reqwest::header::HOST);
+    request_headers.remove("connection");
+    request_headers.remove("content-length");  // reqwest 会自动计算
+
+    // OpenAI 兼容上游不认识 Anthropic 专用头
+    if profile.upstream_protocol == UpstreamProtocol::OpenAI {
+        request_headers.remove("anthropic-version");
+        request_headers.remove("anthropic-beta");
+    }
+
+    // 最后应用 Profile 的自定义请求头规则
+    apply_header_rules(&mut request_headers, &profile.header_rules);
+
+    request_headers
+}
+
+/// 按顺序应用请求头规则，无效的名称或值会被跳过
+fn apply_header_rules(request_headers: &mut reqwest::header::HeaderMap, rules: &[HeaderRule]) {
+    for rule in rules {
+        let Ok(name) = reqwest::header::HeaderName::from_bytes(rule.name.trim().as_bytes()) else {
+            log::warn!("Skipping invalid header rule name: {}", rule.name);
+            continue;
+        };
+
+        if rule.action == HeaderAction::Remove {
+            request_headers.remove(&name);
+            continue;
+        }
+        if rule.action == HeaderAction::Add && request_headers.contains_key(&name) {
+            continue;
+        }
+        match reqwest::header::HeaderValue::from_str(&rule.value) {
+            Ok(value) => {
+                request_headers.insert(name, value);
+            }
+            Err(_) => log::warn!("Skipping invalid header rule value for {}", rule.name),
+        }
+    }
+}
+
+/// 鉴权方式为查询参数时附加到上游 URL 的参数（其他方式返回空列表）
+pub(super) fn upstream_auth_query(profile: &Profile) -> Vec<(&str, &str)> {
+    if profile.auth_scheme != UpstreamAuthScheme::Query || profile.api_key.is_empty() || profile.is_api_key_locked() {
+        return Vec::new();
+    }
+    let name = profile.auth_param.as_deref().filter(|p| !p.is_empty()).unwrap_or("key");
+    vec![(name, profile.api_key.as_str())]
+}
+
+/// 判断上游响应状态是否应该触发故障转移（限流或服务端错误）
+fn is_failover_status(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+/// 创建某次尝试的日志记录
+fn new_request_log(profile: &Profile, context: &RequestContext, prepared: &PreparedRequest) -> RequestLog {
+    let mut request_log = RequestLog::new(
+        profile.id.clone(),
+        profile.name.clone(),
+        context.original_model.clone(),
+        crate::logger::ModelMode::from_mapping_mode(&profile.model_mapping_mode),
+        prepared.mapped_model.clone(),
+        profile.api_base_url.clone(),
+        prepared.modified_body.len(),
+    );
+    request_log.parent_request_id = Some(context.parent_request_id.clone());
+    request_log.pool_id = context.pool_id.clone();
+    request_log.client_key_id = context.client_key_id.clone();
+    request_log.endpoint = Some("POST /v1/messages".to_string());
+    request_log.attempts = prepared.attempts.max(1) as i32;
+    request_log
+}
+
+/// 非流式响应缺少 usage 时，用本地分词器估算缺失的输入/输出 token 数
+///
+/// 两项都缺失时记为 local_estimate，只缺一项时记为 partial；无法初始化分词器时保留上游给出的数值。
+fn fill_missing_usage(
+    input: i32,
+    output: i32,
+    has_input: bool,  // 上游给出了输入 token（包括提示词缓存的 token）
+    response: &serde_json::Value,
+    request_body: &str,
+    request_log: &mut RequestLog,
+) -> (i32, i32, TokenSource) {
+    let Some(counter) = TokenCounter::for_model(&request_log.forwarded_model) else {
+        log::error!("Failed to initialize token counter");
+        let source = if has_input || output > 0 { TokenSource::Upstream } else { TokenSource::None };
+        return (input, output, source);
+    };
+    let source = if has_input || output > 0 { TokenSource::Partial } else { TokenSource::LocalEstimate };
+    log::warn!("⚠️  Missing usage info from upstream API (in: {}, out: {}), using local token counting", input, output);
+    request_log.tokenizer = Some(counter.tokenizer().as_str().to_string());
+
+    let input = if has_input { input } else { counter.count_input_tokens(request_body) };
+    let output = if output > 0 {
+        output
+    } else {
+        let output_text: String = response.get("content")
+            .and_then(|c| c.as_array())
+            .map(|blocks| blocks.iter()
+                .filter_map(|block| block.get("text").or_else(|| block.get("thinking")))
+                .filter_map(|t| t.as_str())
+                .collect())
+            .unwrap_or_default();
+        counter.count_output_tokens(&output_text)
+    };
+    log::info!("🔢 Local count ({}) - input tokens: {}, output tokens: {}", counter.tokenizer().as_str(), input, output);
+    (input, output, source)
+}
+
+/// 超出预算时返回的错误（429 overloaded_error）
+fn budget_exceeded_error(status: &BudgetStatus) -> ProxyError {
+    let message = format!(
+        "Profile budget exceeded: {} {} usage {:.2} reached limit {:.2}",
+        status.period.as_str(), status.metric.as_str(), status.used, status.limit
+    );
+    log::error!("🚫 {}", message);
+
+    ProxyError::new(ProxyErrorKind::BudgetExceeded, message)
+}
+
+/// 在后台保存日志，不阻塞请求处理
+pub(super) fn spawn_save_log(request_log: RequestLog, app_handle: &tauri::AppHandle) -> tokio::task::JoinHandle<()> {
+    let app_handle = app_handle.clone();
+    tokio::spawn(async move {
+        crate::logger::save_log(request_log, Some(&app_handle)).await;
+    })
+}
+
+/// 从上游错误响应体中提取错误信息
+pub(super) fn extract_error_message(body: &str) -> String {
+    serde_json::from_str::<serde_json::Value>(body)
+        .ok()
+        .and_then(|json| {
+            json.get("error")
+                .and_then(|e| e.get("message"))
+                .and_then(|m| m.as_str())
+                .or_else(|| json.get("message").and_then(|m| m.as_str()))
+                .map(|m| m.to_string())
+        })
+        .unwrap_or_else(|| "Unknown error".to_string())
+}
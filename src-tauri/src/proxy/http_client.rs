@@ -0,0 +1,326 @@
+// 上游 HTTP 客户端：按 Profile 缓存复用，并应用 Profile 的超时和网络设置
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Mutex, RwLock};
+use std::time::Duration;
+use lazy_static::lazy_static;
+use crate::config::{Profile, ProfileTimeouts, RetryPolicy, UpstreamNetwork};
+use super::retry;
+
+const DEFAULT_CONNECT_SECS: u64 = 10;
+const DEFAULT_STREAM_FIRST_BYTE_SECS: u64 = 120;
+const DEFAULT_FIRST_BYTE_SECS: u64 = 600;
+const DEFAULT_IDLE_SECS: u64 = 120;
+const DEFAULT_TOTAL_SECS: u64 = 600;
+
+lazy_static! {
+    /// Profile ID → (构建客户端时的设置, 客户端)
+    static ref CLIENTS: Mutex<HashMap<String, (ClientSettings, UpstreamClient)>> = Mutex::new(HashMap::new());
+    /// 全局默认网络设置（来自代理服务器配置）
+    static ref DEFAULT_NETWORK: RwLock<UpstreamNetwork> = RwLock::new(UpstreamNetwork::default());
+}
+
+/// 影响客户端构建的设置，变化时需要重新构建客户端
+#[derive(Debug, Clone, PartialEq)]
+struct ClientSettings {
+    connect_timeout: Option<Duration>,
+    network: UpstreamNetwork,
+}
+
+impl ClientSettings {
+    fn from_profile(profile: &Profile) -> Self {
+        let network = match &profile.network {
+            Some(network) => network.clone(),
+            None => DEFAULT_NETWORK.read().map(|n| n.clone()).unwrap_or_default(),
+        };
+        Self {
+            connect_timeout: resolve(profile.timeouts.connect_secs, DEFAULT_CONNECT_SECS),
+            network,
+        }
+    }
+
+    fn build(&self) -> Result<UpstreamClient, String> {
+        // reqwest 默认启用所有解压功能（gzip, deflate, br, zstd）
+        let mut builder = reqwest::Client::builder()
+            .pool_idle_timeout(Duration::from_secs(90))
+            .tcp_keepalive(Duration::from_secs(60));
+        if let Some(timeout) = self.connect_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+
+        // 未设置代理地址时沿用系统代理环境变量（HTTP_PROXY 等）
+        let mut proxy_label = None;
+        if let Some(proxy_url) = self.network.proxy_url() {
+            let mut url = reqwest::Url::parse(proxy_url)
+                .map_err(|e| format!("Invalid proxy URL: {}", e))?;
+            proxy_label = Some(format!(
+                "{}://{}:{}",
+                url.scheme(),
+                url.host_str().unwrap_or_default(),
+                url.port_or_known_default().unwrap_or_default()
+            ));
+
+            // 认证信息写入 URL，HTTP 代理和 SOCKS5 代理都能识别
+            if let Some(username) = self.network.proxy_username.as_deref().filter(|u| !u.is_empty()) {
+                url.set_username(username)
+                    .and_then(|_| url.set_password(
+                        self.network.proxy_password.as_deref().filter(|p| !crate::db::is_encrypted(p)),
+                    ))
+                    .map_err(|_| "Invalid proxy URL: cannot set credentials".to_string())?;
+            }
+
+            let mut proxy = reqwest::Proxy::all(url.as_str())
+                .map_err(|e| format!("Invalid proxy URL: {}", e))?;
+            if let Some(no_proxy) = self.network.no_proxy.as_deref().filter(|n| !n.trim().is_empty()) {
+                proxy = proxy.no_proxy(reqwest::NoProxy::from_string(no_proxy));
+            }
+            builder = builder.proxy(proxy);
+        }
+
+        if let Some(path) = self.network.ca_cert_path.as_deref().filter(|p| !p.is_empty()) {
+            let pem = std::fs::read(path)
+                .map_err(|e| format!("Failed to read CA certificate {}: {}", path, e))?;
+            let certs = reqwest::Certificate::from_pem_bundle(&pem)
+                .map_err(|e| format!("Invalid CA certificate {}: {}", path, e))?;
+            for cert in certs {
+                builder = builder.add_root_certificate(cert);
+            }
+        }
+
+        if self.network.tls_skip_verify {
+            builder = builder.danger_accept_invalid_certs(true);
+        }
+
+        let client = builder.build().map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+        Ok(UpstreamClient { client, proxy_label })
+    }
+}
+
+/// 访问上游的 HTTP 客户端
+#[derive(Clone)]
+pub(super) struct UpstreamClient {
+    client: reqwest::Client,
+    /// 出站代理地址（不含认证信息，用于日志）
+    proxy_label: Option<String>,
+}
+
+impl UpstreamClient {
+    pub fn post(&self, url: &str) -> reqwest::RequestBuilder {
+        self.client.post(url)
+    }
+
+    pub fn request(&self, method: reqwest::Method, url: &str) -> reqwest::RequestBuilder {
+        self.client.request(method, url)
+    }
+
+    /// 按超时设置发送请求
+    ///
+    /// 总超时覆盖响应体的读取；首字节超时只限制等待响应头的时间。
+    pub async fn send(
+        &self,
+        request: reqwest::RequestBuilder,
+        timeouts: RequestTimeouts,
+    ) -> Result<reqwest::Response, SendError> {
+        let request = match timeouts.total {
+            Some(total) => request.timeout(total),
+            None => request,
+        };
+
+        // 查询参数鉴权时 URL 中带有 API Key，不能出现在日志和错误信息中
+        let send = async {
+            request.send().await.map_err(|e| {
+                let e = e.without_url();
+                match &self.proxy_label {
+                    // 经过代理时无法区分代理本身和隧道另一端的连接失败，统一归为代理错误
+                    Some(proxy) if e.is_connect() => {
+                        log::error!("🔌 Outbound proxy connection failed ({}): {}", proxy, e);
+                        SendError::Proxy { proxy: proxy.clone(), error: e }
+                    }
+                    _ => SendError::Request(e),
+                }
+            })
+        };
+
+        match timeouts.first_byte {
+            Some(first_byte) => tokio::time::timeout(first_byte, send)
+                .await
+                .unwrap_or(Err(SendError::FirstByteTimeout(first_byte))),
+            None => send.await,
+        }
+    }
+
+    /// 发送请求，遇到暂时性失败（连接失败、429、5xx、529）时按重试策略重试
+    ///
+    /// 在返回响应之前完成全部重试，因此不会出现已向客户端输出数据后再重试的情况。
+    /// 返回最后一次的结果和实际尝试次数。
+    pub async fn send_with_retry(
+        &self,
+        mut request: reqwest::RequestBuilder,
+        timeouts: RequestTimeouts,
+        policy: &RetryPolicy,
+    ) -> (Result<reqwest::Response, SendError>, u32) {
+        let mut attempt = 1;
+        loop {
+            // 请求体是内存中的字节，可以复制；复制失败时只发送一次
+            let next_request = if attempt < policy.max_attempts { request.try_clone() } else { None };
+            let result = self.send(request, timeouts).await;
+            let Some(next_request) = next_request else {
+                return (result, attempt);
+            };
+
+            let (reason, delay) = match &result {
+                Ok(response) if retry::is_retryable_status(response.status()) => (
+                    response.status().to_string(),
+                    retry::next_delay(policy, attempt, retry::retry_after(response.headers())),
+                ),
+                Err(e) if e.is_connect() => (e.to_string(), retry::next_delay(policy, attempt, None)),
+                _ => return (result, attempt),
+            };
+            let Some(delay) = delay else {
+                return (result, attempt);
+            };
+
+            log::warn!("🔁 Upstream failed ({}), retrying in {}ms (attempt {}/{})",
+                reason, delay.as_millis(), attempt + 1, policy.max_attempts);
+            drop(result);
+            tokio::time::sleep(delay).await;
+
+            request = next_request;
+            attempt += 1;
+        }
+    }
+}
+
+/// 未设置时使用默认值，设置为 0 表示不限制
+fn resolve(secs: Option<u64>, default: u64) -> Option<Duration> {
+    match secs.unwrap_or(default) {
+        0 => None,
+        secs => Some(Duration::from_secs(secs)),
+    }
+}
+
+/// 单次请求生效的超时
+#[derive(Debug, Clone, Copy)]
+pub(super) struct RequestTimeouts {
+    pub first_byte: Option<Duration>,
+    pub idle: Option<Duration>,
+    pub total: Option<Duration>,
+}
+
+impl RequestTimeouts {
+    /// 按是否流式请求计算生效的超时（流式请求默认不限制总时长，避免中断长时间的思考输出）
+    pub fn for_profile(timeouts: &ProfileTimeouts, is_stream: bool) -> Self {
+        if is_stream {
+            Self {
+                first_byte: resolve(timeouts.first_byte_secs, DEFAULT_STREAM_FIRST_BYTE_SECS),
+                idle: resolve(timeouts.idle_secs, DEFAULT_IDLE_SECS),
+                total: resolve(timeouts.total_secs, 0),
+            }
+        } else {
+            Self {
+                first_byte: resolve(timeouts.first_byte_secs, DEFAULT_FIRST_BYTE_SECS),
+                idle: resolve(timeouts.idle_secs, DEFAULT_IDLE_SECS),
+                total: resolve(timeouts.total_secs, DEFAULT_TOTAL_SECS),
+            }
+        }
+    }
+}
+
+/// 获取 Profile 的 HTTP 客户端（复用连接池、TLS 会话和 HTTP/2 连接）
+pub(super) fn client_for(profile: &Profile) -> Result<UpstreamClient, String> {
+    let settings = ClientSettings::from_profile(profile);
+    let mut clients = CLIENTS.lock().map_err(|e| e.to_string())?;
+
+    if let Some((cached_settings, client)) = clients.get(&profile.id) {
+        if *cached_settings == settings {
+            return Ok(client.clone());
+        }
+    }
+
+    log::debug!("Building HTTP client for profile: {}", profile.name);
+    let client = settings.build()?;
+    clients.insert(profile.id.clone(), (settings, client.clone()));
+    Ok(client)
+}
+
+/// 移除 Profile 的缓存客户端（Profile 删除或修改后调用）
+pub fn invalidate_client(profile_id: &str) {
+    if let Ok(mut clients) = CLIENTS.lock() {
+        clients.remove(profile_id);
+    }
+}
+
+/// 设置全局默认网络设置（代理服务器启动或配置变更时调用）
+pub(super) fn set_default_network(network: &UpstreamNetwork) {
+    if let Ok(mut default) = DEFAULT_NETWORK.write() {
+        if *default != *network {
+            *default = network.clone();
+            // 使用默认设置的客户端会在下次获取时因设置变化而重新构建
+            log::info!("Upstream network settings updated (proxy: {})", network.proxy_url().is_some());
+        }
+    }
+}
+
+/// 发送请求失败的原因
+#[derive(Debug)]
+pub(super) enum SendError {
+    /// 超过首字节超时仍未收到响应头
+    FirstByteTimeout(Duration),
+    /// 无法通过出站代理建立连接
+    Proxy { proxy: String, error: reqwest::Error },
+    Request(reqwest::Error),
+}
+
+impl SendError {
+    pub fn is_timeout(&self) -> bool {
+        match self {
+            SendError::FirstByteTimeout(_) => true,
+            SendError::Proxy { error, .. } | SendError::Request(error) => error.is_timeout(),
+        }
+    }
+
+    pub fn is_connect(&self) -> bool {
+        match self {
+            SendError::FirstByteTimeout(_) => false,
+            SendError::Proxy { .. } => true,
+            SendError::Request(e) => e.is_connect(),
+        }
+    }
+}
+
+impl fmt::Display for SendError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SendError::FirstByteTimeout(timeout) => {
+                write!(f, "no response from upstream within {}s", timeout.as_secs())
+            }
+            SendError::Proxy { proxy, error } => write!(f, "outbound proxy {} error: {}", proxy, error),
+            SendError::Request(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_timeouts_defaults() {
+        let defaults = ProfileTimeouts::default();
+
+        let stream = RequestTimeouts::for_profile(&defaults, true);
+        assert_eq!(stream.first_byte, Some(Duration::from_secs(DEFAULT_STREAM_FIRST_BYTE_SECS)));
+        assert_eq!(stream.total, None);
+
+        let non_stream = RequestTimeouts::for_profile(&defaults, false);
+        assert_eq!(non_stream.first_byte, Some(Duration::from_secs(DEFAULT_FIRST_BYTE_SECS)));
+        assert_eq!(non_stream.total, Some(Duration::from_secs(DEFAULT_TOTAL_SECS)));
+
+        // 0 表示不限制，显式设置的值对流式请求同样生效
+        let custom = ProfileTimeouts { idle_secs: Some(0), total_secs: Some(1800), ..Default::default() };
+        let stream = RequestTimeouts::for_profile(&custom, true);
+        assert_eq!(stream.idle, None);
+        assert_eq!(stream.total, Some(Duration::from_secs(1800)));
+    }
+}
@@ -1,14 +1,27 @@
+mod balancer;
+mod budget;
+mod count_tokens;
+mod error;
 mod handler;
+mod http_client;
+mod openai;
+mod passthrough;
+mod retry;
+mod sse;
 mod stream;
 mod utils;
 mod proxy_config;
+mod request;
+mod response_cache;
 mod token_counter;
 
+pub use budget::{BudgetStatus, get_budget_status, record_budget_usage};
+pub use http_client::invalidate_client;
 pub use proxy_config::{ProxyConfig, ProxyServerStatus};
-pub use token_counter::TokenCounter;
+pub use token_counter::{TokenCounter, Tokenizer};
 
 use axum::{
-    routing::post,
+    routing::{any, post},
     Router,
 };
 use std::sync::Arc;
@@ -16,7 +29,9 @@ use std::time::{SystemTime, UNIX_EPOCH};
 use tokio::sync::{RwLock, mpsc};
 use crate::config::SharedConfigManager;
 use crate::db::{save_proxy_status, load_proxy_status};
+use count_tokens::handle_count_tokens;
 use handler::handle_messages;
+use passthrough::handle_passthrough;
 
 /// 代理服务器控制命令
 #[derive(Debug, Clone)]
@@ -118,6 +133,10 @@ pub async fn start_proxy_server(
     let mut current_config = initial_config;
 
     loop {
+        // 访问上游的默认网络设置随配置一起生效
+        http_client::set_default_network(&current_config.upstream_network);
+        response_cache::configure(&current_config.response_cache);
+
         // 验证配置
         if let Err(e) = current_config.validate() {
             log::error!("Invalid proxy config: {}", e);
@@ -201,6 +220,9 @@ pub async fn start_proxy_server(
         // 创建应用
         let app = Router::new()
             .route("/v1/messages", post(handle_messages))
+            .route("/v1/messages/count_tokens", post(handle_count_tokens))
+            // 其他 /v1/* 接口（任意方法）透传到上游
+            .route("/v1/*path", any(handle_passthrough))
             .with_state((config.clone(), app_handle.clone()));
 
         // 创建关闭信号通道
@@ -0,0 +1,750 @@
+// Anthropic Messages API 与 OpenAI Chat Completions API 之间的格式转换
+
+use bytes::Bytes;
+use futures::stream::Stream;
+use serde_json::{json, Map, Value};
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use super::sse::{SseEvent, SseParser};
+
+/// 将 Anthropic Messages 请求转换为 OpenAI Chat Completions 请求
+pub(super) fn convert_request(request: &Value) -> Value {
+    let mut result = Map::new();
+
+    if let Some(model) = request.get("model") {
+        result.insert("model".to_string(), model.clone());
+    }
+
+    // system 可以是字符串，也可以是 text 块数组
+    let mut messages = Vec::new();
+    if let Some(system) = request.get("system") {
+        let system_text = extract_text(system);
+        if !system_text.is_empty() {
+            messages.push(json!({ "role": "system", "content": system_text }));
+        }
+    }
+
+    if let Some(source_messages) = request.get("messages").and_then(|m| m.as_array()) {
+        for message in source_messages {
+            let role = message.get("role").and_then(|r| r.as_str()).unwrap_or("user");
+            match role {
+                "assistant" => messages.push(convert_assistant_message(message.get("content"))),
+                _ => messages.extend(convert_user_message(message.get("content"))),
+            }
+        }
+    }
+    result.insert("messages".to_string(), Value::Array(messages));
+
+    if let Some(max_tokens) = request.get("max_tokens") {
+        result.insert("max_tokens".to_string(), max_tokens.clone());
+    }
+    for key in ["temperature", "top_p"] {
+        if let Some(value) = request.get(key) {
+            result.insert(key.to_string(), value.clone());
+        }
+    }
+    if let Some(stop_sequences) = request.get("stop_sequences").and_then(|s| s.as_array()) {
+        if !stop_sequences.is_empty() {
+            result.insert("stop".to_string(), Value::Array(stop_sequences.clone()));
+        }
+    }
+    if let Some(user_id) = request
+        .get("metadata")
+        .and_then(|m| m.get("user_id"))
+        .and_then(|u| u.as_str())
+    {
+        result.insert("user".to_string(), Value::String(user_id.to_string()));
+    }
+
+    // 工具定义：只转换带 input_schema 的自定义工具，服务端工具（如 web_search）没有对应形式
+    if let Some(tools) = request.get("tools").and_then(|t| t.as_array()) {
+        let converted: Vec<Value> = tools
+            .iter()
+            .filter_map(|tool| {
+                let name = tool.get("name")?.as_str()?;
+                let parameters = tool.get("input_schema")?.clone();
+                let mut function = Map::new();
+                function.insert("name".to_string(), Value::String(name.to_string()));
+                if let Some(description) = tool.get("description") {
+                    function.insert("description".to_string(), description.clone());
+                }
+                function.insert("parameters".to_string(), parameters);
+                Some(json!({ "type": "function", "function": function }))
+            })
+            .collect();
+        if !converted.is_empty() {
+            result.insert("tools".to_string(), Value::Array(converted));
+        }
+    }
+
+    if let Some(tool_choice) = request.get("tool_choice") {
+        let choice_type = tool_choice.get("type").and_then(|t| t.as_str()).unwrap_or("auto");
+        let converted = match choice_type {
+            "any" => Some(json!("required")),
+            "none" => Some(json!("none")),
+            "tool" => tool_choice
+                .get("name")
+                .and_then(|n| n.as_str())
+                .map(|name| json!({ "type": "function", "function": { "name": name } })),
+            _ => Some(json!("auto")),
+        };
+        if let Some(converted) = converted {
+            if result.contains_key("tools") {
+                result.insert("tool_choice".to_string(), converted);
+            }
+        }
+        if tool_choice.get("disable_parallel_tool_use").and_then(|d| d.as_bool()) == Some(true)
+            && result.contains_key("tools")
+        {
+            result.insert("parallel_tool_calls".to_string(), Value::Bool(false));
+        }
+    }
+
+    if request.get("stream").and_then(|s| s.as_bool()) == Some(true) {
+        result.insert("stream".to_string(), Value::Bool(true));
+        // 要求上游在最后一个 chunk 中返回 usage，否则流式请求拿不到 Token 统计
+        result.insert("stream_options".to_string(), json!({ "include_usage": true }));
+    }
+
+    Value::Object(result)
+}
+
+/// 提取字符串或 text 块数组中的文本
+fn extract_text(value: &Value) -> String {
+    if let Some(text) = value.as_str() {
+        return text.to_string();
+    }
+
+    value
+        .as_array()
+        .map(|blocks| {
+            blocks
+                .iter()
+                .filter(|block| block.get("type").and_then(|t| t.as_str()) == Some("text"))
+                .filter_map(|block| block.get("text").and_then(|t| t.as_str()))
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+        .unwrap_or_default()
+}
+
+/// 转换 user 消息：tool_result 块拆分为独立的 tool 消息，其余内容保留在 user 消息中
+fn convert_user_message(content: Option<&Value>) -> Vec<Value> {
+    let content = match content {
+        Some(content) => content,
+        None => return vec![json!({ "role": "user", "content": "" })],
+    };
+
+    if let Some(text) = content.as_str() {
+        return vec![json!({ "role": "user", "content": text })];
+    }
+
+    let blocks = match content.as_array() {
+        Some(blocks) => blocks,
+        None => return Vec::new(),
+    };
+
+    let mut tool_messages = Vec::new();
+    let mut parts = Vec::new();
+    let mut has_image = false;
+
+    for block in blocks {
+        match block.get("type").and_then(|t| t.as_str()) {
+            Some("text") => {
+                if let Some(text) = block.get("text").and_then(|t| t.as_str()) {
+                    parts.push(json!({ "type": "text", "text": text }));
+                }
+            }
+            Some("image") => {
+                if let Some(url) = image_url(block.get("source")) {
+                    has_image = true;
+                    parts.push(json!({ "type": "image_url", "image_url": { "url": url } }));
+                }
+            }
+            Some("tool_result") => {
+                let tool_call_id = block.get("tool_use_id").and_then(|t| t.as_str()).unwrap_or("");
+                let text = block.get("content").map(extract_text).unwrap_or_default();
+                let text = if block.get("is_error").and_then(|e| e.as_bool()) == Some(true) {
+                    format!("Error: {}", text)
+                } else {
+                    text
+                };
+                tool_messages.push(json!({
+                    "role": "tool",
+                    "tool_call_id": tool_call_id,
+                    "content": text,
+                }));
+            }
+            _ => {}
+        }
+    }
+
+    // tool 消息必须紧跟在包含 tool_calls 的 assistant 消息之后
+    let mut messages = tool_messages;
+    if !parts.is_empty() {
+        let content = if has_image {
+            Value::Array(parts)
+        } else {
+            Value::String(
+                parts
+                    .iter()
+                    .filter_map(|part| part.get("text").and_then(|t| t.as_str()))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            )
+        };
+        messages.push(json!({ "role": "user", "content": content }));
+    }
+
+    messages
+}
+
+/// 将 Anthropic 图片来源转换为 OpenAI image_url
+fn image_url(source: Option<&Value>) -> Option<String> {
+    let source = source?;
+    match source.get("type").and_then(|t| t.as_str()) {
+        Some("base64") => {
+            let media_type = source.get("media_type").and_then(|m| m.as_str()).unwrap_or("image/png");
+            let data = source.get("data").and_then(|d| d.as_str())?;
+            Some(format!("data:{};base64,{}", media_type, data))
+        }
+        Some("url") => source.get("url").and_then(|u| u.as_str()).map(|u| u.to_string()),
+        _ => None,
+    }
+}
+
+/// 转换 assistant 消息：text 块合并为 content，tool_use 块转换为 tool_calls
+fn convert_assistant_message(content: Option<&Value>) -> Value {
+    let content = match content {
+        Some(content) => content,
+        None => return json!({ "role": "assistant", "content": "" }),
+    };
+
+    if let Some(text) = content.as_str() {
+        return json!({ "role": "assistant", "content": text });
+    }
+
+    let mut texts = Vec::new();
+    let mut tool_calls = Vec::new();
+
+    if let Some(blocks) = content.as_array() {
+        for block in blocks {
+            match block.get("type").and_then(|t| t.as_str()) {
+                Some("text") => {
+                    if let Some(text) = block.get("text").and_then(|t| t.as_str()) {
+                        texts.push(text);
+                    }
+                }
+                Some("tool_use") => {
+                    let arguments = block
+                        .get("input")
+                        .map(|input| input.to_string())
+                        .unwrap_or_else(|| "{}".to_string());
+                    tool_calls.push(json!({
+                        "id": block.get("id").cloned().unwrap_or(Value::Null),
+                        "type": "function",
+                        "function": {
+                            "name": block.get("name").cloned().unwrap_or(Value::Null),
+                            "arguments": arguments,
+                        },
+                    }));
+                }
+                // thinking 等块在 OpenAI 格式中没有对应字段，直接丢弃
+                _ => {}
+            }
+        }
+    }
+
+    let mut message = Map::new();
+    message.insert("role".to_string(), json!("assistant"));
+    if texts.is_empty() && !tool_calls.is_empty() {
+        message.insert("content".to_string(), Value::Null);
+    } else {
+        message.insert("content".to_string(), Value::String(texts.join("\n")));
+    }
+    if !tool_calls.is_empty() {
+        message.insert("tool_calls".to_string(), Value::Array(tool_calls));
+    }
+
+    Value::Object(message)
+}
+
+/// 将 OpenAI finish_reason 转换为 Anthropic stop_reason
+fn convert_finish_reason(finish_reason: Option<&str>) -> &'static str {
+    match finish_reason {
+        Some("length") => "max_tokens",
+        Some("tool_calls") | Some("function_call") => "tool_use",
+        _ => "end_turn",
+    }
+}
+
+/// 从 OpenAI usage 中提取 (input, output, cache_read)
+fn convert_usage(usage: Option<&Value>) -> (i64, i64, i64) {
+    let usage = match usage {
+        Some(usage) => usage,
+        None => return (0, 0, 0),
+    };
+    let prompt = usage.get("prompt_tokens").and_then(|t| t.as_i64()).unwrap_or(0);
+    let completion = usage.get("completion_tokens").and_then(|t| t.as_i64()).unwrap_or(0);
+    let cached = usage
+        .get("prompt_tokens_details")
+        .and_then(|d| d.get("cached_tokens"))
+        .and_then(|t| t.as_i64())
+        .unwrap_or(0);
+    // OpenAI 的 prompt_tokens 包含缓存命中部分，Anthropic 的 input_tokens 不包含
+    (prompt - cached, completion, cached)
+}
+
+/// 将 OpenAI Chat Completions 非流式响应转换为 Anthropic Messages 响应
+pub(super) fn convert_response(response: &Value, fallback_model: &str) -> Value {
+    let choice = response
+        .get("choices")
+        .and_then(|c| c.as_array())
+        .and_then(|arr| arr.first());
+    let message = choice.and_then(|c| c.get("message"));
+
+    let mut content = Vec::new();
+    if let Some(text) = message.and_then(|m| m.get("content")).and_then(|c| c.as_str()) {
+        if !text.is_empty() {
+            content.push(json!({ "type": "text", "text": text }));
+        }
+    }
+    if let Some(tool_calls) = message.and_then(|m| m.get("tool_calls")).and_then(|t| t.as_array()) {
+        for tool_call in tool_calls {
+            let function = tool_call.get("function");
+            let arguments = function
+                .and_then(|f| f.get("arguments"))
+                .and_then(|a| a.as_str())
+                .unwrap_or("{}");
+            let input = serde_json::from_str::<Value>(arguments).unwrap_or_else(|_| json!({}));
+            content.push(json!({
+                "type": "tool_use",
+                "id": tool_call.get("id").cloned().unwrap_or(Value::Null),
+                "name": function.and_then(|f| f.get("name")).cloned().unwrap_or(Value::Null),
+                "input": input,
+            }));
+        }
+    }
+
+    let finish_reason = choice.and_then(|c| c.get("finish_reason")).and_then(|f| f.as_str());
+    let (input_tokens, output_tokens, cache_read) = convert_usage(response.get("usage"));
+
+    json!({
+        "id": response.get("id").cloned().unwrap_or_else(|| json!(format!("msg_{}", uuid::Uuid::new_v4().simple()))),
+        "type": "message",
+        "role": "assistant",
+        "model": response.get("model").and_then(|m| m.as_str()).unwrap_or(fallback_model),
+        "content": content,
+        "stop_reason": convert_finish_reason(finish_reason),
+        "stop_sequence": null,
+        "usage": {
+            "input_tokens": input_tokens,
+            "output_tokens": output_tokens,
+            "cache_read_input_tokens": cache_read,
+        },
+    })
+}
+
+/// 将 OpenAI 错误响应转换为 Anthropic 错误格式
+pub(super) fn convert_error(response: &Value, status: u16) -> Value {
+    let message = response
+        .get("error")
+        .and_then(|e| e.get("message").or(Some(e)))
+        .and_then(|m| m.as_str())
+        .or_else(|| response.get("message").and_then(|m| m.as_str()))
+        .unwrap_or("Unknown error");
+
+    let error_type = match status {
+        400 | 422 => "invalid_request_error",
+        401 => "authentication_error",
+        403 => "permission_error",
+        404 => "not_found_error",
+        413 => "request_too_large",
+        429 => "rate_limit_error",
+        529 => "overloaded_error",
+        _ => "api_error",
+    };
+
+    json!({
+        "type": "error",
+        "error": { "type": error_type, "message": message },
+    })
+}
+
+/// 当前打开的内容块类型
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum OpenBlock {
+    Text,
+    ToolUse(u64),
+}
+
+/// OpenAI SSE 到 Anthropic SSE 的增量转换器
+///
+/// 每次输入一行 OpenAI SSE 文本，输出零个或多个完整的 Anthropic SSE 事件。
+#[derive(Default)]
+pub(super) struct StreamTranslator {
+    model: String,
+    started: bool,
+    finished: bool,
+    block_index: usize,
+    open_block: Option<OpenBlock>,
+    finish_reason: Option<String>,
+    input_tokens: i64,
+    output_tokens: i64,
+    cache_read_tokens: i64,
+}
+
+impl StreamTranslator {
+    pub(super) fn new(model: &str) -> Self {
+        Self {
+            model: model.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// 处理一个 OpenAI SSE 事件的 data
+    pub(super) fn push_data(&mut self, data: &str) -> Vec<String> {
+        let mut events = Vec::new();
+        let data = data.trim();
+
+        if data == "[DONE]" {
+            events.extend(self.finish());
+            return events;
+        }
+
+        let chunk = match serde_json::from_str::<Value>(data) {
+            Ok(chunk) => chunk,
+            Err(_) => {
+                log::debug!("⚠️  Failed to parse OpenAI SSE chunk");
+                return events;
+            }
+        };
+
+        if !self.started {
+            self.started = true;
+            let id = chunk
+                .get("id")
+                .and_then(|i| i.as_str())
+                .map(|i| i.to_string())
+                .unwrap_or_else(|| format!("msg_{}", uuid::Uuid::new_v4().simple()));
+            if let Some(model) = chunk.get("model").and_then(|m| m.as_str()) {
+                self.model = model.to_string();
+            }
+            events.push(sse_event("message_start", &json!({
+                "type": "message_start",
+                "message": {
+                    "id": id,
+                    "type": "message",
+                    "role": "assistant",
+                    "model": self.model,
+                    "content": [],
+                    "stop_reason": null,
+                    "stop_sequence": null,
+                    "usage": { "input_tokens": 0, "output_tokens": 0 },
+                },
+            })));
+        }
+
+        if let Some(usage) = chunk.get("usage").filter(|u| !u.is_null()) {
+            let (input, output, cache_read) = convert_usage(Some(usage));
+            self.input_tokens = input;
+            self.output_tokens = output;
+            self.cache_read_tokens = cache_read;
+        }
+
+        let choice = chunk
+            .get("choices")
+            .and_then(|c| c.as_array())
+            .and_then(|arr| arr.first());
+        let choice = match choice {
+            Some(choice) => choice,
+            None => return events,
+        };
+
+        if let Some(delta) = choice.get("delta") {
+            if let Some(text) = delta.get("content").and_then(|c| c.as_str()) {
+                if !text.is_empty() {
+                    if self.open_block != Some(OpenBlock::Text) {
+                        events.extend(self.close_block());
+                        events.push(sse_event("content_block_start", &json!({
+                            "type": "content_block_start",
+                            "index": self.block_index,
+                            "content_block": { "type": "text", "text": "" },
+                        })));
+                        self.open_block = Some(OpenBlock::Text);
+                    }
+                    events.push(sse_event("content_block_delta", &json!({
+                        "type": "content_block_delta",
+                        "index": self.block_index,
+                        "delta": { "type": "text_delta", "text": text },
+                    })));
+                }
+            }
+
+            if let Some(tool_calls) = delta.get("tool_calls").and_then(|t| t.as_array()) {
+                for tool_call in tool_calls {
+                    let index = tool_call.get("index").and_then(|i| i.as_u64()).unwrap_or(0);
+                    let function = tool_call.get("function");
+
+                    if self.open_block != Some(OpenBlock::ToolUse(index)) {
+                        events.extend(self.close_block());
+                        events.push(sse_event("content_block_start", &json!({
+                            "type": "content_block_start",
+                            "index": self.block_index,
+                            "content_block": {
+                                "type": "tool_use",
+                                "id": tool_call.get("id").cloned().unwrap_or(Value::Null),
+                                "name": function.and_then(|f| f.get("name")).cloned().unwrap_or(Value::Null),
+                                "input": {},
+                            },
+                        })));
+                        self.open_block = Some(OpenBlock::ToolUse(index));
+                    }
+
+                    if let Some(arguments) = function
+                        .and_then(|f| f.get("arguments"))
+                        .and_then(|a| a.as_str())
+                    {
+                        if !arguments.is_empty() {
+                            events.push(sse_event("content_block_delta", &json!({
+                                "type": "content_block_delta",
+                                "index": self.block_index,
+                                "delta": { "type": "input_json_delta", "partial_json": arguments },
+                            })));
+                        }
+                    }
+                }
+            }
+        }
+
+        if let Some(finish_reason) = choice.get("finish_reason").and_then(|f| f.as_str()) {
+            self.finish_reason = Some(finish_reason.to_string());
+        }
+
+        events
+    }
+
+    /// 关闭当前打开的内容块
+    fn close_block(&mut self) -> Vec<String> {
+        match self.open_block.take() {
+            Some(_) => {
+                let event = sse_event("content_block_stop", &json!({
+                    "type": "content_block_stop",
+                    "index": self.block_index,
+                }));
+                self.block_index += 1;
+                vec![event]
+            }
+            None => Vec::new(),
+        }
+    }
+
+    /// 结束流：输出 message_delta（含 stop_reason 和 usage）和 message_stop
+    pub(super) fn finish(&mut self) -> Vec<String> {
+        if self.finished || !self.started {
+            return Vec::new();
+        }
+        self.finished = true;
+
+        let mut events = self.close_block();
+        events.push(sse_event("message_delta", &json!({
+            "type": "message_delta",
+            "delta": {
+                "stop_reason": convert_finish_reason(self.finish_reason.as_deref()),
+                "stop_sequence": null,
+            },
+            "usage": {
+                "input_tokens": self.input_tokens,
+                "output_tokens": self.output_tokens,
+                "cache_read_input_tokens": self.cache_read_tokens,
+            },
+        })));
+        events.push(sse_event("message_stop", &json!({ "type": "message_stop" })));
+        events
+    }
+}
+
+/// 格式化一个 SSE 事件
+fn sse_event(event_type: &str, data: &Value) -> String {
+    format!("event: {}\ndata: {}\n\n", event_type, data)
+}
+
+/// 包装 OpenAI 上游字节流，输出 Anthropic 格式的 SSE 字节流
+pub(super) struct AnthropicSseStream {
+    inner: Pin<Box<dyn Stream<Item = Result<Bytes, reqwest::Error>> + Send>>,
+    translator: StreamTranslator,
+    parser: SseParser,
+    pending: VecDeque<Bytes>,
+    inner_done: bool,
+}
+
+impl AnthropicSseStream {
+    pub(super) fn new(
+        inner: Pin<Box<dyn Stream<Item = Result<Bytes, reqwest::Error>> + Send>>,
+        model: &str,
+    ) -> Self {
+        Self {
+            inner,
+            translator: StreamTranslator::new(model),
+            parser: SseParser::new(),
+            pending: VecDeque::new(),
+            inner_done: false,
+        }
+    }
+
+    /// 翻译解析出的 OpenAI 事件
+    fn translate(&mut self, events: impl IntoIterator<Item = SseEvent>) {
+        for event in events {
+            for converted in self.translator.push_data(&event.data) {
+                self.pending.push_back(Bytes::from(converted));
+            }
+        }
+    }
+}
+
+impl Stream for AnthropicSseStream {
+    type Item = Result<Bytes, reqwest::Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            if let Some(bytes) = self.pending.pop_front() {
+                return Poll::Ready(Some(Ok(bytes)));
+            }
+            if self.inner_done {
+                return Poll::Ready(None);
+            }
+
+            match self.inner.as_mut().poll_next(cx) {
+                Poll::Ready(Some(Ok(chunk))) => {
+                    let events = self.parser.feed(&chunk);
+                    self.translate(events);
+                }
+                Poll::Ready(Some(Err(e))) => return Poll::Ready(Some(Err(e))),
+                Poll::Ready(None) => {
+                    self.inner_done = true;
+                    // 处理最后一个事件（可能没有以空行结尾）
+                    let last = self.parser.finish();
+                    self.translate(last);
+                    let events = self.translator.finish();
+                    self.pending.extend(events.into_iter().map(Bytes::from));
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_convert_request() {
+        let request = json!({
+            "model": "gpt-4o",
+            "max_tokens": 1024,
+            "system": [{ "type": "text", "text": "You are helpful." }],
+            "stop_sequences": ["END"],
+            "stream": true,
+            "tools": [{
+                "name": "read_file",
+                "description": "Read a file",
+                "input_schema": { "type": "object", "properties": { "path": { "type": "string" } } }
+            }],
+            "tool_choice": { "type": "any" },
+            "messages": [
+                { "role": "user", "content": "Read main.rs" },
+                { "role": "assistant", "content": [
+                    { "type": "text", "text": "Sure." },
+                    { "type": "tool_use", "id": "call_1", "name": "read_file", "input": { "path": "main.rs" } }
+                ]},
+                { "role": "user", "content": [
+                    { "type": "tool_result", "tool_use_id": "call_1", "content": "fn main() {}" },
+                    { "type": "text", "text": "Explain it" }
+                ]}
+            ]
+        });
+
+        let converted = convert_request(&request);
+        let messages = converted["messages"].as_array().unwrap();
+
+        assert_eq!(messages[0], json!({ "role": "system", "content": "You are helpful." }));
+        assert_eq!(messages[1], json!({ "role": "user", "content": "Read main.rs" }));
+        assert_eq!(messages[2]["tool_calls"][0]["function"]["arguments"], json!("{\"path\":\"main.rs\"}"));
+        assert_eq!(messages[3], json!({ "role": "tool", "tool_call_id": "call_1", "content": "fn main() {}" }));
+        assert_eq!(messages[4], json!({ "role": "user", "content": "Explain it" }));
+        assert_eq!(converted["stop"], json!(["END"]));
+        assert_eq!(converted["tool_choice"], json!("required"));
+        assert_eq!(converted["tools"][0]["function"]["name"], json!("read_file"));
+        assert_eq!(converted["stream_options"]["include_usage"], json!(true));
+    }
+
+    #[test]
+    fn test_convert_response() {
+        let response = json!({
+            "id": "chatcmpl-1",
+            "model": "gpt-4o",
+            "choices": [{
+                "message": {
+                    "role": "assistant",
+                    "content": "Hello",
+                    "tool_calls": [{
+                        "id": "call_1",
+                        "type": "function",
+                        "function": { "name": "read_file", "arguments": "{\"path\":\"a.rs\"}" }
+                    }]
+                },
+                "finish_reason": "tool_calls"
+            }],
+            "usage": { "prompt_tokens": 10, "completion_tokens": 5 }
+        });
+
+        let converted = convert_response(&response, "fallback");
+        assert_eq!(converted["content"][0], json!({ "type": "text", "text": "Hello" }));
+        assert_eq!(converted["content"][1]["input"], json!({ "path": "a.rs" }));
+        assert_eq!(converted["stop_reason"], json!("tool_use"));
+        assert_eq!(converted["usage"]["input_tokens"], json!(10));
+        assert_eq!(converted["usage"]["output_tokens"], json!(5));
+    }
+
+    #[test]
+    fn test_stream_translator() {
+        let mut translator = StreamTranslator::new("gpt-4o");
+        let mut events = Vec::new();
+        for data in [
+            r#"{"id":"c1","model":"gpt-4o","choices":[{"delta":{"role":"assistant","content":"Hi"}}]}"#,
+            r#"{"id":"c1","choices":[{"delta":{"tool_calls":[{"index":0,"id":"call_1","function":{"name":"ls","arguments":""}}]}}]}"#,
+            r#"{"id":"c1","choices":[{"delta":{"tool_calls":[{"index":0,"function":{"arguments":"{}"}}]},"finish_reason":"tool_calls"}]}"#,
+            r#"{"id":"c1","choices":[],"usage":{"prompt_tokens":7,"completion_tokens":3}}"#,
+            "[DONE]",
+        ] {
+            events.extend(translator.push_data(data));
+        }
+
+        let types: Vec<&str> = events
+            .iter()
+            .map(|e| e.lines().next().unwrap().trim_start_matches("event: "))
+            .collect();
+        assert_eq!(types, vec![
+            "message_start",
+            "content_block_start",
+            "content_block_delta",
+            "content_block_stop",
+            "content_block_start",
+            "content_block_delta",
+            "content_block_stop",
+            "message_delta",
+            "message_stop",
+        ]);
+
+        let message_delta: Value = serde_json::from_str(
+            events[7].lines().nth(1).unwrap().trim_start_matches("data: "),
+        ).unwrap();
+        assert_eq!(message_delta["delta"]["stop_reason"], json!("tool_use"));
+        assert_eq!(message_delta["usage"]["output_tokens"], json!(3));
+
+        // [DONE] 之后流结束不应重复输出结束事件
+        assert!(translator.finish().is_empty());
+    }
+}
@@ -0,0 +1,151 @@
+use axum::{
+    body::{Body, Bytes},
+    extract::State,
+    http::{HeaderMap, Method, Uri},
+    response::Response,
+};
+use std::time::Instant;
+use crate::config::SharedConfigManager;
+use crate::logger::RequestLog;
+use super::error::{unrouted_log, ProxyError, ProxyErrorKind};
+use super::handler::{authorize, build_upstream_headers, no_active_profile_error, select_chain, spawn_save_log, upstream_auth_query};
+use super::http_client::{self, RequestTimeouts};
+use super::retry;
+
+/// 透传 /v1/* 下的其他请求（如 /v1/models、/v1/messages/batches）
+///
+/// 与 /v1/messages 使用相同的鉴权、Profile 选择和请求头改写，但不做协议转换和 token 统计，
+/// 响应体按原样流式返回。只使用选中链路的第一个 Profile，不做故障转移；
+/// 只有幂等请求（GET/HEAD/OPTIONS/DELETE）按重试策略重试。
+pub(super) async fn handle_passthrough(
+    State((config, app_handle)): State<(SharedConfigManager, tauri::AppHandle)>,
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Response, ProxyError> {
+    let start_time = Instant::now();
+    let endpoint = format!("{} {}", method, uri.path());
+    log::info!("\n{}\n🔀 Passthrough Request: {}\n{}", "=".repeat(60), endpoint, "=".repeat(60));
+
+    // 请求体带有 model 字段时同样参与路由和模型映射
+    let request_json = serde_json::from_slice::<serde_json::Value>(&body).ok();
+    let original_model = request_json.as_ref()
+        .and_then(|json| json.get("model"))
+        .and_then(|m| m.as_str())
+        .map(|m| m.to_string());
+
+    let unrouted = |error: ProxyError| {
+        let request_log = unrouted_log(&endpoint, original_model.as_deref().unwrap_or_default(), body.len());
+        error.save_log(request_log, start_time, &app_handle);
+        error
+    };
+
+    let client_key_id = authorize(&config, &headers).map_err(unrouted)?;
+
+    let (chain, _pool_guard, routed_model) = select_chain(&config, original_model.as_deref().unwrap_or(""))
+        .map_err(unrouted)?;
+    let profile = chain.into_iter().next().ok_or_else(|| unrouted(no_active_profile_error()))?;
+    log::info!("📋 Profile: {}", profile.name);
+
+    let (forwarded_model, upstream_body) = match (&original_model, request_json) {
+        (Some(_), Some(mut json)) => {
+            let mapped = profile.resolve_model(&routed_model);
+            if json.get("model").and_then(|m| m.as_str()) != Some(mapped.as_str()) {
+                log::info!("🤖 Model: {} → {}", original_model.as_deref().unwrap_or_default(), mapped);
+                json["model"] = serde_json::Value::String(mapped.clone());
+                let body = serde_json::to_vec(&json).map(Bytes::from).unwrap_or(body);
+                (mapped, body)
+            } else {
+                (mapped, body)
+            }
+        }
+        _ => (String::new(), body),
+    };
+
+    let upstream_url = match uri.query() {
+        Some(query) => format!("{}{}?{}", profile.api_base_url, uri.path(), query),
+        None => format!("{}{}", profile.api_base_url, uri.path()),
+    };
+    log::debug!("Forwarding to: {}", upstream_url);
+
+    let mut request_log = RequestLog::new(
+        profile.id.clone(),
+        profile.name.clone(),
+        original_model.clone().unwrap_or_default(),
+        crate::logger::ModelMode::from_mapping_mode(&profile.model_mapping_mode),
+        forwarded_model,
+        profile.api_base_url.clone(),
+        upstream_body.len(),
+    );
+    request_log.endpoint = Some(endpoint);
+    request_log.client_key_id = client_key_id;
+
+    let client = match http_client::client_for(&profile) {
+        Ok(client) => client,
+        Err(e) => {
+            log::error!("{}", e);
+            let error = ProxyError::new(ProxyErrorKind::Internal, e);
+            error.save_log(request_log, start_time, &app_handle);
+            return Err(error);
+        }
+    };
+
+    // 只重试幂等请求，POST 等请求失败时只发送一次
+    let retryable = retry::is_idempotent_method(&method);
+    let mut request = client
+        .request(method, &upstream_url)
+        .query(&upstream_auth_query(&profile))
+        .headers(build_upstream_headers(&headers, &profile));
+    if !upstream_body.is_empty() {
+        request = request.body(upstream_body);
+    }
+
+    // 透传的响应可能是流式的，按流式请求的超时处理（默认不限制总时长）
+    let timeouts = RequestTimeouts::for_profile(&profile.timeouts, true);
+    let (result, attempts) = if retryable {
+        client.send_with_retry(request, timeouts, &profile.retry).await
+    } else {
+        (client.send(request, timeouts).await, 1)
+    };
+    request_log.attempts = attempts as i32;
+    let response = match result {
+        Ok(response) => response,
+        Err(e) => {
+            log::error!("Failed to forward request: {}", e);
+            let error = ProxyError::upstream(e.is_timeout(), e.to_string());
+            error.save_log(request_log, start_time, &app_handle);
+            return Err(error);
+        }
+    };
+
+    let status = response.status();
+    log::info!("📊 Passthrough: {} | {}ms", status, start_time.elapsed().as_millis());
+
+    // 获取响应头（移除压缩和传输编码相关的头，reqwest 已自动解压）
+    let mut builder = Response::builder().status(status);
+    for (key, value) in response.headers().iter() {
+        if key == "content-encoding"
+            || key == "content-length"
+            || key == "transfer-encoding" {
+            continue;
+        }
+        builder = builder.header(key, value);
+    }
+
+    request_log.duration_ms = start_time.elapsed().as_millis() as i64;
+    request_log.upstream_duration_ms = Some(request_log.duration_ms);
+    request_log.status_code = status.as_u16() as i32;
+    request_log.is_stream = response.headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("text/event-stream"));
+    spawn_save_log(request_log, &app_handle);
+
+    builder
+        .body(Body::from_stream(response.bytes_stream()))
+        .map_err(|e| {
+            log::error!("Failed to build response: {}", e);
+            ProxyError::new(ProxyErrorKind::Internal, "Failed to build response")
+        })
+}
@@ -3,6 +3,7 @@
 use serde::{Deserialize, Serialize};
 use std::net::IpAddr;
 use std::str::FromStr;
+use crate::config::UpstreamNetwork;
 
 /// 代理服务器配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -12,6 +13,40 @@ pub struct ProxyConfig {
     pub host: String,
     /// 监听端口（默认：15288）
     pub port: u16,
+    /// 访问上游的默认网络设置（Profile 未单独设置时使用）
+    #[serde(default)]
+    pub upstream_network: UpstreamNetwork,
+    /// 非流式响应的本地缓存（默认关闭）
+    #[serde(default)]
+    pub response_cache: ResponseCacheConfig,
+}
+
+/// 响应缓存配置
+///
+/// 开启后，相同 Profile 下请求体完全相同的请求直接返回缓存的响应，不再请求上游；
+/// 流式请求缓存完整的 SSE 事件序列，命中时按原事件重新发送。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ResponseCacheConfig {
+    /// 是否启用
+    pub enabled: bool,
+    /// 缓存有效期（秒）
+    pub ttl_secs: u64,
+    /// 缓存占用的最大内存（MB），超出时淘汰最早的缓存
+    pub max_size_mb: u64,
+    /// 回放缓存的流式响应时，相邻两个 SSE 事件之间的间隔（毫秒，0 表示一次性发送）
+    pub replay_delay_ms: u64,
+}
+
+impl Default for ResponseCacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            ttl_secs: 600,
+            max_size_mb: 64,
+            replay_delay_ms: 0,
+        }
+    }
 }
 
 impl Default for ProxyConfig {
@@ -19,6 +54,8 @@ impl Default for ProxyConfig {
         Self {
             host: "127.0.0.1".to_string(),
             port: 15288,
+            upstream_network: UpstreamNetwork::default(),
+            response_cache: ResponseCacheConfig::default(),
         }
     }
 }
@@ -36,6 +73,13 @@ impl ProxyConfig {
             return Err("Port cannot be 0".to_string());
         }
 
+        if self.response_cache.ttl_secs == 0 {
+            return Err("Response cache TTL must be greater than 0".to_string());
+        }
+        if self.response_cache.max_size_mb == 0 {
+            return Err("Response cache size must be greater than 0".to_string());
+        }
+
         Ok(())
     }
 
@@ -102,12 +146,14 @@ mod tests {
         let config = ProxyConfig {
             host: "invalid.ip.address".to_string(),
             port: 15288,
+            ..Default::default()
         };
         assert!(config.validate().is_err());
 
         let config = ProxyConfig {
             host: "0.0.0.0".to_string(),
             port: 0,
+            ..Default::default()
         };
         assert!(config.validate().is_err());
     }
@@ -117,6 +163,7 @@ mod tests {
         let config = ProxyConfig {
             host: "127.0.0.1".to_string(),
             port: 8080,
+            ..Default::default()
         };
         let addr = config.to_socket_addr().unwrap();
         assert_eq!(addr.to_string(), "127.0.0.1:8080");
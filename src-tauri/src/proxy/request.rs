@@ -0,0 +1,157 @@
+// /v1/messages 请求体的类型化视图：入站请求只解析一次，路由、流式判断和日志都以此为准
+
+use serde_json::Value;
+
+/// Anthropic Messages API 请求（只包含代理关心的字段，其余字段保留在原始请求体中原样转发）
+///
+/// 各字段宽松解析：类型不符的字段（如 `"max_tokens": 1024.0`、`"role": null`）视为缺失，
+/// 不会让整个请求失去模型映射、路由和流式判断。
+#[derive(Debug, Clone, Default)]
+pub(super) struct MessagesRequest {
+    pub model: Option<String>,
+    pub stream: Option<bool>,
+    pub max_tokens: Option<u64>,
+    pub metadata: Option<Value>,
+    pub thinking: Option<Value>,
+    pub tools: Option<Vec<Value>>,
+    pub messages: Vec<Message>,
+}
+
+/// 对话中的一条消息
+#[derive(Debug, Clone, Default)]
+pub(super) struct Message {
+    pub role: String,
+    pub content: Value,
+}
+
+impl MessagesRequest {
+    /// 解析请求体，不是 JSON 对象时返回 None
+    pub fn parse(body: &str) -> Option<Self> {
+        match serde_json::from_str::<Value>(body) {
+            Ok(json) if json.is_object() => Some(Self::from_value(&json)),
+            Ok(_) => {
+                log::warn!("Messages request body is not a JSON object");
+                None
+            }
+            Err(e) => {
+                log::warn!("Failed to parse messages request: {}", e);
+                None
+            }
+        }
+    }
+
+    fn from_value(json: &Value) -> Self {
+        let non_null = |field: &str| json.get(field).filter(|value| !value.is_null()).cloned();
+        Self {
+            model: json.get("model").and_then(|m| m.as_str()).map(|m| m.to_string()),
+            stream: json.get("stream").and_then(|s| s.as_bool()),
+            max_tokens: json.get("max_tokens").and_then(|t| {
+                t.as_u64().or_else(|| t.as_f64().filter(|t| *t >= 0.0).map(|t| t as u64))
+            }),
+            metadata: non_null("metadata"),
+            thinking: non_null("thinking"),
+            tools: json.get("tools").and_then(|t| t.as_array()).cloned(),
+            messages: json.get("messages")
+                .and_then(|m| m.as_array())
+                .map(|messages| messages.iter().map(|message| Message {
+                    role: message.get("role").and_then(|r| r.as_str()).unwrap_or_default().to_string(),
+                    content: message.get("content").cloned().unwrap_or_default(),
+                }).collect())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// 请求的模型（缺失时为 "unknown"）
+    pub fn model(&self) -> &str {
+        self.model.as_deref().unwrap_or("unknown")
+    }
+
+    pub fn is_stream(&self) -> bool {
+        self.stream.unwrap_or(false)
+    }
+
+    /// 最后一条用户消息中的文本（用于日志预览）
+    pub fn last_user_prompt(&self) -> Option<&str> {
+        let message = self.messages.iter().rev().find(|msg| msg.role == "user")?;
+        match &message.content {
+            Value::String(text) => Some(text),
+            Value::Array(blocks) => blocks.iter()
+                .find(|block| block.get("type").and_then(|t| t.as_str()) == Some("text"))
+                .and_then(|block| block.get("text"))
+                .and_then(|t| t.as_str()),
+            _ => None,
+        }
+    }
+}
+
+/// 为指定的转发模型生成请求体：模型未变化时原样返回，保证逐字节透传
+pub(super) fn body_for_model(request: Option<&MessagesRequest>, body: &str, model: &str) -> String {
+    match request {
+        // 原始请求体没有 model 时不注入（映射结果只是占位的 "unknown"）
+        Some(request) if request.model.is_some() && request.model.as_deref() != Some(model) => {
+            match serde_json::from_str::<Value>(body) {
+                Ok(mut json) => {
+                    json["model"] = Value::String(model.to_string());
+                    serde_json::to_string(&json).unwrap_or_else(|_| body.to_string())
+                }
+                Err(_) => body.to_string(),
+            }
+        }
+        _ => body.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stream_detection() {
+        // prompt 中出现 "stream":true 文本不应被当作流式请求
+        let body = r#"{"model":"claude-sonnet-4","stream":false,"messages":[{"role":"user","content":"set \"stream\":true"}]}"#;
+        let request = MessagesRequest::parse(body).unwrap();
+        assert!(!request.is_stream());
+        assert_eq!(request.last_user_prompt(), Some("set \"stream\":true"));
+
+        // 其他格式的 JSON 也应正确识别
+        let body = "{\n  \"model\" : \"claude-sonnet-4\",\n  \"stream\" :\ttrue,\n  \"messages\": []\n}";
+        assert!(MessagesRequest::parse(body).unwrap().is_stream());
+    }
+
+    #[test]
+    fn test_lenient_fields() {
+        // 字段类型不符时只忽略该字段，模型和流式判断不受影响
+        let body = r#"{"model":"claude-sonnet-4","max_tokens":1024.0,"stream":true,"metadata":null,"messages":[{"role":null,"content":"hi"},{"role":"user","content":"hello"}]}"#;
+        let request = MessagesRequest::parse(body).unwrap();
+        assert_eq!(request.model(), "claude-sonnet-4");
+        assert!(request.is_stream());
+        assert_eq!(request.max_tokens, Some(1024));
+        assert!(request.metadata.is_none());
+        assert_eq!(request.last_user_prompt(), Some("hello"));
+
+        assert!(MessagesRequest::parse("[1, 2]").is_none());
+    }
+
+    #[test]
+    fn test_body_for_model() {
+        let body = r#"{ "model": "claude-sonnet-4",  "max_tokens": 1024, "messages": [] }"#;
+        let request = MessagesRequest::parse(body);
+
+        // 模型未变化时逐字节保留原始请求体
+        assert_eq!(body_for_model(request.as_ref(), body, "claude-sonnet-4"), body);
+
+        let rewritten = body_for_model(request.as_ref(), body, "glm-4.6");
+        let json: Value = serde_json::from_str(&rewritten).unwrap();
+        assert_eq!(json["model"], "glm-4.6");
+        assert_eq!(json["max_tokens"], 1024);
+    }
+
+    #[test]
+    fn test_body_for_model_without_model() {
+        let body = r#"{"max_tokens": 1024, "messages": []}"#;
+        let request = MessagesRequest::parse(body);
+        assert!(request.is_some());
+
+        assert_eq!(body_for_model(request.as_ref(), body, "unknown"), body);
+    }
+}
@@ -0,0 +1,357 @@
+// 响应的本地缓存：同一 Profile 下请求体相同的请求直接返回缓存的响应（流式请求回放缓存的 SSE 事件）
+
+use axum::{
+    body::Body,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+use bytes::Bytes;
+use futures::StreamExt;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, Instant};
+use super::proxy_config::ResponseCacheConfig;
+use super::sse::SseEvent;
+
+/// 标记响应缓存状态的响应头（hit/miss/bypass）
+pub(super) const CACHE_STATUS_HEADER: &str = "x-prism-cache";
+
+/// 计算缓存键时忽略的字段（不影响响应内容，但每个会话都不同）
+const IGNORED_FIELDS: &[&str] = &["metadata"];
+
+lazy_static::lazy_static! {
+    static ref CONFIG: RwLock<ResponseCacheConfig> = RwLock::new(ResponseCacheConfig::default());
+    static ref CACHE: Mutex<ResponseCache> = Mutex::new(ResponseCache::default());
+}
+
+/// 请求的缓存状态
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum CacheStatus {
+    /// 命中缓存，未请求上游
+    Hit,
+    /// 未命中，请求上游后写入缓存
+    Miss,
+    /// 客户端要求跳过缓存（Cache-Control: no-cache / no-store）
+    Bypass,
+}
+
+impl CacheStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CacheStatus::Hit => "hit",
+            CacheStatus::Miss => "miss",
+            CacheStatus::Bypass => "bypass",
+        }
+    }
+
+    pub fn header_value(&self) -> HeaderValue {
+        HeaderValue::from_static(self.as_str())
+    }
+}
+
+/// 缓存的响应内容
+#[derive(Debug, Clone)]
+pub(super) enum CachedBody {
+    /// 非流式请求的 JSON 响应体
+    Json(String),
+    /// 流式请求的完整 SSE 事件序列
+    Events(Vec<SseEvent>),
+}
+
+impl CachedBody {
+    /// 占用的字节数（用于容量限制）
+    pub fn size(&self) -> usize {
+        match self {
+            CachedBody::Json(body) => body.len(),
+            CachedBody::Events(events) => events.iter()
+                .map(|event| event.event.as_ref().map_or(0, |name| name.len()) + event.data.len())
+                .sum(),
+        }
+    }
+}
+
+/// 一条缓存的响应
+#[derive(Debug, Clone)]
+pub(super) struct CachedResponse {
+    pub body: CachedBody,
+    /// 产生该响应的原始请求 ID
+    pub request_id: String,
+}
+
+struct CacheEntry {
+    response: CachedResponse,
+    size: usize,
+    expires_at: Instant,
+}
+
+#[derive(Default)]
+struct ResponseCache {
+    entries: HashMap<String, CacheEntry>,
+    /// 按写入顺序排列的缓存键，超出容量时从最早的开始淘汰
+    order: VecDeque<String>,
+    total_bytes: usize,
+}
+
+impl ResponseCache {
+    fn remove(&mut self, key: &str) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.total_bytes -= entry.size;
+            self.order.retain(|k| k != key);
+        }
+    }
+
+    /// 淘汰最早的缓存，直到总大小不超过上限
+    fn shrink_to(&mut self, max_bytes: usize) {
+        while self.total_bytes > max_bytes {
+            let Some(key) = self.order.pop_front() else {
+                break;
+            };
+            if let Some(entry) = self.entries.remove(&key) {
+                self.total_bytes -= entry.size;
+            }
+        }
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+        self.total_bytes = 0;
+    }
+}
+
+/// 更新缓存配置（关闭缓存时清空已有缓存）
+pub(super) fn configure(config: &ResponseCacheConfig) {
+    if let Ok(mut current) = CONFIG.write() {
+        *current = config.clone();
+    }
+    if let Ok(mut cache) = CACHE.lock() {
+        if config.enabled {
+            cache.shrink_to(max_bytes(config));
+        } else {
+            cache.clear();
+        }
+    }
+}
+
+fn max_bytes(config: &ResponseCacheConfig) -> usize {
+    (config.max_size_mb as usize).saturating_mul(1024 * 1024)
+}
+
+/// 请求的初始缓存状态：未启用缓存时为 None
+pub(super) fn status_for(headers: &HeaderMap) -> Option<CacheStatus> {
+    let enabled = CONFIG.read().map(|config| config.enabled).unwrap_or(false);
+    if !enabled {
+        return None;
+    }
+
+    let bypass = headers.get_all(header::CACHE_CONTROL).iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|directive| {
+            let directive = directive.trim();
+            directive.eq_ignore_ascii_case("no-cache") || directive.eq_ignore_ascii_case("no-store")
+        });
+    Some(if bypass { CacheStatus::Bypass } else { CacheStatus::Miss })
+}
+
+/// 计算缓存键：Profile ID + 规范化（键排序、去掉无关字段）后的请求体的 SHA-256
+pub(super) fn cache_key(profile_id: &str, body: &str) -> String {
+    let canonical = match serde_json::from_str::<Value>(body) {
+        Ok(Value::Object(mut map)) => {
+            for field in IGNORED_FIELDS {
+                map.remove(*field);
+            }
+            canonicalize(&Value::Object(map)).to_string()
+        }
+        _ => body.to_string(),
+    };
+
+    let mut hasher = Sha256::new();
+    hasher.update(profile_id.as_bytes());
+    hasher.update([0u8]);
+    hasher.update(canonical.as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+/// 按键名排序对象的字段，保证字段顺序不同的相同请求得到相同的键
+fn canonicalize(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            Value::Object(keys.into_iter().map(|key| (key.clone(), canonicalize(&map[key]))).collect())
+        }
+        Value::Array(items) => Value::Array(items.iter().map(canonicalize).collect()),
+        other => other.clone(),
+    }
+}
+
+/// 查找未过期的缓存响应
+pub(super) fn get(key: &str) -> Option<CachedResponse> {
+    let mut cache = CACHE.lock().ok()?;
+    let expired = cache.entries.get(key)?.expires_at <= Instant::now();
+    if expired {
+        cache.remove(key);
+        return None;
+    }
+    cache.entries.get(key).map(|entry| entry.response.clone())
+}
+
+/// 写入非流式请求的响应（只缓存成功的 Anthropic 格式响应体）
+pub(super) fn put_json(key: String, body: String, request_id: String) {
+    insert(key, CachedResponse { body: CachedBody::Json(body), request_id });
+}
+
+/// 写入流式请求的事件序列（只缓存以 message_stop 正常结束、没有 error 事件的流）
+pub(super) fn put_events(key: String, events: Vec<SseEvent>, request_id: String) {
+    let event_is = |event: &SseEvent, name: &str| event.event.as_deref() == Some(name);
+    let completed = events.last().is_some_and(|event| event_is(event, "message_stop"));
+    if !completed || events.iter().any(|event| event_is(event, "error")) {
+        log::debug!("Stream did not complete cleanly, not caching it");
+        return;
+    }
+    insert(key, CachedResponse { body: CachedBody::Events(events), request_id });
+}
+
+fn insert(key: String, response: CachedResponse) {
+    let Ok(config) = CONFIG.read().map(|config| config.clone()) else {
+        return;
+    };
+    let max_bytes = max_bytes(&config);
+    let size = response.body.size();
+    if !config.enabled || size > max_bytes {
+        return;
+    }
+
+    let Ok(mut cache) = CACHE.lock() else {
+        return;
+    };
+    cache.remove(&key);
+    cache.total_bytes += size;
+    cache.entries.insert(key.clone(), CacheEntry {
+        response,
+        size,
+        expires_at: Instant::now() + Duration::from_secs(config.ttl_secs),
+    });
+    cache.order.push_back(key);
+    cache.shrink_to(max_bytes);
+}
+
+/// 构建命中缓存时返回给客户端的响应（流式请求按原事件重新发送 SSE）
+pub(super) fn hit_response(body: CachedBody) -> Response {
+    let mut headers = HeaderMap::new();
+    headers.insert(CACHE_STATUS_HEADER, CacheStatus::Hit.header_value());
+
+    match body {
+        CachedBody::Json(body) => {
+            headers.insert(header::CONTENT_TYPE, HeaderValue::from_static("application/json"));
+            (StatusCode::OK, headers, body).into_response()
+        }
+        CachedBody::Events(events) => {
+            headers.insert(header::CONTENT_TYPE, HeaderValue::from_static("text/event-stream"));
+            headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+            (StatusCode::OK, headers, Body::from_stream(replay_stream(events))).into_response()
+        }
+    }
+}
+
+/// 按顺序重新发送缓存的事件，配置了回放间隔时在事件之间等待
+fn replay_stream(events: Vec<SseEvent>) -> impl futures::Stream<Item = Result<Bytes, std::io::Error>> {
+    let delay = CONFIG.read()
+        .map(|config| Duration::from_millis(config.replay_delay_ms))
+        .unwrap_or_default();
+
+    futures::stream::iter(events.into_iter().enumerate()).then(move |(index, event)| async move {
+        if index > 0 && !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+        Ok(Bytes::from(without_billable_usage(event).encode()))
+    })
+}
+
+/// 将事件中的 usage 计数清零：回放的响应没有产生上游费用
+fn without_billable_usage(mut event: SseEvent) -> SseEvent {
+    let Ok(mut json) = serde_json::from_str::<Value>(&event.data) else {
+        return event;
+    };
+
+    let mut changed = false;
+    if let Some(usage) = json.get_mut("usage") {
+        changed |= zero_counts(usage);
+    }
+    if let Some(usage) = json.get_mut("message").and_then(|message| message.get_mut("usage")) {
+        changed |= zero_counts(usage);
+    }
+    if changed {
+        event.data = json.to_string();
+    }
+    event
+}
+
+/// 将 usage 对象中的所有数值（包括嵌套对象中的）置为 0
+fn zero_counts(usage: &mut Value) -> bool {
+    let Some(map) = usage.as_object_mut() else {
+        return false;
+    };
+    for value in map.values_mut() {
+        if value.is_number() {
+            *value = Value::from(0);
+        } else if value.is_object() {
+            zero_counts(value);
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_key_is_canonical() {
+        let a = r#"{"model":"claude-haiku-4","max_tokens":32,"messages":[{"role":"user","content":"hi"}],"metadata":{"user_id":"a"}}"#;
+        let b = r#"{ "messages": [{"content": "hi", "role": "user"}], "max_tokens": 32, "model": "claude-haiku-4", "metadata": {"user_id": "b"} }"#;
+        assert_eq!(cache_key("p1", a), cache_key("p1", b));
+
+        // 不同 Profile 或不同请求不共享缓存
+        assert_ne!(cache_key("p1", a), cache_key("p2", a));
+        assert_ne!(cache_key("p1", a), cache_key("p1", &a.replace("\"hi\"", "\"hello\"")));
+    }
+
+    #[test]
+    fn test_shrink_evicts_oldest() {
+        let mut cache = ResponseCache::default();
+        for (key, body) in [("a", "1111"), ("b", "2222"), ("c", "3333")] {
+            cache.total_bytes += body.len();
+            cache.entries.insert(key.to_string(), CacheEntry {
+                response: CachedResponse { body: CachedBody::Json(body.to_string()), request_id: key.to_string() },
+                size: body.len(),
+                expires_at: Instant::now() + Duration::from_secs(60),
+            });
+            cache.order.push_back(key.to_string());
+        }
+
+        cache.shrink_to(8);
+        assert!(!cache.entries.contains_key("a"));
+        assert!(cache.entries.contains_key("b") && cache.entries.contains_key("c"));
+        assert_eq!(cache.total_bytes, 8);
+    }
+
+    #[test]
+    fn test_replayed_usage_is_zero() {
+        let delta = SseEvent {
+            event: Some("message_delta".to_string()),
+            data: r#"{"type":"message_delta","delta":{"stop_reason":"end_turn"},"usage":{"output_tokens":42,"cache_creation":{"ephemeral_5m_input_tokens":8}}}"#.to_string(),
+        };
+        let json: Value = serde_json::from_str(&without_billable_usage(delta).data).unwrap();
+        assert_eq!(json["usage"]["output_tokens"], 0);
+        assert_eq!(json["usage"]["cache_creation"]["ephemeral_5m_input_tokens"], 0);
+        assert_eq!(json["delta"]["stop_reason"], "end_turn");
+
+        // 没有 usage 的事件原样发送
+        let ping = SseEvent { event: Some("ping".to_string()), data: r#"{"type": "ping"}"#.to_string() };
+        assert_eq!(without_billable_usage(ping.clone()), ping);
+    }
+}
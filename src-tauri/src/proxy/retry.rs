@@ -0,0 +1,102 @@
+// 上游暂时性失败的重试：判断是否可重试，并计算等待时间（指数退避 + 抖动，遵循 retry-after）
+
+use std::time::Duration;
+use axum::http::{Method, StatusCode};
+use rand::Rng;
+use reqwest::header::HeaderMap;
+use crate::config::RetryPolicy;
+
+/// retry-after 超过该时长时不再等待，直接交给故障转移
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// 是否为可重试的上游状态码（限流、服务端错误和 Anthropic 的 529 overloaded_error）
+pub(super) fn is_retryable_status(status: StatusCode) -> bool {
+    matches!(status.as_u16(), 429 | 500 | 502 | 503 | 504 | 529)
+}
+
+/// 是否为可以安全重发的请求方法（非幂等的 POST 重发可能创建重复的 batch 或文件）
+pub(super) fn is_idempotent_method(method: &Method) -> bool {
+    matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS | Method::DELETE)
+}
+
+/// 解析上游要求的等待时间（retry-after-ms、retry-after 秒数或 HTTP 日期）
+pub(super) fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    if let Some(ms) = headers
+        .get("retry-after-ms")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<f64>().ok())
+        .filter(|ms| ms.is_finite() && *ms >= 0.0)
+    {
+        return Some(Duration::from_millis(ms as u64));
+    }
+
+    let value = headers.get(reqwest::header::RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(secs) = value.parse::<f64>() {
+        return (secs.is_finite() && secs >= 0.0).then(|| Duration::from_millis((secs * 1000.0) as u64));
+    }
+
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let wait_ms = date.timestamp_millis() - chrono::Utc::now().timestamp_millis();
+    Some(Duration::from_millis(wait_ms.max(0) as u64))
+}
+
+/// 第 retry 次重试前的退避时间（从 1 开始）：base * 2^(retry-1)，不超过上限，并在后一半范围内随机抖动
+fn backoff_delay(policy: &RetryPolicy, retry: u32) -> Duration {
+    let exponential = policy.base_delay_ms.saturating_mul(1u64 << (retry.saturating_sub(1)).min(20));
+    let capped = exponential.min(policy.max_delay_ms);
+    let jitter = rand::thread_rng().gen_range(0..=capped - capped / 2);
+    Duration::from_millis(capped / 2 + jitter)
+}
+
+/// 计算下一次重试前的等待时间，返回 None 表示不再重试
+///
+/// attempt 为已经完成的尝试次数。上游给出 retry-after 时以其为准，过长时放弃重试。
+pub(super) fn next_delay(policy: &RetryPolicy, attempt: u32, retry_after: Option<Duration>) -> Option<Duration> {
+    if attempt >= policy.max_attempts {
+        return None;
+    }
+    match retry_after {
+        Some(wait) if wait > MAX_RETRY_AFTER => None,
+        Some(wait) => Some(wait),
+        None => Some(backoff_delay(policy, attempt)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_delay() {
+        let policy = RetryPolicy { max_attempts: 3, base_delay_ms: 1_000, max_delay_ms: 1_500 };
+
+        // 第 1 次重试：[500, 1000]；第 2 次重试被上限截断：[750, 1500]
+        let first = next_delay(&policy, 1, None).unwrap();
+        assert!(first >= Duration::from_millis(500) && first <= Duration::from_millis(1_000));
+        let second = next_delay(&policy, 2, None).unwrap();
+        assert!(second >= Duration::from_millis(750) && second <= Duration::from_millis(1_500));
+
+        // 用尽尝试次数
+        assert_eq!(next_delay(&policy, 3, None), None);
+
+        // retry-after 优先，过长时放弃
+        assert_eq!(next_delay(&policy, 1, Some(Duration::from_secs(5))), Some(Duration::from_secs(5)));
+        assert_eq!(next_delay(&policy, 1, Some(Duration::from_secs(120))), None);
+    }
+
+    #[test]
+    fn test_retry_after() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(retry_after(&headers), None);
+
+        headers.insert("retry-after", "2".parse().unwrap());
+        assert_eq!(retry_after(&headers), Some(Duration::from_secs(2)));
+
+        headers.insert("retry-after-ms", "250".parse().unwrap());
+        assert_eq!(retry_after(&headers), Some(Duration::from_millis(250)));
+
+        let mut headers = HeaderMap::new();
+        headers.insert("retry-after", "Wed, 21 Oct 2015 07:28:00 GMT".parse().unwrap());
+        assert_eq!(retry_after(&headers), Some(Duration::ZERO));
+    }
+}
@@ -0,0 +1,207 @@
+// 增量 SSE 解析器：按顺序解析跨数据块的 Server-Sent Events
+
+/// 一个完整的 SSE 事件
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(super) struct SseEvent {
+    /// event 字段（未设置时为 None）
+    pub event: Option<String>,
+    /// data 字段，多行 data 以换行符连接
+    pub data: String,
+}
+
+impl SseEvent {
+    /// 编码为以空行结尾的 SSE 文本，多行 data 拆分为多个 data 行
+    pub fn encode(&self) -> String {
+        let mut text = String::new();
+        if let Some(event) = &self.event {
+            text.push_str(&format!("event: {}\n", event));
+        }
+        for line in self.data.split('\n') {
+            text.push_str(&format!("data: {}\n", line));
+        }
+        text.push('\n');
+        text
+    }
+}
+
+/// 增量 SSE 解析器
+///
+/// 以字节缓存未结束的行，行完整后再解码，数据块边界落在行中间或多字节 UTF-8 字符中间时不会丢失数据。
+/// 支持 `\n`、`\r\n` 和单独的 `\r` 换行、`:` 开头的注释行以及多行 data。
+#[derive(Debug, Default)]
+pub(super) struct SseParser {
+    /// 尚未遇到换行符的部分行
+    buffer: Vec<u8>,
+    /// 上一个数据块以 `\r` 结尾，下一个数据块开头的 `\n` 属于同一个换行符
+    skip_lf: bool,
+    event: Option<String>,
+    data: Vec<String>,
+}
+
+impl SseParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 输入一个数据块，返回其中完整结束的事件
+    pub fn feed(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        let mut events = Vec::new();
+        let mut rest = chunk;
+        if std::mem::take(&mut self.skip_lf) {
+            rest = rest.strip_prefix(b"\n").unwrap_or(rest);
+        }
+
+        while let Some(pos) = rest.iter().position(|b| *b == b'\n' || *b == b'\r') {
+            let (line, tail) = rest.split_at(pos);
+            rest = &tail[1..];
+            if tail[0] == b'\r' {
+                match rest.strip_prefix(b"\n") {
+                    Some(after_lf) => rest = after_lf,
+                    None => self.skip_lf = rest.is_empty(),
+                }
+            }
+
+            if self.buffer.is_empty() {
+                self.process_line(line, &mut events);
+            } else {
+                self.buffer.extend_from_slice(line);
+                let line = std::mem::take(&mut self.buffer);
+                self.process_line(&line, &mut events);
+            }
+        }
+
+        self.buffer.extend_from_slice(rest);
+        events
+    }
+
+    /// 流结束时处理剩余数据（上游最后一个事件可能没有以空行结尾）
+    pub fn finish(&mut self) -> Option<SseEvent> {
+        let mut events = Vec::new();
+        if !self.buffer.is_empty() {
+            let line = std::mem::take(&mut self.buffer);
+            self.process_line(&line, &mut events);
+        }
+        self.dispatch(&mut events);
+        events.pop()
+    }
+
+    fn process_line(&mut self, line: &[u8], events: &mut Vec<SseEvent>) {
+        // 空行表示事件结束
+        if line.is_empty() {
+            self.dispatch(events);
+            return;
+        }
+
+        let line = String::from_utf8_lossy(line);
+        // 注释行（如心跳 ": ping"）
+        if line.starts_with(':') {
+            return;
+        }
+
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line.as_ref(), ""),
+        };
+        match field {
+            "event" => self.event = Some(value.to_string()),
+            "data" => self.data.push(value.to_string()),
+            // id、retry 等字段与统计无关
+            _ => {}
+        }
+    }
+
+    fn dispatch(&mut self, events: &mut Vec<SseEvent>) {
+        let event = self.event.take();
+        if self.data.is_empty() {
+            return;
+        }
+        events.push(SseEvent {
+            event,
+            data: std::mem::take(&mut self.data).join("\n"),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STREAM: &str = concat!(
+        "event: message_start\r\n",
+        "data: {\"type\":\"message_start\",\"message\":{\"usage\":{\"input_tokens\":12}}}\r\n",
+        "\r\n",
+        ": ping\n",
+        "\n",
+        "event: content_block_delta\n",
+        "data: {\"type\":\"content_block_delta\",\"delta\":{\"text\":\"你好，世界 🌍\"}}\n",
+        "\n",
+        "event: multi\n",
+        "data: first\n",
+        "data:second\n",
+        "id: 7\n",
+        "\n",
+        "event: message_stop\n",
+        "data: {\"type\":\"message_stop\"}",
+    );
+
+    fn parse_chunks(chunks: &[&[u8]]) -> Vec<SseEvent> {
+        let mut parser = SseParser::new();
+        let mut events: Vec<SseEvent> = chunks.iter().flat_map(|chunk| parser.feed(chunk)).collect();
+        events.extend(parser.finish());
+        events
+    }
+
+    #[test]
+    fn test_parse_events() {
+        let events = parse_chunks(&[STREAM.as_bytes()]);
+
+        assert_eq!(events.len(), 4);
+        assert_eq!(events[0].event.as_deref(), Some("message_start"));
+        assert!(events[1].data.contains("你好，世界 🌍"));
+        assert_eq!(events[2], SseEvent { event: Some("multi".to_string()), data: "first\nsecond".to_string() });
+        // 最后一个事件没有以空行结尾
+        assert_eq!(events[3].event.as_deref(), Some("message_stop"));
+    }
+
+    #[test]
+    fn test_every_split_point() {
+        // 在每个字节位置切分（包括 \r\n 中间和多字节 UTF-8 字符中间），结果都应一致
+        let bytes = STREAM.as_bytes();
+        let expected = parse_chunks(&[bytes]);
+
+        for split in 0..=bytes.len() {
+            let (a, b) = bytes.split_at(split);
+            assert_eq!(parse_chunks(&[a, b]), expected, "split at {}", split);
+        }
+    }
+
+    #[test]
+    fn test_bare_cr_line_endings() {
+        let bytes = STREAM.replace("\r\n", "\n").replace('\n', "\r").into_bytes();
+        let expected = parse_chunks(&[STREAM.as_bytes()]);
+        assert_eq!(parse_chunks(&[&bytes]), expected);
+
+        // \r 落在数据块末尾时，无论下一个数据块是否以 \n 开头都应得到相同结果
+        let crlf = STREAM.replace("\r\n", "\n").replace('\n', "\r\n").into_bytes();
+        for bytes in [&bytes, &crlf] {
+            for split in 0..=bytes.len() {
+                let (a, b) = bytes.split_at(split);
+                assert_eq!(parse_chunks(&[a, b]), expected, "split at {}", split);
+            }
+        }
+    }
+
+    #[test]
+    fn test_single_byte_chunks() {
+        let bytes = STREAM.as_bytes();
+        let chunks: Vec<&[u8]> = bytes.chunks(1).collect();
+        assert_eq!(parse_chunks(&chunks), parse_chunks(&[bytes]));
+    }
+
+    #[test]
+    fn test_encode_roundtrip() {
+        let events = parse_chunks(&[STREAM.as_bytes()]);
+        let encoded: String = events.iter().map(SseEvent::encode).collect();
+        assert_eq!(parse_chunks(&[encoded.as_bytes()]), events);
+    }
+}
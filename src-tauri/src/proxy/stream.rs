@@ -1,23 +1,37 @@
 use axum::{
     body::Body,
-    http::{HeaderMap, StatusCode},
+    http::HeaderMap,
     response::{IntoResponse, Response},
 };
 use futures::stream::Stream;
 use std::pin::Pin;
 use std::sync::{Arc, Mutex};
 use std::task::{Context, Poll};
-use std::time::Instant;
+use std::future::Future;
+use std::time::{Duration, Instant};
 use bytes::Bytes;
 use tokio::sync::oneshot;
-use crate::logger::RequestLog;
+use crate::config::{Profile, UpstreamProtocol};
+use crate::logger::{CompletionReason, RequestLog, TokenSource};
+use super::balancer::PoolGuard;
+use super::http_client::RequestTimeouts;
+use super::openai::AnthropicSseStream;
+use super::response_cache;
+use super::sse::{SseEvent, SseParser};
 use super::token_counter::TokenCounter;
 
 /// 包装流，用于在转发的同时收集 Token 统计信息
 struct TokenCollectorStream {
     inner: Pin<Box<dyn Stream<Item = Result<Bytes, reqwest::Error>> + Send>>,
     token_stats: Arc<Mutex<TokenStats>>,
-    completion_tx: Option<oneshot::Sender<()>>,
+    completion_tx: Option<oneshot::Sender<CompletionReason>>,
+    /// 两个数据块之间允许的最长间隔（None 表示不限制）
+    idle_timeout: Option<Duration>,
+    idle_deadline: Option<Pin<Box<tokio::time::Sleep>>>,
+    /// 已因空闲超时中断，下一次轮询时结束流
+    timed_out: bool,
+    /// 增量 SSE 解析器，跨数据块缓存未完成的行和事件
+    parser: SseParser,
 }
 
 #[derive(Default, Clone)]
@@ -28,182 +42,265 @@ struct TokenStats {
     cache_read_input_tokens: i32,
     has_usage: bool,  // 标记是否已经收集到 usage 信息
     output_text: String,  // 收集输出文本用于本地计数
-    full_response: String,  // 收集完整的响应数据用于调试
+    full_response: Vec<u8>,  // 收集完整的响应数据用于调试（按字节保存，避免数据块切断 UTF-8 字符）
+    events: Option<Vec<SseEvent>>,  // 写入响应缓存的完整事件序列（未启用缓存时为 None）
+}
+
+impl TokenStats {
+    /// 记录一个数据块，并按顺序处理其中完整的 SSE 事件
+    fn collect(&mut self, parser: &mut SseParser, chunk: &[u8]) {
+        self.full_response.extend_from_slice(chunk);
+        for event in parser.feed(chunk) {
+            self.apply_event(&event);
+        }
+    }
+
+    /// 流结束时处理解析器中剩余的最后一个事件
+    fn finish(&mut self, parser: &mut SseParser) {
+        if let Some(event) = parser.finish() {
+            self.apply_event(&event);
+        }
+    }
+
+    fn apply_event(&mut self, event: &SseEvent) {
+        if let Some(events) = self.events.as_mut() {
+            events.push(event.clone());
+        }
+
+        let Ok(json) = serde_json::from_str::<serde_json::Value>(&event.data) else {
+            log::debug!("⚠️  Failed to parse JSON from SSE event {:?}", event.event);
+            return;
+        };
+
+        // 记录事件类型
+        let event_type = json.get("type").and_then(|t| t.as_str()).unwrap_or("unknown");
+        log::debug!("🔍 SSE event type: {}", event_type);
+
+        // 收集输出文本（用于本地 token 计数）
+        if event_type == "content_block_delta" {
+            if let Some(text) = json.get("delta").and_then(|d| d.get("text")).and_then(|t| t.as_str()) {
+                self.output_text.push_str(text);
+            }
+        }
+
+        // 尝试从顶层 usage 字段提取（message_delta 事件）
+        if let Some(usage) = json.get("usage") {
+            self.has_usage = true;
+            log::debug!("✅ Found usage in top-level: {:?}", usage);
+
+            // 使用最新值更新（SSE 流中的 usage 是累积的，每次都是完整值）
+            // 只在字段存在时更新，避免用 0 覆盖已有的非零值
+            if let Some(input) = usage.get("input_tokens")
+                .and_then(|t| t.as_i64())
+                .or_else(|| usage.get("prompt_tokens").and_then(|t| t.as_i64())) {
+                if input > 0 || self.input_tokens == 0 {
+                    self.input_tokens = input as i32;
+                }
+            }
+            if let Some(output) = usage.get("output_tokens")
+                .and_then(|t| t.as_i64())
+                .or_else(|| usage.get("completion_tokens").and_then(|t| t.as_i64())) {
+                if output > 0 || self.output_tokens == 0 {
+                    self.output_tokens = output as i32;
+                }
+            }
+            if let Some(cache_creation) = usage.get("cache_creation_input_tokens")
+                .and_then(|t| t.as_i64()) {
+                if cache_creation > 0 || self.cache_creation_input_tokens == 0 {
+                    self.cache_creation_input_tokens = cache_creation as i32;
+                }
+            }
+            if let Some(cache_read) = usage.get("cache_read_input_tokens")
+                .and_then(|t| t.as_i64()) {
+                if cache_read > 0 || self.cache_read_input_tokens == 0 {
+                    self.cache_read_input_tokens = cache_read as i32;
+                }
+            }
+            log::debug!("📊 Updated token stats: in={}, out={}, cache_creation={}, cache_read={}",
+                self.input_tokens, self.output_tokens,
+                self.cache_creation_input_tokens, self.cache_read_input_tokens);
+        }
+
+        // 尝试从 message.usage 字段提取（message_start 事件）
+        if let Some(usage) = json.get("message").and_then(|m| m.get("usage")) {
+            self.has_usage = true;
+            log::debug!("✅ Found usage in message: {:?}", usage);
+
+            if let Some(input) = usage.get("input_tokens")
+                .and_then(|t| t.as_i64())
+                .or_else(|| usage.get("prompt_tokens").and_then(|t| t.as_i64())) {
+                self.input_tokens = input as i32;
+            }
+            if let Some(output) = usage.get("output_tokens")
+                .and_then(|t| t.as_i64())
+                .or_else(|| usage.get("completion_tokens").and_then(|t| t.as_i64())) {
+                self.output_tokens = output as i32;
+            }
+            if let Some(cache_creation) = usage.get("cache_creation_input_tokens")
+                .and_then(|t| t.as_i64()) {
+                self.cache_creation_input_tokens = cache_creation as i32;
+            }
+            if let Some(cache_read) = usage.get("cache_read_input_tokens")
+                .and_then(|t| t.as_i64()) {
+                self.cache_read_input_tokens = cache_read as i32;
+            }
+            log::debug!("📊 Updated token stats: in={}, out={}, cache_creation={}, cache_read={}",
+                self.input_tokens, self.output_tokens,
+                self.cache_creation_input_tokens, self.cache_read_input_tokens);
+        }
+    }
+}
+
+impl TokenCollectorStream {
+    /// 流结束（正常结束、出错、超时或客户端断开）：处理剩余事件并发送完成信号，只在第一次调用时生效
+    fn complete(&mut self, reason: CompletionReason) {
+        let Some(tx) = self.completion_tx.take() else {
+            return;
+        };
+        if let Ok(mut stats) = self.token_stats.lock() {
+            stats.finish(&mut self.parser);
+        }
+        let _ = tx.send(reason);
+    }
+}
+
+impl Drop for TokenCollectorStream {
+    fn drop(&mut self) {
+        // 流在结束前被丢弃，说明客户端已断开连接
+        if self.completion_tx.is_some() {
+            log::warn!("Client disconnected before the stream completed");
+            self.complete(CompletionReason::ClientCancelled);
+        }
+    }
 }
 
 impl Stream for TokenCollectorStream {
     type Item = Result<Bytes, std::io::Error>;
 
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if self.timed_out {
+            return Poll::Ready(None);
+        }
+
         match self.inner.as_mut().poll_next(cx) {
             Poll::Ready(Some(Ok(chunk))) => {
-                // 克隆 chunk 用于后台统计，立即返回原始 chunk（零延迟转发）
-                let chunk_clone = chunk.clone();
-                let stats_clone = Arc::clone(&self.token_stats);
-
-                // 在后台线程处理 token 统计，完全不阻塞转发
-                tokio::spawn(async move {
-                    if let Ok(text) = std::str::from_utf8(&chunk_clone) {
-                        // 调试日志（仅在 debug 模式下）
-                        if log::log_enabled!(log::Level::Debug) {
-                            let preview = if text.len() > 500 {
-                                format!("{}...", &text[..500])
-                            } else {
-                                text.to_string()
-                            };
-                            log::debug!("📦 Received chunk ({} bytes): {}", chunk_clone.len(), preview);
-                        }
-
-                        if let Ok(mut stats) = stats_clone.lock() {
-                            // 收集完整的响应数据
-                            stats.full_response.push_str(text);
-
-                            for line in text.lines() {
-                                if line.starts_with("data: ") {
-                                    let json_str = &line[6..];
-
-                                    if let Ok(json) = serde_json::from_str::<serde_json::Value>(json_str) {
-                                        // 记录事件类型
-                                        let event_type = json.get("type").and_then(|t| t.as_str()).unwrap_or("unknown");
-                                        log::debug!("🔍 SSE event type: {}", event_type);
-
-                                        // 收集输出文本（用于本地 token 计数）
-                                        if event_type == "content_block_delta" {
-                                            if let Some(delta) = json.get("delta") {
-                                                if let Some(text) = delta.get("text").and_then(|t| t.as_str()) {
-                                                    stats.output_text.push_str(text);
-                                                }
-                                            }
-                                        }
-
-                                        // 尝试从顶层 usage 字段提取（message_delta 事件）
-                                        if let Some(usage) = json.get("usage") {
-                                            stats.has_usage = true;
-                                            log::debug!("✅ Found usage in top-level: {:?}", usage);
-
-                                            // 使用最新值更新（SSE 流中的 usage 是累积的，每次都是完整值）
-                                            // 只在字段存在时更新，避免用 0 覆盖已有的非零值
-                                            if let Some(input) = usage.get("input_tokens")
-                                                .and_then(|t| t.as_i64())
-                                                .or_else(|| usage.get("prompt_tokens").and_then(|t| t.as_i64())) {
-                                                if input > 0 || stats.input_tokens == 0 {
-                                                    stats.input_tokens = input as i32;
-                                                }
-                                            }
-                                            if let Some(output) = usage.get("output_tokens")
-                                                .and_then(|t| t.as_i64())
-                                                .or_else(|| usage.get("completion_tokens").and_then(|t| t.as_i64())) {
-                                                if output > 0 || stats.output_tokens == 0 {
-                                                    stats.output_tokens = output as i32;
-                                                }
-                                            }
-                                            if let Some(cache_creation) = usage.get("cache_creation_input_tokens")
-                                                .and_then(|t| t.as_i64()) {
-                                                if cache_creation > 0 || stats.cache_creation_input_tokens == 0 {
-                                                    stats.cache_creation_input_tokens = cache_creation as i32;
-                                                }
-                                            }
-                                            if let Some(cache_read) = usage.get("cache_read_input_tokens")
-                                                .and_then(|t| t.as_i64()) {
-                                                if cache_read > 0 || stats.cache_read_input_tokens == 0 {
-                                                    stats.cache_read_input_tokens = cache_read as i32;
-                                                }
-                                            }
-                                            log::debug!("📊 Updated token stats: in={}, out={}, cache_creation={}, cache_read={}",
-                                                stats.input_tokens, stats.output_tokens,
-                                                stats.cache_creation_input_tokens, stats.cache_read_input_tokens);
-                                        }
-
-                                        // 尝试从 message.usage 字段提取（message_start 事件）
-                                        if let Some(message) = json.get("message") {
-                                            if let Some(usage) = message.get("usage") {
-                                                stats.has_usage = true;
-                                                log::debug!("✅ Found usage in message: {:?}", usage);
-
-                                                if let Some(input) = usage.get("input_tokens")
-                                                    .and_then(|t| t.as_i64())
-                                                    .or_else(|| usage.get("prompt_tokens").and_then(|t| t.as_i64())) {
-                                                    stats.input_tokens = input as i32;
-                                                }
-                                                if let Some(output) = usage.get("output_tokens")
-                                                    .and_then(|t| t.as_i64())
-                                                    .or_else(|| usage.get("completion_tokens").and_then(|t| t.as_i64())) {
-                                                    stats.output_tokens = output as i32;
-                                                }
-                                                if let Some(cache_creation) = usage.get("cache_creation_input_tokens")
-                                                    .and_then(|t| t.as_i64()) {
-                                                    stats.cache_creation_input_tokens = cache_creation as i32;
-                                                }
-                                                if let Some(cache_read) = usage.get("cache_read_input_tokens")
-                                                    .and_then(|t| t.as_i64()) {
-                                                    stats.cache_read_input_tokens = cache_read as i32;
-                                                }
-                                                log::debug!("📊 Updated token stats: in={}, out={}, cache_creation={}, cache_read={}",
-                                                    stats.input_tokens, stats.output_tokens,
-                                                    stats.cache_creation_input_tokens, stats.cache_read_input_tokens);
-                                            }
-                                        }
-                                    } else {
-                                        log::debug!("⚠️  Failed to parse JSON from SSE line");
-                                    }
-                                }
-                            }
-                        }
+                // 收到数据，重新计算空闲超时
+                if let Some(idle_timeout) = self.idle_timeout {
+                    if let Some(deadline) = self.idle_deadline.as_mut() {
+                        deadline.as_mut().reset(tokio::time::Instant::now() + idle_timeout);
                     }
-                });
+                }
+
+                // 调试日志（仅在 debug 模式下）
+                if log::log_enabled!(log::Level::Debug) {
+                    let text = String::from_utf8_lossy(&chunk);
+                    let preview: String = text.chars().take(500).collect();
+                    log::debug!("📦 Received chunk ({} bytes): {}", chunk.len(), preview);
+                }
+
+                // 按到达顺序同步解析，保证跨数据块的事件不丢失、统计结果确定
+                let this = &mut *self;
+                if let Ok(mut stats) = this.token_stats.lock() {
+                    stats.collect(&mut this.parser, &chunk);
+                }
 
-                // 立即返回 chunk，不等待统计完成
                 Poll::Ready(Some(Ok(chunk)))
             }
             Poll::Ready(Some(Err(e))) => {
                 log::error!("Stream error: {}", e);
                 // 流出错时也要发送完成信号
-                if let Some(tx) = self.completion_tx.take() {
-                    let _ = tx.send(());
-                }
+                self.complete(CompletionReason::UpstreamError);
                 Poll::Ready(Some(Err(std::io::Error::new(std::io::ErrorKind::Other, e))))
             }
             Poll::Ready(None) => {
                 // 流结束时发送完成信号
-                if let Some(tx) = self.completion_tx.take() {
-                    log::debug!("Stream completed, sending completion signal");
-                    let _ = tx.send(());
-                }
+                log::debug!("Stream completed, sending completion signal");
+                self.complete(CompletionReason::Completed);
                 Poll::Ready(None)
             },
-            Poll::Pending => Poll::Pending,
+            Poll::Pending => {
+                let idle_elapsed = self.idle_deadline.as_mut()
+                    .is_some_and(|deadline| deadline.as_mut().poll(cx).is_ready());
+                if !idle_elapsed {
+                    return Poll::Pending;
+                }
+
+                let idle_secs = self.idle_timeout.map(|t| t.as_secs()).unwrap_or_default();
+                log::error!("Stream idle for {}s, aborting", idle_secs);
+                self.timed_out = true;
+                self.complete(CompletionReason::IdleTimeout);
+                Poll::Ready(Some(Err(std::io::Error::new(
+                    std::io::ErrorKind::TimedOut,
+                    format!("upstream stream idle for {}s", idle_secs),
+                ))))
+            }
         }
     }
 }
 
+/// 流结束后更新日志和写入缓存所需的请求信息
+pub(super) struct StreamContext {
+    /// 已保存的基础日志，流结束后 UPDATE
+    pub request_log: RequestLog,
+    pub start_time: Instant,
+    /// 请求体，上游未返回 usage 时用于计算 input tokens
+    pub request_body: String,
+    /// 保存基础日志的任务，最终的 UPDATE 必须在它完成之后执行
+    pub initial_save: tokio::task::JoinHandle<()>,
+    /// 响应缓存键，流正常结束后写入缓存
+    pub cache_key: Option<String>,
+}
+
 /// 处理流式响应（真正的流式转发）
 pub(super) async fn handle_stream_response(
     response: reqwest::Response,
-    request_log: RequestLog,
-    start_time: Instant,
-    request_body: String,  // 添加请求体参数用于计算 input tokens
+    context: StreamContext,
+    profile: &Profile,  // 上游协议和空闲超时取自 Profile
+    pool_guard: Option<PoolGuard>,  // 负载均衡池的并发占用，流结束后释放
     app_handle: tauri::AppHandle,
-) -> Result<Response, StatusCode> {
+) -> Response {
+    let StreamContext { request_log, start_time, request_body, initial_save, cache_key } = context;
+    let is_translated = profile.upstream_protocol == UpstreamProtocol::OpenAI;
+    let idle_timeout = RequestTimeouts::for_profile(&profile.timeouts, true).idle;
+
     // 获取响应头
     let mut response_headers = HeaderMap::new();
     for (key, value) in response.headers().iter() {
+        // 转换后的流长度与上游不同，不能沿用上游的 content-length
+        if is_translated && (key == "content-length" || key == "content-encoding") {
+            continue;
+        }
         if let Ok(value) = axum::http::HeaderValue::from_bytes(value.as_bytes()) {
             response_headers.insert(key.clone(), value);
         }
     }
 
     // 创建共享的 Token 统计
-    let token_stats = Arc::new(Mutex::new(TokenStats::default()));
+    let token_stats = Arc::new(Mutex::new(TokenStats {
+        events: cache_key.as_ref().map(|_| Vec::new()),
+        ..TokenStats::default()
+    }));
     let token_stats_clone = Arc::clone(&token_stats);
 
     // 创建 channel 用于流完成通知
     let (completion_tx, completion_rx) = oneshot::channel();
 
+    // OpenAI 上游的 SSE 先转换为 Anthropic 事件，再交给统计流处理
+    let inner: Pin<Box<dyn Stream<Item = Result<Bytes, reqwest::Error>> + Send>> = if is_translated {
+        Box::pin(AnthropicSseStream::new(Box::pin(response.bytes_stream()), &request_log.forwarded_model))
+    } else {
+        Box::pin(response.bytes_stream())
+    };
+
     // 创建包装流
     let stream = TokenCollectorStream {
-        inner: Box::pin(response.bytes_stream()),
+        inner,
         token_stats: token_stats_clone,
         completion_tx: Some(completion_tx),
+        idle_timeout,
+        idle_deadline: idle_timeout.map(|timeout| Box::pin(tokio::time::sleep(timeout))),
+        timed_out: false,
+        parser: SseParser::new(),
     };
 
     // 在流结束后更新日志（等待流真正完成的信号）
@@ -211,65 +308,99 @@ pub(super) async fn handle_stream_response(
     let request_body_clone = request_body.clone();
     let app_handle_clone = app_handle.clone();
     tokio::spawn(async move {
-        // 等待流完成信号，最多等待 120 秒（超长响应的兜底）
-        let timeout_duration = tokio::time::Duration::from_secs(120);
-        match tokio::time::timeout(timeout_duration, completion_rx).await {
-            Ok(Ok(())) => {
-                log::debug!("Received stream completion signal");
-            }
-            Ok(Err(_)) => {
-                log::warn!("Stream completion channel closed unexpectedly");
+        // 等待流完成信号：流正常结束、出错、空闲超时或被丢弃（客户端断开）时都会立即发送
+        let reason = match completion_rx.await {
+            Ok(reason) => {
+                log::debug!("Received stream completion signal: {}", reason.as_str());
+                reason
             }
             Err(_) => {
-                log::warn!("Stream completion timeout after 120s");
+                log::warn!("Stream completion channel closed unexpectedly");
+                CompletionReason::ClientCancelled
             }
-        }
+        };
 
-        // 额外等待 100ms 确保最后的 token 统计已经处理完
-        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+        // 流已结束，释放负载均衡池的并发占用
+        drop(pool_guard);
 
         let mut log = request_log_clone;
-        if let Ok(stats) = token_stats.lock() {
+        log.completion_reason = Some(reason.as_str().to_string());
+        if let Ok(mut stats) = token_stats.lock() {
+            // 完整结束的流写入响应缓存
+            if let (Some(key), Some(events)) = (cache_key, stats.events.take()) {
+                if reason == CompletionReason::Completed {
+                    response_cache::put_events(key, events, log.request_id.clone());
+                }
+            }
+
             log.input_tokens = stats.input_tokens;
             log.output_tokens = stats.output_tokens;
             log.cache_creation_input_tokens = stats.cache_creation_input_tokens;
             log.cache_read_input_tokens = stats.cache_read_input_tokens;
             log.duration_ms = start_time.elapsed().as_millis() as i64;
 
-            // 如果上游 API 没有返回 token 统计（或返回 0），使用本地计数作为兜底
-            if !stats.has_usage || (stats.input_tokens == 0 && stats.output_tokens == 0) {
+            // 判断 token 数量来源：上游 usage 缺失（或全为 0）时全部本地估算，
+            // 只缺输入或输出一项时由本地估算补齐缺失部分
+            let has_input = stats.input_tokens > 0
+                || stats.cache_creation_input_tokens > 0
+                || stats.cache_read_input_tokens > 0;
+            let has_output = stats.output_tokens > 0;
+            let source = if !stats.has_usage || (!has_input && !has_output) {
+                TokenSource::LocalEstimate
+            } else if has_input && has_output {
+                TokenSource::Upstream
+            } else {
+                TokenSource::Partial
+            };
+
+            if source == TokenSource::LocalEstimate {
                 log::warn!("⚠️  No valid usage info from upstream API, using local token counting");
+            } else if source == TokenSource::Partial {
+                log::warn!("⚠️  Incomplete usage info from upstream API (in: {}, out: {}), filling the rest locally",
+                    stats.input_tokens, stats.output_tokens);
+            }
+
+            log.token_source = source.as_str().to_string();
+            if source != TokenSource::Upstream {
+                // 尝试使用本地 token 计数（按转发的模型选择分词器）
+                if let Some(counter) = TokenCounter::for_model(&log.forwarded_model) {
+                    log.tokenizer = Some(counter.tokenizer().as_str().to_string());
 
-                // 尝试使用本地 token 计数
-                if let Ok(counter) = TokenCounter::new() {
                     // 计算 input tokens（从原始请求体）
-                    if !request_body_clone.is_empty() {
+                    if !has_input && !request_body_clone.is_empty() {
                         let local_input_tokens = counter.count_input_tokens(&request_body_clone);
                         log.input_tokens = local_input_tokens;
-                        log::info!("🔢 Local count - input tokens: {}", local_input_tokens);
+                        log::info!("🔢 Local count ({}) - input tokens: {}", counter.tokenizer().as_str(), local_input_tokens);
                     }
 
                     // 计算 output tokens（从收集的输出文本）
-                    if !stats.output_text.is_empty() {
+                    if !has_output && !stats.output_text.is_empty() {
                         let local_output_tokens = counter.count_output_tokens(&stats.output_text);
                         log.output_tokens = local_output_tokens;
-                        log::info!("🔢 Local count - output tokens: {}", local_output_tokens);
+                        log::info!("🔢 Local count ({}) - output tokens: {}", counter.tokenizer().as_str(), local_output_tokens);
                     }
                 } else {
                     log::error!("Failed to initialize token counter");
+                    // 无法估算时，只有上游给出的部分可信
+                    let fallback = if source == TokenSource::Partial { TokenSource::Upstream } else { TokenSource::None };
+                    log.token_source = fallback.as_str().to_string();
                 }
             }
 
             // 如果 output_tokens 为 0，保存完整响应体用于调试
             if log.output_tokens == 0 && !stats.full_response.is_empty() {
                 log::warn!("⚠️  Output tokens is 0, saving full response body for debugging");
-                log.response_body = Some(stats.full_response.clone());
+                log.response_body = Some(String::from_utf8_lossy(&stats.full_response).into_owned());
             }
 
             // 输出流式响应的统计信息
             let total_tokens = log.input_tokens + log.output_tokens;
 
-            if stats.has_usage && (stats.input_tokens > 0 || stats.output_tokens > 0) {
+            if reason != CompletionReason::Completed {
+                log::warn!("⚠️  Stream ended early: {}", reason.as_str());
+            }
+
+            if source == TokenSource::Upstream {
                 log::info!("✅ Stream completed");
                 log::info!("📊 Stats: {} tokens (in: {}, out: {}) | {}ms",
                     total_tokens, log.input_tokens, log.output_tokens, log.duration_ms);
@@ -279,18 +410,51 @@ pub(super) async fn handle_stream_response(
                         stats.cache_creation_input_tokens, stats.cache_read_input_tokens);
                 }
             } else {
-                log::info!("✅ Stream completed (local counting)");
+                log::info!("✅ Stream completed ({})", source.as_str());
                 log::info!("📊 Stats: {} tokens (in: {}, out: {}) | {}ms",
                     total_tokens, log.input_tokens, log.output_tokens, log.duration_ms);
             }
             log::info!("{}\n", "=".repeat(60));
         }
 
-        // 使用 UPDATE 更新已存在的日志记录
+        // 使用 UPDATE 更新已存在的日志记录（短流可能在基础日志写入之前就已结束）
+        if let Err(e) = initial_save.await {
+            log::error!("Failed to wait for the initial log save: {}", e);
+        }
         crate::logger::update_log(log, Some(&app_handle_clone)).await;
     });
 
     // 立即返回流式响应
     let body = Body::from_stream(stream);
-    Ok((response_headers, body).into_response())
+    (response_headers, body).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_stats_across_chunk_boundaries() {
+        let body = concat!(
+            "event: message_start\n",
+            "data: {\"type\":\"message_start\",\"message\":{\"usage\":{\"input_tokens\":25,\"output_tokens\":1}}}\n\n",
+            "event: content_block_delta\n",
+            "data: {\"type\":\"content_block_delta\",\"delta\":{\"type\":\"text_delta\",\"text\":\"你好\"}}\n\n",
+            "event: message_delta\n",
+            "data: {\"type\":\"message_delta\",\"usage\":{\"output_tokens\":42}}",
+        ).as_bytes();
+
+        // 每 3 个字节一个数据块：行、JSON 和中文字符都会被切断
+        let mut stats = TokenStats::default();
+        let mut parser = SseParser::new();
+        for chunk in body.chunks(3) {
+            stats.collect(&mut parser, chunk);
+        }
+        stats.finish(&mut parser);
+
+        assert!(stats.has_usage);
+        assert_eq!((stats.input_tokens, stats.output_tokens), (25, 42));
+        assert_eq!(stats.output_text, "你好");
+        assert_eq!(stats.full_response, body);
+    }
 }
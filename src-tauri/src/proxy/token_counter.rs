@@ -1,60 +1,150 @@
-use tiktoken_rs::cl100k_base;
+use base64::Engine;
+use lazy_static::lazy_static;
+use serde_json::Value;
+use tiktoken_rs::{cl100k_base, o200k_base};
+
+/// 每条消息的固定开销（角色标记和分隔符）
+const MESSAGE_OVERHEAD_TOKENS: usize = 4;
+
+/// 每个工具定义的固定开销（工具定义外层结构）
+const TOOL_OVERHEAD_TOKENS: usize = 8;
+
+/// 每个内容块的固定开销（块类型标记）
+const BLOCK_OVERHEAD_TOKENS: usize = 2;
+
+/// 图片长边的最大像素数（超过时上游会先缩放）
+const IMAGE_MAX_EDGE: f64 = 1568.0;
+
+/// 单张图片的 token 上限（约 1.15 百万像素）
+const IMAGE_MAX_TOKENS: usize = 1600;
+
+/// 每 750 像素约 1 个 token
+const IMAGE_PIXELS_PER_TOKEN: f64 = 750.0;
+
+/// PDF 每页的估算 token 数（页面文本约 1000 + 页面图片约 1500）
+const PDF_PAGE_TOKENS: usize = 2500;
+
+/// Claude 近似分词器的放大系数：经验估计同样的文本 Claude 分词器产生的 token 比 cl100k_base 多约 15%
+/// （尚未用上游计数校准，见 test_claude_code_corpus_matches_upstream）
+const CLAUDE_TOKEN_MULTIPLIER: f64 = 1.15;
+
+/// 本地计数使用的分词器
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tokenizer {
+    /// OpenAI cl100k_base（GPT-4 / GPT-3.5 及大多数兼容模型）
+    Cl100k,
+    /// OpenAI o200k_base（GPT-4o、o 系列及更新的模型）
+    O200k,
+    /// Claude 近似：cl100k_base 计数乘以放大系数
+    ClaudeApprox,
+}
+
+impl Tokenizer {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Tokenizer::Cl100k => "cl100k_base",
+            Tokenizer::O200k => "o200k_base",
+            Tokenizer::ClaudeApprox => "claude_approx",
+        }
+    }
+
+    /// 按转发的模型名称选择分词器（按顺序匹配注册表，未命中时使用 cl100k_base）
+    ///
+    /// 带供应商前缀的模型名称（如 "openai/gpt-4o"）只匹配最后一段。
+    pub fn for_model(model: &str) -> Self {
+        let name = model.rsplit('/').next().unwrap_or(model);
+        TOKENIZER_REGISTRY
+            .iter()
+            .find(|(pattern, _)| pattern.is_match(name))
+            .map(|(_, tokenizer)| *tokenizer)
+            .unwrap_or(Tokenizer::Cl100k)
+    }
+}
+
+/// 模型名称模式 → 分词器（按顺序匹配，忽略大小写）
+const TOKENIZER_RULES: &[(&str, Tokenizer)] = &[
+    (r"^(gpt-4o|gpt-4\.1|gpt-4\.5|gpt-5|chatgpt-4o|o1|o3|o4)", Tokenizer::O200k),
+    (r"^(gpt-4|gpt-3\.5|text-embedding-3|text-embedding-ada)", Tokenizer::Cl100k),
+    (r"claude", Tokenizer::ClaudeApprox),
+];
+
+lazy_static! {
+    static ref TOKENIZER_REGISTRY: Vec<(regex::Regex, Tokenizer)> = TOKENIZER_RULES
+        .iter()
+        .filter_map(|(pattern, tokenizer)| {
+            regex::RegexBuilder::new(pattern)
+                .case_insensitive(true)
+                .build()
+                .ok()
+                .map(|re| (re, *tokenizer))
+        })
+        .collect();
+
+    /// 共享的计数器实例（加载分词器词表开销较大，只构建一次）
+    static ref CL100K_COUNTER: Option<TokenCounter> = build_shared(Tokenizer::Cl100k);
+    static ref O200K_COUNTER: Option<TokenCounter> = build_shared(Tokenizer::O200k);
+    static ref CLAUDE_COUNTER: Option<TokenCounter> = build_shared(Tokenizer::ClaudeApprox);
+}
+
+fn build_shared(tokenizer: Tokenizer) -> Option<TokenCounter> {
+    TokenCounter::with_tokenizer(tokenizer)
+        .map_err(|e| log::error!("Failed to initialize {} tokenizer: {}", tokenizer.as_str(), e))
+        .ok()
+}
 
 /// 本地 token 计数器（用于上游 API 不返回 token 统计时的兜底方案）
 pub struct TokenCounter {
     bpe: tiktoken_rs::CoreBPE,
+    tokenizer: Tokenizer,
 }
 
 impl TokenCounter {
-    /// 创建新的 token 计数器
+    /// 创建新的 token 计数器（cl100k_base）
     pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
-        let bpe = cl100k_base()?;
-        Ok(Self { bpe })
+        Self::with_tokenizer(Tokenizer::Cl100k)
+    }
+
+    /// 使用指定分词器创建 token 计数器
+    pub fn with_tokenizer(tokenizer: Tokenizer) -> Result<Self, Box<dyn std::error::Error>> {
+        let bpe = match tokenizer {
+            Tokenizer::O200k => o200k_base()?,
+            Tokenizer::Cl100k | Tokenizer::ClaudeApprox => cl100k_base()?,
+        };
+        Ok(Self { bpe, tokenizer })
+    }
+
+    /// 获取适用于指定模型的共享计数器
+    pub fn for_model(model: &str) -> Option<&'static TokenCounter> {
+        let counter = match Tokenizer::for_model(model) {
+            Tokenizer::Cl100k => &*CL100K_COUNTER,
+            Tokenizer::O200k => &*O200K_COUNTER,
+            Tokenizer::ClaudeApprox => &*CLAUDE_COUNTER,
+        };
+        counter.as_ref()
+    }
+
+    /// 当前使用的分词器
+    pub fn tokenizer(&self) -> Tokenizer {
+        self.tokenizer
     }
 
     /// 计算文本的 token 数量
     pub fn count_tokens(&self, text: &str) -> usize {
-        self.bpe.encode_with_special_tokens(text).len()
+        let tokens = self.bpe.encode_with_special_tokens(text).len();
+        match self.tokenizer {
+            Tokenizer::ClaudeApprox => (tokens as f64 * CLAUDE_TOKEN_MULTIPLIER).round() as usize,
+            Tokenizer::Cl100k | Tokenizer::O200k => tokens,
+        }
     }
 
     /// 从请求体中提取并计算 input tokens
+    ///
+    /// 覆盖 system（字符串或块数组）、tools 定义以及消息中的所有内容块类型：
+    /// text、image、document、tool_use、tool_result、thinking 等。
     pub fn count_input_tokens(&self, request_body: &str) -> i32 {
         // 解析请求体 JSON
-        if let Ok(json) = serde_json::from_str::<serde_json::Value>(request_body) {
-            let mut total_tokens = 0;
-
-            // 计算 system prompt tokens
-            if let Some(system) = json.get("system").and_then(|s| s.as_str()) {
-                total_tokens += self.count_tokens(system);
-            }
-
-            // 计算 messages tokens
-            if let Some(messages) = json.get("messages").and_then(|m| m.as_array()) {
-                for message in messages {
-                    // 计算 role tokens (通常很少)
-                    if let Some(role) = message.get("role").and_then(|r| r.as_str()) {
-                        total_tokens += self.count_tokens(role);
-                    }
-
-                    // 计算 content tokens
-                    if let Some(content) = message.get("content") {
-                        if let Some(text) = content.as_str() {
-                            // 简单文本内容
-                            total_tokens += self.count_tokens(text);
-                        } else if let Some(arr) = content.as_array() {
-                            // 复杂内容（包含 text, image 等）
-                            for item in arr {
-                                if let Some(text) = item.get("text").and_then(|t| t.as_str()) {
-                                    total_tokens += self.count_tokens(text);
-                                }
-                                // 图片等其他类型暂时忽略，因为计算比较复杂
-                            }
-                        }
-                    }
-                }
-            }
-
-            total_tokens as i32
+        if let Ok(json) = serde_json::from_str::<Value>(request_body) {
+            self.count_request(&json) as i32
         } else {
             log::warn!("Failed to parse request body for token counting");
             0
@@ -65,6 +155,120 @@ impl TokenCounter {
     pub fn count_output_tokens(&self, output_text: &str) -> i32 {
         self.count_tokens(output_text) as i32
     }
+
+    /// 计算整个请求的 input tokens
+    fn count_request(&self, json: &Value) -> usize {
+        let mut total_tokens = 0;
+
+        // 计算 system prompt tokens（字符串或块数组）
+        if let Some(system) = json.get("system") {
+            total_tokens += self.count_content(system);
+        }
+
+        // 计算工具定义 tokens
+        if let Some(tools) = json.get("tools").and_then(|t| t.as_array()) {
+            for tool in tools {
+                total_tokens += self.count_tool_definition(tool);
+            }
+        }
+
+        // 计算 messages tokens
+        if let Some(messages) = json.get("messages").and_then(|m| m.as_array()) {
+            for message in messages {
+                total_tokens += MESSAGE_OVERHEAD_TOKENS;
+                if let Some(content) = message.get("content") {
+                    total_tokens += self.count_content(content);
+                }
+            }
+        }
+
+        total_tokens
+    }
+
+    /// 计算工具定义的 tokens（名称、描述和参数 schema）
+    fn count_tool_definition(&self, tool: &Value) -> usize {
+        let mut tokens = TOOL_OVERHEAD_TOKENS;
+
+        if let Some(name) = tool.get("name").and_then(|n| n.as_str()) {
+            tokens += self.count_tokens(name);
+        }
+        if let Some(description) = tool.get("description").and_then(|d| d.as_str()) {
+            tokens += self.count_tokens(description);
+        }
+        if let Some(schema) = tool.get("input_schema") {
+            tokens += self.count_json(schema);
+        }
+
+        tokens
+    }
+
+    /// 计算内容（字符串或内容块数组）的 tokens
+    fn count_content(&self, content: &Value) -> usize {
+        match content {
+            Value::String(text) => self.count_tokens(text),
+            Value::Array(blocks) => blocks.iter().map(|block| self.count_block(block)).sum(),
+            Value::Null => 0,
+            other => self.count_json(other),
+        }
+    }
+
+    /// 计算单个内容块的 tokens
+    fn count_block(&self, block: &Value) -> usize {
+        let block_type = block.get("type").and_then(|t| t.as_str()).unwrap_or("text");
+        let str_field = |field: &str| block.get(field).and_then(|v| v.as_str()).unwrap_or("");
+
+        let tokens = match block_type {
+            "text" => self.count_tokens(str_field("text")),
+            "image" => estimate_image_tokens(block.get("source")),
+            "document" => {
+                let title = self.count_tokens(str_field("title")) + self.count_tokens(str_field("context"));
+                title + self.count_document(block.get("source"))
+            }
+            "tool_use" | "server_tool_use" => {
+                self.count_tokens(str_field("name"))
+                    + block.get("input").map_or(0, |input| self.count_json(input))
+            }
+            "tool_result" => block.get("content").map_or(0, |content| self.count_content(content)),
+            "thinking" => self.count_tokens(str_field("thinking")),
+            // 加密的思考内容无法分词，按约 4 字节 1 个 token 估算
+            "redacted_thinking" => str_field("data").len() / 4,
+            // 其他块类型（如 web_search_tool_result）按序列化后的 JSON 计算
+            _ => self.count_json(block),
+        };
+
+        BLOCK_OVERHEAD_TOKENS + tokens
+    }
+
+    /// 计算文档块 source 的 tokens
+    fn count_document(&self, source: Option<&Value>) -> usize {
+        let Some(source) = source else {
+            return 0;
+        };
+        let source_type = source.get("type").and_then(|t| t.as_str()).unwrap_or("");
+
+        match source_type {
+            // 纯文本文档
+            "text" => self.count_tokens(source.get("data").and_then(|d| d.as_str()).unwrap_or("")),
+            // 自定义内容文档
+            "content" => source.get("content").map_or(0, |content| self.count_content(content)),
+            // base64 PDF：按页数估算
+            "base64" => {
+                let pages = source.get("data")
+                    .and_then(|d| d.as_str())
+                    .and_then(decode_base64)
+                    .map(|bytes| count_pdf_pages(&bytes))
+                    .unwrap_or(1);
+                pages * PDF_PAGE_TOKENS
+            }
+            // URL 或 file_id 引用的 PDF 无法得知页数，按一页估算
+            _ => PDF_PAGE_TOKENS,
+        }
+    }
+
+    /// 计算 JSON 值序列化后的 tokens
+    fn count_json(&self, value: &Value) -> usize {
+        serde_json::to_string(value).map_or(0, |text| self.count_tokens(&text))
+    }
 }
 
 impl Default for TokenCounter {
@@ -73,6 +277,97 @@ impl Default for TokenCounter {
     }
 }
 
+/// 估算图片的 tokens：(宽 × 高) / 750，长边超过 1568 像素时先等比缩放
+///
+/// 无法解析尺寸（如 URL 图片）时按上限估算。
+fn estimate_image_tokens(source: Option<&Value>) -> usize {
+    let dimensions = source
+        .filter(|s| s.get("type").and_then(|t| t.as_str()) == Some("base64"))
+        .and_then(|s| s.get("data"))
+        .and_then(|d| d.as_str())
+        .and_then(decode_base64)
+        .and_then(|bytes| image_dimensions(&bytes));
+
+    match dimensions {
+        Some((width, height)) => image_tokens_for_dimensions(width, height),
+        None => IMAGE_MAX_TOKENS,
+    }
+}
+
+/// 根据图片尺寸计算 tokens
+fn image_tokens_for_dimensions(width: u32, height: u32) -> usize {
+    let (width, height) = (width as f64, height as f64);
+    let scale = (IMAGE_MAX_EDGE / width.max(height)).min(1.0);
+    let pixels = width * scale * height * scale;
+    ((pixels / IMAGE_PIXELS_PER_TOKEN).ceil() as usize).clamp(1, IMAGE_MAX_TOKENS)
+}
+
+/// 解码 base64 数据（兼容带换行的数据）
+fn decode_base64(data: &str) -> Option<Vec<u8>> {
+    let engine = base64::engine::general_purpose::STANDARD;
+    engine.decode(data).ok().or_else(|| {
+        let compact: String = data.chars().filter(|c| !c.is_whitespace()).collect();
+        engine.decode(compact).ok()
+    })
+}
+
+/// 从图片文件头解析尺寸（支持 PNG、JPEG、GIF、WebP）
+fn image_dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    let be16 = |i: usize| Some(u16::from_be_bytes([*bytes.get(i)?, *bytes.get(i + 1)?]) as u32);
+    let le16 = |i: usize| Some(u16::from_le_bytes([*bytes.get(i)?, *bytes.get(i + 1)?]) as u32);
+    let be32 = |i: usize| Some(u32::from_be_bytes(bytes.get(i..i + 4)?.try_into().ok()?));
+    let le24 = |i: usize| Some(u32::from_le_bytes([*bytes.get(i)?, *bytes.get(i + 1)?, *bytes.get(i + 2)?, 0]));
+
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        // PNG：IHDR 块紧跟在文件签名之后
+        return Some((be32(16)?, be32(20)?));
+    }
+
+    if bytes.starts_with(b"GIF8") {
+        return Some((le16(6)?, le16(8)?));
+    }
+
+    if bytes.starts_with(b"RIFF") && bytes.get(8..12) == Some(b"WEBP") {
+        return match bytes.get(12..16)? {
+            b"VP8 " => Some((le16(26)? & 0x3fff, le16(28)? & 0x3fff)),
+            b"VP8L" => {
+                let bits = u32::from_le_bytes(bytes.get(21..25)?.try_into().ok()?);
+                Some(((bits & 0x3fff) + 1, ((bits >> 14) & 0x3fff) + 1))
+            }
+            b"VP8X" => Some((le24(24)? + 1, le24(27)? + 1)),
+            _ => None,
+        };
+    }
+
+    if bytes.starts_with(&[0xff, 0xd8]) {
+        // JPEG：遍历段，找到 SOF 段读取尺寸
+        let mut i = 2;
+        while i + 9 < bytes.len() {
+            if bytes[i] != 0xff {
+                i += 1;
+                continue;
+            }
+            let marker = bytes[i + 1];
+            let is_sof = (0xc0..=0xcf).contains(&marker) && !matches!(marker, 0xc4 | 0xc8 | 0xcc);
+            if is_sof {
+                return Some((be16(i + 7)?, be16(i + 5)?));
+            }
+            i += 2 + be16(i + 2)? as usize;
+        }
+    }
+
+    None
+}
+
+/// 统计 PDF 页数（匹配 "/Type /Page" 对象，排除 "/Type /Pages"）
+fn count_pdf_pages(bytes: &[u8]) -> usize {
+    let text = String::from_utf8_lossy(bytes);
+    let pages = regex::Regex::new(r"/Type\s*/Page\b")
+        .map(|re| re.find_iter(&text).count())
+        .unwrap_or(0);
+    pages.max(1)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -97,4 +392,116 @@ mod tests {
         let count = counter.count_input_tokens(request_body);
         assert!(count > 0);
     }
+
+    #[test]
+    fn test_tokenizer_for_model() {
+        assert_eq!(Tokenizer::for_model("gpt-4o-mini"), Tokenizer::O200k);
+        assert_eq!(Tokenizer::for_model("o3-mini"), Tokenizer::O200k);
+        assert_eq!(Tokenizer::for_model("openai/gpt-4.1"), Tokenizer::O200k);
+        assert_eq!(Tokenizer::for_model("gpt-4-turbo"), Tokenizer::Cl100k);
+        assert_eq!(Tokenizer::for_model("claude-sonnet-4-20250514"), Tokenizer::ClaudeApprox);
+        assert_eq!(Tokenizer::for_model("anthropic/Claude-3.5-Haiku"), Tokenizer::ClaudeApprox);
+        assert_eq!(Tokenizer::for_model("glm-4.6"), Tokenizer::Cl100k);
+
+        let counter = TokenCounter::for_model("claude-opus-4").unwrap();
+        assert_eq!(counter.tokenizer(), Tokenizer::ClaudeApprox);
+        assert!(std::ptr::eq(counter, TokenCounter::for_model("claude-haiku-4").unwrap()));
+    }
+
+    #[test]
+    fn test_claude_approx_multiplier() {
+        let cl100k = TokenCounter::new().unwrap();
+        let claude = TokenCounter::with_tokenizer(Tokenizer::ClaudeApprox).unwrap();
+        let text = "fn main() { println!(\"Hello, world!\"); } // a short Rust program for testing";
+        let expected = (cl100k.count_tokens(text) as f64 * CLAUDE_TOKEN_MULTIPLIER).round() as usize;
+        assert_eq!(claude.count_tokens(text), expected);
+    }
+
+    #[test]
+    fn test_image_dimensions() {
+        // 1x1 PNG
+        let png = decode_base64("iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mNkYPhfDwAChwGA60e6kgAAAABJRU5ErkJggg==").unwrap();
+        assert_eq!(image_dimensions(&png), Some((1, 1)));
+
+        // GIF 头：宽 300、高 200
+        let gif = [b"GIF89a".as_slice(), &[0x2c, 0x01, 0xc8, 0x00]].concat();
+        assert_eq!(image_dimensions(&gif), Some((300, 200)));
+
+        assert_eq!(image_tokens_for_dimensions(1000, 1000), 1334);
+        // 超大图片先缩放，再受上限约束
+        assert_eq!(image_tokens_for_dimensions(4000, 3000), IMAGE_MAX_TOKENS);
+    }
+
+    #[test]
+    fn test_count_pdf_pages() {
+        let pdf = b"%PDF-1.4\n1 0 obj << /Type /Pages /Kids [2 0 R 3 0 R] >>\n2 0 obj << /Type /Page >>\n3 0 obj <</Type/Page>>";
+        assert_eq!(count_pdf_pages(pdf), 2);
+    }
+
+    /// Claude Code 请求样本目录（覆盖 tool_use/tool_result、thinking、图片和 PDF）
+    ///
+    /// 每个样本是 `{"upstream_input_tokens": N, "request": {...}}`：请求体及上游 count_tokens 接口返回的 token 数，
+    /// 用 `scripts/record-token-sample.sh` 录制；尚未录制的样本 upstream_input_tokens 为 null。
+    const CORPUS_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/proxy/testdata/claude_code");
+
+    /// 本地估算与上游计数之间允许的相对误差
+    const CORPUS_TOLERANCE: f64 = 0.15;
+
+    /// 读取全部样本，样本目录缺失或为空时测试失败
+    fn load_corpus() -> Vec<(std::path::PathBuf, Value)> {
+        let entries = std::fs::read_dir(CORPUS_DIR)
+            .unwrap_or_else(|e| panic!("Failed to read corpus directory {}: {}", CORPUS_DIR, e));
+        let samples: Vec<_> = entries
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().and_then(|ext| ext.to_str()) == Some("json"))
+            .map(|path| {
+                let sample = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+                (path, sample)
+            })
+            .collect();
+        assert!(!samples.is_empty(), "No .json samples in {}", CORPUS_DIR);
+        samples
+    }
+
+    #[test]
+    fn test_claude_code_corpus_is_counted() {
+        let counter = TokenCounter::with_tokenizer(Tokenizer::ClaudeApprox).unwrap();
+        for (path, sample) in load_corpus() {
+            assert!(sample["request"]["messages"].is_array(), "{}: missing request.messages", path.display());
+            assert!(counter.count_input_tokens(&sample["request"].to_string()) > 0, "{}", path.display());
+        }
+    }
+
+    #[test]
+    #[ignore = "样本的上游计数尚未录制：运行 scripts/record-token-sample.sh 后去掉 ignore"]
+    fn test_claude_code_corpus_matches_upstream() {
+        let counter = TokenCounter::with_tokenizer(Tokenizer::ClaudeApprox).unwrap();
+        for (path, sample) in load_corpus() {
+            let expected = sample["upstream_input_tokens"].as_i64()
+                .unwrap_or_else(|| panic!("{}: upstream_input_tokens not recorded", path.display())) as f64;
+            let actual = counter.count_input_tokens(&sample["request"].to_string()) as f64;
+
+            let error = (actual - expected).abs() / expected;
+            assert!(error <= CORPUS_TOLERANCE,
+                "{}: local estimate {} vs upstream {} ({:.1}% off)", path.display(), actual, expected, error * 100.0);
+        }
+    }
+
+    #[test]
+    fn test_every_block_type_is_counted() {
+        let counter = TokenCounter::new().unwrap();
+        let blocks = [
+            serde_json::json!({"type": "text", "text": "hello"}),
+            serde_json::json!({"type": "tool_use", "id": "toolu_1", "name": "Bash", "input": {"command": "ls"}}),
+            serde_json::json!({"type": "tool_result", "tool_use_id": "toolu_1", "content": [{"type": "text", "text": "src"}]}),
+            serde_json::json!({"type": "thinking", "thinking": "Let me check", "signature": "sig"}),
+            serde_json::json!({"type": "redacted_thinking", "data": "EmwKAhgBEgy3va3pzix/LafPsn4aDFIT"}),
+            serde_json::json!({"type": "image", "source": {"type": "url", "url": "https://example.com/a.png"}}),
+            serde_json::json!({"type": "document", "source": {"type": "text", "media_type": "text/plain", "data": "notes"}}),
+            serde_json::json!({"type": "web_search_tool_result", "tool_use_id": "srvtoolu_1", "content": []}),
+        ];
+        for block in blocks {
+            assert!(counter.count_block(&block) > BLOCK_OVERHEAD_TOKENS, "block not counted: {}", block);
+        }
+    }
 }
@@ -62,7 +62,8 @@ fn build_tray_menu<R: Runtime>(
 
     // 获取当前服务状态
     let active_profile = config_guard.get_active_profile();
-    let status_text = if active_profile.is_some() {
+    let active_pool = config_guard.get_active_pool();
+    let status_text = if active_profile.is_some() || active_pool.is_some() {
         "🟢 代理服务运行中"
     } else {
         "⚪ 代理服务未激活"
@@ -99,6 +100,33 @@ fn build_tray_menu<R: Runtime>(
         }
     }
 
+    // 构建负载均衡池子菜单（菜单 ID 使用 "pool:" 前缀与配置档案区分）
+    let mut pools = config_guard.list_pools();
+    pools.sort_by(|a, b| a.name.cmp(&b.name));
+    let mut pool_submenu = SubmenuBuilder::new(app, "负载均衡池");
+
+    if pools.is_empty() {
+        let empty_item = MenuItemBuilder::new("(无负载均衡池)")
+            .enabled(false)
+            .build(app)?;
+        pool_submenu = pool_submenu.item(&empty_item);
+    } else {
+        for pool in pools {
+            let display_name = if pool.is_active {
+                format!("✓ {}", pool.name)
+            } else {
+                pool.name.clone()
+            };
+
+            let item = MenuItemBuilder::new(&display_name)
+                .id(format!("pool:{}", pool.id))
+                .enabled(true)
+                .build(app)?;
+
+            pool_submenu = pool_submenu.item(&item);
+        }
+    }
+
     // 构建主菜单
     let status_item = MenuItemBuilder::new(status_text)
         .id("status")
@@ -115,6 +143,7 @@ fn build_tray_menu<R: Runtime>(
         .item(&status_item)
         .separator()
         .item(&profile_submenu.build()?)
+        .item(&pool_submenu.build()?)
         .separator()
         .item(&show_window_item)
         .item(&quit_item)
@@ -143,6 +172,40 @@ fn handle_tray_menu_event<R: Runtime>(
         "status" => {
             // 状态项不可点击，忽略
         }
+        pool_menu_id if pool_menu_id.starts_with("pool:") => {
+            // 切换负载均衡池
+            let pool_id = &pool_menu_id["pool:".len()..];
+            log::info!("Switching to pool: {}", pool_id);
+
+            let result = {
+                let mut config_guard = match config.write() {
+                    Ok(guard) => guard,
+                    Err(e) => {
+                        log::error!("Failed to acquire config write lock: {}", e);
+                        return;
+                    }
+                };
+
+                // 激活负载均衡池
+                if let Err(e) = config_guard.activate_pool(pool_id) {
+                    log::error!("Failed to activate pool: {}", e);
+                    return;
+                }
+
+                // 保存配置
+                config_guard.save_to_file(&get_config_path())
+            };
+
+            if let Err(e) = result {
+                log::error!("Failed to save config: {}", e);
+                return;
+            }
+
+            // 重建托盘菜单
+            if let Err(e) = rebuild_tray_menu(app, &config) {
+                log::error!("Failed to rebuild tray menu: {}", e);
+            }
+        }
         profile_id => {
             // 切换配置
             log::info!("Switching to profile: {}", profile_id);
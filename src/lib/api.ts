@@ -145,6 +145,18 @@ export interface DashboardStats {
   todayTokens: number
   totalRequests: number
   totalTokens: number
+  // 其中由本地分词器估算（上游未返回 usage）的 token 数
+  todayEstimatedTokens: number
+  totalEstimatedTokens: number
+  // 按价格表计算的成本（美元）
+  todayCost: number
+  totalCost: number
+  // 失败的请求数（包括鉴权失败、无可用配置、连接上游失败等未到达上游的请求）
+  todayErrors: number
+  totalErrors: number
+  // 响应缓存命中率（0-1）
+  todayCacheHitRate: number
+  totalCacheHitRate: number
 }
 
 // 获取仪表盘统计数据
@@ -165,6 +177,7 @@ export interface TokenDataPoint {
   label: string
   tokens: number
   cacheReadTokens: number  // 缓存命中的 token 数
+  cost: number  // 成本（美元）
 }
 
 export type TimeRange = 'hour' | 'day' | 'week' | 'month'
@@ -174,6 +187,9 @@ export interface ProfileConsumption {
   profileId: string
   profileName: string
   totalTokens: number
+  totalCost: number  // 成本（美元）
+  totalRequests: number
+  totalRetries: number  // 重试次数
   percentage: number
   rank: number
 }
@@ -303,10 +319,30 @@ export async function updateTrayMenu(): Promise<void> {
 
 // ==================== 代理服务器配置相关接口 ====================
 
+// 访问上游的网络设置（出站代理和 TLS）
+export interface UpstreamNetwork {
+  proxyUrl?: string | null
+  proxyUsername?: string | null
+  proxyPassword?: string | null
+  noProxy?: string | null
+  caCertPath?: string | null
+  tlsSkipVerify?: boolean
+}
+
 // 代理服务器配置接口
 export interface ProxyConfig {
   host: string
   port: number
+  upstreamNetwork?: UpstreamNetwork
+  responseCache?: ResponseCacheConfig
+}
+
+// 响应缓存配置（相同的请求直接返回缓存的响应，流式请求回放缓存的 SSE 事件）
+export interface ResponseCacheConfig {
+  enabled: boolean
+  ttlSecs: number
+  maxSizeMb: number
+  replayDelayMs: number
 }
 
 // 代理服务器状态接口
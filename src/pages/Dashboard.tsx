@@ -16,6 +16,14 @@ export function Dashboard() {
     todayTokens: 0,
     totalRequests: 0,
     totalTokens: 0,
+    todayEstimatedTokens: 0,
+    totalEstimatedTokens: 0,
+    todayCost: 0,
+    totalCost: 0,
+    todayErrors: 0,
+    totalErrors: 0,
+    todayCacheHitRate: 0,
+    totalCacheHitRate: 0,
   })
   const [timeRange, setTimeRange] = useState<api.TimeRange>('hour')
   const [tokenData, setTokenData] = useState<api.TokenDataPoint[]>([])
@@ -221,6 +229,11 @@ export function Dashboard() {
             <div>
               <p className="text-xs text-gray-500 dark:text-gray-400 mb-0.5">{t('stats.todayRequests')}</p>
               <p className="text-2xl font-bold text-gray-900 dark:text-white">{formatNumber(stats.todayRequests)}</p>
+              {stats.todayCacheHitRate > 0 && (
+                <p className="text-xs text-gray-500 dark:text-gray-400 mt-0.5">
+                  {t('stats.cacheHitRate', { rate: (stats.todayCacheHitRate * 100).toFixed(1) })}
+                </p>
+              )}
             </div>
             <div className="w-10 h-10 bg-purple-100 dark:bg-purple-900/30 rounded-lg flex items-center justify-center">
               <svg className="w-5 h-5 text-purple-600 dark:text-purple-400" fill="none" stroke="currentColor" viewBox="0 0 24 24">
@@ -12,6 +12,7 @@ export function Settings() {
   const [saving, setSaving] = useState(false)
   const [message, setMessage] = useState<{ type: 'success' | 'error', text: string } | null>(null)
   const [showUpdateDialog, setShowUpdateDialog] = useState(false)
+  const responseCache = proxyConfig.responseCache ?? { enabled: false, ttlSecs: 600, maxSizeMb: 64, replayDelayMs: 0 }
 
   // 加载配置和状态
   useEffect(() => {
@@ -117,6 +118,64 @@ export function Settings() {
                     />
                   </div>
                 </div>
+                <div className="p-4 bg-gray-50 dark:bg-gray-900 rounded-lg space-y-3">
+                  <div className="flex items-center justify-between">
+                    <div>
+                      <div className="text-sm font-medium text-gray-900 dark:text-white">{t('proxyService.responseCache')}</div>
+                      <div className="text-xs text-gray-500 dark:text-gray-400 mt-1">{t('proxyService.responseCacheDesc')}</div>
+                    </div>
+                    <button
+                      onClick={() => setProxyConfigState({ ...proxyConfig, responseCache: { ...responseCache, enabled: !responseCache.enabled } })}
+                      className={`relative inline-flex h-6 w-11 items-center rounded-full transition-colors ${
+                        responseCache.enabled ? 'bg-blue-600 dark:bg-blue-500' : 'bg-gray-200 dark:bg-gray-700'
+                      }`}
+                    >
+                      <span className={`inline-block h-4 w-4 transform rounded-full bg-white transition ${
+                        responseCache.enabled ? 'translate-x-6' : 'translate-x-1'
+                      }`} />
+                    </button>
+                  </div>
+                  {responseCache.enabled && (
+                    <div className="grid grid-cols-3 gap-4">
+                      <div>
+                        <label className="block text-xs font-medium text-gray-700 dark:text-gray-300 mb-1">
+                          {t('proxyService.cacheTtl')}
+                        </label>
+                        <input
+                          type="number"
+                          min={1}
+                          value={responseCache.ttlSecs}
+                          onChange={(e) => setProxyConfigState({ ...proxyConfig, responseCache: { ...responseCache, ttlSecs: parseInt(e.target.value) || 600 } })}
+                          className="w-full px-3 py-1.5 border border-gray-300 dark:border-gray-600 rounded-lg bg-white dark:bg-gray-800 text-gray-900 dark:text-gray-100 focus:outline-none focus:ring-2 focus:ring-blue-500"
+                        />
+                      </div>
+                      <div>
+                        <label className="block text-xs font-medium text-gray-700 dark:text-gray-300 mb-1">
+                          {t('proxyService.cacheMaxSize')}
+                        </label>
+                        <input
+                          type="number"
+                          min={1}
+                          value={responseCache.maxSizeMb}
+                          onChange={(e) => setProxyConfigState({ ...proxyConfig, responseCache: { ...responseCache, maxSizeMb: parseInt(e.target.value) || 64 } })}
+                          className="w-full px-3 py-1.5 border border-gray-300 dark:border-gray-600 rounded-lg bg-white dark:bg-gray-800 text-gray-900 dark:text-gray-100 focus:outline-none focus:ring-2 focus:ring-blue-500"
+                        />
+                      </div>
+                      <div>
+                        <label className="block text-xs font-medium text-gray-700 dark:text-gray-300 mb-1">
+                          {t('proxyService.cacheReplayDelay')}
+                        </label>
+                        <input
+                          type="number"
+                          min={0}
+                          value={responseCache.replayDelayMs}
+                          onChange={(e) => setProxyConfigState({ ...proxyConfig, responseCache: { ...responseCache, replayDelayMs: parseInt(e.target.value) || 0 } })}
+                          className="w-full px-3 py-1.5 border border-gray-300 dark:border-gray-600 rounded-lg bg-white dark:bg-gray-800 text-gray-900 dark:text-gray-100 focus:outline-none focus:ring-2 focus:ring-blue-500"
+                        />
+                      </div>
+                    </div>
+                  )}
+                </div>
                 <div className="flex items-center justify-between p-4 bg-gray-50 dark:bg-gray-900 rounded-lg">
                   <div>
                     <div className="text-sm font-medium text-gray-900 dark:text-white">{t('proxyService.serviceStatus')}</div>